@@ -16,11 +16,14 @@ pub use crate::coin::{Cw20Coin, Cw20CoinVerified};
 pub use crate::denom::Denom;
 pub use crate::helpers::Cw20Contract;
 pub use crate::logo::{EmbeddedLogo, Logo, LogoInfo};
-pub use crate::msg::Cw20ExecuteMsg;
+pub use crate::msg::{ClaimExit, Cw20ExecuteMsg, KeeperConfig};
 pub use crate::query::{
     AllAccountsResponse, AllAllowancesResponse, AllSpenderAllowancesResponse, AllowanceInfo,
     AllowanceResponse, BalanceResponse, Cw20QueryMsg, DownloadLogoResponse, MarketingInfoResponse,
-    MinterResponse, SpenderAllowanceInfo, TokenInfoResponse,PoolInfoResponse
+    MinterResponse, SpenderAllowanceInfo, TokenInfoResponse,PoolInfoResponse, RolesResponse,
+    RouteInfo, RoutesResponse, PendingUnbondsResponse, LockInfo, LocksResponse, SimulateJoinResponse,
+    TvlResponse, SharePriceResponse, ApyResponse, HarvestInfo, HarvestHistoryResponse,
+    KeeperResponse, NextCompoundEpochResponse, EmergencyExitInfo, VaultStatusResponse
 };
 pub use crate::receiver::Cw20ReceiveMsg;
 pub use crate::receiver::AddBondReceiveMsg;
@@ -3,6 +3,7 @@ use crate::receiver::Pair;
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Binary, Uint128};
 use osmosis_std::{types::cosmos::base::v1beta1::Coin, shim::Duration};
+use osmosis_std::types::osmosis::gamm::v1beta1::SwapAmountInRoute;
 use cw_utils::Expiration;
 
 
@@ -12,9 +13,11 @@ use cw_utils::Expiration;
 pub enum Cw20ExecuteMsg {
     // [Option<Pair>, Option<Pair>]
 
+    /// Joins pool `pool_id`, which has to be the vault pool, at the pool ratio with the
+    /// pool assets the vault holds, each capped by its amount in `token_in_maxs` if listed
+    /// there. The share amount is the most the pool reserves allow.
     JoinPool{
         pool_id: u64,
-        amount: String,
         token_in_maxs: Vec<Coin>
     },
     
@@ -26,14 +29,87 @@ pub enum Cw20ExecuteMsg {
 
     UnbondAll{ },
 
-    ConvertRewards{ },
+    /// Swaps whitelisted rewards along their routes. Every swap must return at least its
+    /// TWAP value minus `max_slippage_bps`, which can only tighten the vault setting.
+    ConvertRewards{ max_slippage_bps: Option<u64> },
 
-    Unbond{ id: u64, },
+    /// Begins unlocking `coins` of lock `id`, or the whole lock when `coins` is empty.
+    Unbond{ id: u64, #[serde(default)] coins: Vec<cosmwasm_std::Coin> },
 
     WithdrawTokens{ to_address: String, tokens: Vec<cosmwasm_std::Coin>},
 
     UpdateWhiteList{ coins: Vec<String>},
 
+    /// Only with the owner role. Sets the gamm pools `denom` rewards are swapped
+    /// through by `ConvertRewards`. The last hop must end in `denom_1` or `denom_2`.
+    SetRoute { denom: String, route: Vec<SwapAmountInRoute> },
+    /// Only with the owner role. Stops converting `denom` rewards.
+    RemoveRoute { denom: String },
+    /// Only with the owner role. Sets the default max slippage of reward swaps, in basis points.
+    UpdateMaxSlippage { max_slippage_bps: u64 },
+    /// Only with the owner role. Sets how long `Compound` locks the LP shares it mints.
+    UpdateLockDuration { seconds: u64 },
+
+    /// Runs a full harvest: swaps the routed rewards into the pool assets, joins the pool
+    /// with everything the vault holds of them and locks the LP shares it got back.
+    /// In superfluid mode the uosmo staking rewards the vault got since the last harvest
+    /// pay the fee and bounty like other rewards, and the new LP is superfluid delegated.
+    /// With a keeper config anyone can run it once the interval since the last harvest
+    /// has passed, for a bounty out of the rewards. Runs once per incentives epoch.
+    Compound {},
+    /// Only with the owner role. Opens `Compound` to keepers, `None` restricts it to the
+    /// owner and operator again.
+    UpdateKeeper { keeper: Option<KeeperConfig> },
+
+    /// Only with the owner role. Proposes a new owner, who has to confirm
+    /// with `AcceptOwnership` before the transfer takes effect. `None` cancels
+    /// the transfer in progress.
+    UpdateOwner { owner: Option<String> },
+    /// Completes an ownership transfer started by `UpdateOwner`.
+    AcceptOwnership {},
+    /// Only with the owner role. Sets (or clears) the operator allowed to run
+    /// `JoinPool`, `AddBond`, `Unbond`, `UnbondAll`, `ConvertRewards` and `Compound`.
+    UpdateOperator { operator: Option<String> },
+    /// Only with the owner role. Restricts `Deposit` to a single address,
+    /// `None` opens deposits to everyone.
+    UpdateDepositor { depositor: Option<String> },
+    /// Only with the owner role. Sets (or clears) the guardian allowed to `Pause` the vault.
+    UpdateGuardian { guardian: Option<String> },
+
+    /// Only with the owner or guardian role. Stops deposits, compounding and cw20
+    /// transfers of vault tokens until the owner calls `Unpause`.
+    Pause {},
+    /// Only with the owner role. Lifts a `Pause`.
+    Unpause {},
+    /// Only with the owner role for the first call, which starts unlocking every lock
+    /// and switches the vault to withdraw-only. Once the unlock is over anyone can call
+    /// it again to exit the pool, `Withdraw` then pays out the pool assets pro-rata.
+    EmergencyExit {},
+
+    /// Deposits the pool LP shares sent along with the message into the vault and
+    /// mints vault tokens to the sender, priced against the LP the vault already holds.
+    Deposit {},
+    /// Joins the pool single sided with the one pool asset sent along with the message
+    /// and mints vault tokens to the sender for the LP shares it gets.
+    DepositSingle {},
+    /// Swaps half of the one pool asset sent along with the message into the other
+    /// pool asset and joins the pool with both, refunding what the join leaves.
+    Zap {},
+    /// Burns `shares` vault tokens of the sender and pays out the matching
+    /// amount of pool LP shares from the vault's liquid balance, or its share
+    /// of the pool assets after an `EmergencyExit` left the pool.
+    Withdraw { shares: Uint128 },
+    /// Burns `shares` vault tokens of the sender and starts unlocking the LP shares they
+    /// are worth. The LP can be claimed with `Claim` once the unlock is over.
+    RequestUnbond { shares: Uint128 },
+    /// Exits the pool with the LP of all the matured unbond claims of the sender
+    /// and sends the pool assets to the sender. Exits to both assets without a
+    /// minimum output unless `exit` says otherwise.
+    Claim {
+        #[serde(default)]
+        exit: Option<ClaimExit>,
+    },
+
     /// Transfer is a base message to move tokens to another account without triggering actions
     Transfer { recipient: String, amount: Uint128 },
     /// Burn is a base message to destroy tokens forever
@@ -78,12 +154,9 @@ pub enum Cw20ExecuteMsg {
     },
     /// Only with "approval" extension. Destroys tokens forever
     BurnFrom { owner: String, amount: Uint128 },
-    /// Only with the "mintable" extension. If authorized, creates amount new tokens
-    /// and adds to the recipient balance.
+    /// Always rejected by the vault, which only mints shares for deposited LP.
     Mint { recipient: String, amount: Uint128 },
-    /// Only with the "mintable" extension. The current minter may set
-    /// a new minter. Setting the minter to None will remove the
-    /// token's minter forever.
+    /// Always rejected by the vault, which has no minter.
     UpdateMinter { new_minter: Option<String> },
     /// Only with the "marketing" extension. If authorized, updates marketing metadata.
     /// Setting None/null for any of these will leave it unchanged.
@@ -99,3 +172,26 @@ pub enum Cw20ExecuteMsg {
     /// If set as the "marketing" role on the contract, upload a new URL, SVG, or PNG for the token
     UploadLogo(Logo),
 }
+
+/// Terms of permissionless `Compound` calls.
+#[cw_serde]
+pub struct KeeperConfig {
+    /// Minimum time or blocks between two harvests
+    pub interval: cw_utils::Duration,
+    /// Share of the rewards, net of the performance fee, paid to the caller
+    pub bounty: cosmwasm_std::Decimal,
+    /// A keeper harvest has to take at least this much of one reward denom,
+    /// rewards not listed here don't count. Empty for no minimum
+    pub min_rewards: Vec<cosmwasm_std::Coin>,
+}
+
+/// How a `Claim` takes its LP shares out of the pool.
+#[cw_serde]
+pub enum ClaimExit {
+    /// Both pool assets in the pool ratio, each at least its amount in `token_out_mins`
+    Proportional {
+        token_out_mins: Vec<cosmwasm_std::Coin>,
+    },
+    /// Only `denom`, the other pool asset swapped into it inside the pool
+    SingleDenom { denom: String, min_out: Uint128 },
+}
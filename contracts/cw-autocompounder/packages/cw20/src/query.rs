@@ -2,10 +2,11 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Binary, Uint128};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Timestamp, Uint128};
 
 use crate::logo::LogoInfo;
 use cw_utils::Expiration;
+use osmosis_std::types::osmosis::gamm::v1beta1::SwapAmountInRoute;
 
 #[cw_serde]
 
@@ -71,6 +72,28 @@ pub struct PoolInfoResponse {
     pub denom_1: String,
     pub denom_2: String,
     pub white_list_denoms: Vec<String>,
+    pub lp_denom: String,
+}
+
+#[cw_serde]
+pub struct RouteInfo {
+    pub denom: String,
+    pub route: Vec<SwapAmountInRoute>,
+}
+
+#[cw_serde]
+pub struct RoutesResponse {
+    pub routes: Vec<RouteInfo>,
+}
+
+#[cw_serde]
+pub struct RolesResponse {
+    pub owner: Option<String>,
+    pub pending_owner: Option<String>,
+    pub operator: Option<String>,
+    pub depositor: Option<String>,
+    #[serde(default)]
+    pub guardian: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug, Default)]
@@ -136,3 +159,123 @@ pub struct AllSpenderAllowancesResponse {
 pub struct AllAccountsResponse {
     pub accounts: Vec<String>,
 }
+
+#[cw_serde]
+pub struct PendingUnbondsResponse {
+    /// LP shares owed to unbond claims that weren't paid out yet
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct LockInfo {
+    /// Id of the lock in the lockup module
+    pub id: u64,
+    pub duration_seconds: u64,
+    pub coins: Vec<Coin>,
+    pub start_time: Timestamp,
+}
+
+#[cw_serde]
+pub struct LocksResponse {
+    pub locks: Vec<LockInfo>,
+}
+
+#[cw_serde]
+pub struct SimulateJoinResponse {
+    /// LP shares the join mints
+    pub share_out_amount: Uint128,
+    /// Amount of every pool asset the join takes
+    pub token_in: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct TvlResponse {
+    /// LP shares held in the vault's bank balance
+    pub liquid_lp: Uint128,
+    /// LP shares locked (or unlocking) in the lockup module
+    pub locked_lp: Uint128,
+    /// LP shares owed to unbond claims, not part of the TVL
+    pub pending_lp: Uint128,
+    /// Pool assets the LP shares backing the vault tokens redeem for
+    pub assets: Vec<Coin>,
+    pub quote_denom: String,
+    /// Value of `assets` in `quote_denom` at the current spot price
+    pub value: Uint128,
+}
+
+#[cw_serde]
+pub struct SharePriceResponse {
+    /// LP shares backing one vault token
+    pub share_price: Decimal,
+    pub total_lp: Uint128,
+    pub total_supply: Uint128,
+}
+
+#[cw_serde]
+pub struct ApyResponse {
+    /// Share price growth since `since`, compounded over a year
+    pub apy: Decimal,
+    /// Time of the harvest the growth is measured from, unset without history
+    pub since: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct HarvestInfo {
+    /// Sequence number of the harvest, starting at 1
+    pub id: u64,
+    pub height: u64,
+    pub time: Timestamp,
+    /// Whitelisted rewards taken by the harvest, performance fee included
+    pub rewards: Vec<Coin>,
+    /// Performance fee sent to the fee collector
+    pub fees: Vec<Coin>,
+    /// Bounty paid to the keeper who ran the harvest
+    #[serde(default)]
+    pub bounty: Vec<Coin>,
+    /// Output of every reward swap
+    pub swap_outputs: Vec<Coin>,
+    /// LP shares the harvest added to the vault
+    pub lp_added: Uint128,
+    /// LP shares backing one vault token after the harvest, unset without vault tokens
+    pub share_price: Option<Decimal>,
+}
+
+#[cw_serde]
+pub struct HarvestHistoryResponse {
+    pub harvests: Vec<HarvestInfo>,
+}
+
+#[cw_serde]
+pub struct KeeperResponse {
+    /// Unset while only the owner and operator can compound
+    pub keeper: Option<crate::KeeperConfig>,
+    /// When keepers can compound next, unset before the first harvest
+    pub next_compound: Option<Expiration>,
+}
+
+#[cw_serde]
+pub struct NextCompoundEpochResponse {
+    /// Epoch the vault compounds once in
+    pub identifier: String,
+    pub current_epoch: u64,
+    /// Epoch of the last `Compound`, unset before the first one
+    pub last_compound_epoch: Option<u64>,
+    /// First epoch the vault can compound in, the current one if it didn't yet
+    pub next_epoch: u64,
+    /// Start of `next_epoch`, right after the incentives of the one before were paid
+    pub starts_at: Timestamp,
+}
+
+#[cw_serde]
+pub struct EmergencyExitInfo {
+    pub started_at: Timestamp,
+    /// Pool assets left for the vault token holders, unset until the vault exited the pool
+    pub assets: Option<Vec<Coin>>,
+}
+
+#[cw_serde]
+pub struct VaultStatusResponse {
+    pub paused: bool,
+    /// Set once an `EmergencyExit` started, the vault is withdraw-only from then on
+    pub emergency_exit: Option<EmergencyExitInfo>,
+}
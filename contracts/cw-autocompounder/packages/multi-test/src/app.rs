@@ -18,6 +18,7 @@ use crate::contracts::Contract;
 use crate::executor::{AppResponse, Executor};
 use crate::module::{FailingModule, Module};
 use crate::staking::{Distribution, DistributionKeeper, StakeKeeper, Staking, StakingSudo};
+use crate::stargate::{Stargate, StargateFailing, StargateMsg, StargateQuery};
 use crate::transactions::transactional;
 use crate::wasm::{ContractData, Wasm, WasmKeeper, WasmSudo};
 
@@ -35,6 +36,7 @@ pub type BasicApp<ExecC = Empty, QueryC = Empty> = App<
     WasmKeeper<ExecC, QueryC>,
     StakeKeeper,
     DistributionKeeper,
+    StargateFailing,
 >;
 
 /// Router is a persisted state. You can query this.
@@ -48,15 +50,16 @@ pub struct App<
     Wasm = WasmKeeper<Empty, Empty>,
     Staking = StakeKeeper,
     Distr = DistributionKeeper,
+    Stargate = StargateFailing,
 > {
-    router: Router<Bank, Custom, Wasm, Staking, Distr>,
+    router: Router<Bank, Custom, Wasm, Staking, Distr, Stargate>,
     api: Api,
     storage: Storage,
     block: BlockInfo,
 }
 
-fn no_init<BankT, CustomT, WasmT, StakingT, DistrT>(
-    _: &mut Router<BankT, CustomT, WasmT, StakingT, DistrT>,
+fn no_init<BankT, CustomT, WasmT, StakingT, DistrT, StargateT>(
+    _: &mut Router<BankT, CustomT, WasmT, StakingT, DistrT, StargateT>,
     _: &dyn Api,
     _: &mut dyn Storage,
 ) {
@@ -79,6 +82,7 @@ impl BasicApp {
                 WasmKeeper<Empty, Empty>,
                 StakeKeeper,
                 DistributionKeeper,
+                StargateFailing,
             >,
             &dyn Api,
             &mut dyn Storage,
@@ -101,6 +105,7 @@ where
             WasmKeeper<ExecC, QueryC>,
             StakeKeeper,
             DistributionKeeper,
+            StargateFailing,
         >,
         &dyn Api,
         &mut dyn Storage,
@@ -109,8 +114,8 @@ where
     AppBuilder::new_custom().build(init_fn)
 }
 
-impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT> Querier
-    for App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT> Querier
+    for App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT>
 where
     CustomT::ExecT: Clone + fmt::Debug + PartialEq + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
@@ -121,6 +126,7 @@ where
     CustomT: Module,
     StakingT: Staking,
     DistrT: Distribution,
+    StargateT: Stargate,
 {
     fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
         self.router
@@ -129,8 +135,8 @@ where
     }
 }
 
-impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT> Executor<CustomT::ExecT>
-    for App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT> Executor<CustomT::ExecT>
+    for App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT>
 where
     CustomT::ExecT: Clone + fmt::Debug + PartialEq + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
@@ -141,6 +147,7 @@ where
     CustomT: Module,
     StakingT: Staking,
     DistrT: Distribution,
+    StargateT: Stargate,
 {
     fn execute(
         &mut self,
@@ -163,10 +170,11 @@ pub type BasicAppBuilder<ExecC, QueryC> = AppBuilder<
     WasmKeeper<ExecC, QueryC>,
     StakeKeeper,
     DistributionKeeper,
+    StargateFailing,
 >;
 
 /// Utility to build App in stages. If particular items wont be set, defaults would be used
-pub struct AppBuilder<Bank, Api, Storage, Custom, Wasm, Staking, Distr> {
+pub struct AppBuilder<Bank, Api, Storage, Custom, Wasm, Staking, Distr, Stargate> {
     api: Api,
     block: BlockInfo,
     storage: Storage,
@@ -175,6 +183,7 @@ pub struct AppBuilder<Bank, Api, Storage, Custom, Wasm, Staking, Distr> {
     custom: Custom,
     staking: Staking,
     distribution: Distr,
+    stargate: Stargate,
 }
 
 impl Default
@@ -186,6 +195,7 @@ impl Default
         WasmKeeper<Empty, Empty>,
         StakeKeeper,
         DistributionKeeper,
+        StargateFailing,
     >
 {
     fn default() -> Self {
@@ -202,6 +212,7 @@ impl
         WasmKeeper<Empty, Empty>,
         StakeKeeper,
         DistributionKeeper,
+        StargateFailing,
     >
 {
     /// Creates builder with default components working with empty exec and query messages.
@@ -215,6 +226,7 @@ impl
             custom: FailingModule::new(),
             staking: StakeKeeper::new(),
             distribution: DistributionKeeper::new(),
+            stargate: StargateFailing::new(),
         }
     }
 }
@@ -228,6 +240,7 @@ impl<ExecC, QueryC>
         WasmKeeper<ExecC, QueryC>,
         StakeKeeper,
         DistributionKeeper,
+        StargateFailing,
     >
 where
     ExecC: Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
@@ -245,12 +258,13 @@ where
             custom: FailingModule::new(),
             staking: StakeKeeper::new(),
             distribution: DistributionKeeper::new(),
+            stargate: StargateFailing::new(),
         }
     }
 }
 
-impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
-    AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT>
+    AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT>
 {
     /// Overwrites default wasm executor.
     ///
@@ -264,7 +278,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_wasm<C: Module, NewWasm: Wasm<C::ExecT, C::QueryT>>(
         self,
         wasm: NewWasm,
-    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, NewWasm, StakingT, DistrT> {
+    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, NewWasm, StakingT, DistrT, StargateT> {
         let AppBuilder {
             bank,
             api,
@@ -273,6 +287,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             staking,
             distribution,
+            stargate,
             ..
         } = self;
 
@@ -285,6 +300,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            stargate,
         }
     }
 
@@ -292,7 +308,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_bank<NewBank: Bank>(
         self,
         bank: NewBank,
-    ) -> AppBuilder<NewBank, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT> {
+    ) -> AppBuilder<NewBank, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT> {
         let AppBuilder {
             wasm,
             api,
@@ -301,6 +317,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             staking,
             distribution,
+            stargate,
             ..
         } = self;
 
@@ -313,6 +330,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            stargate,
         }
     }
 
@@ -320,7 +338,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_api<NewApi: Api>(
         self,
         api: NewApi,
-    ) -> AppBuilder<BankT, NewApi, StorageT, CustomT, WasmT, StakingT, DistrT> {
+    ) -> AppBuilder<BankT, NewApi, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT> {
         let AppBuilder {
             wasm,
             bank,
@@ -329,6 +347,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             staking,
             distribution,
+            stargate,
             ..
         } = self;
 
@@ -341,6 +360,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            stargate,
         }
     }
 
@@ -348,7 +368,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_storage<NewStorage: Storage>(
         self,
         storage: NewStorage,
-    ) -> AppBuilder<BankT, ApiT, NewStorage, CustomT, WasmT, StakingT, DistrT> {
+    ) -> AppBuilder<BankT, ApiT, NewStorage, CustomT, WasmT, StakingT, DistrT, StargateT> {
         let AppBuilder {
             wasm,
             api,
@@ -357,6 +377,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             staking,
             distribution,
+            stargate,
             ..
         } = self;
 
@@ -369,6 +390,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            stargate,
         }
     }
 
@@ -384,7 +406,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_custom<NewCustom: Module>(
         self,
         custom: NewCustom,
-    ) -> AppBuilder<BankT, ApiT, StorageT, NewCustom, WasmT, StakingT, DistrT> {
+    ) -> AppBuilder<BankT, ApiT, StorageT, NewCustom, WasmT, StakingT, DistrT, StargateT> {
         let AppBuilder {
             wasm,
             bank,
@@ -393,6 +415,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             staking,
             distribution,
+            stargate,
             ..
         } = self;
 
@@ -405,6 +428,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            stargate,
         }
     }
 
@@ -412,7 +436,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_staking<NewStaking: Staking>(
         self,
         staking: NewStaking,
-    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, NewStaking, DistrT> {
+    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, NewStaking, DistrT, StargateT> {
         let AppBuilder {
             wasm,
             api,
@@ -421,6 +445,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             bank,
             distribution,
+            stargate,
             ..
         } = self;
 
@@ -433,6 +458,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            stargate,
         }
     }
 
@@ -440,7 +466,39 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_distribution<NewDistribution: Distribution>(
         self,
         distribution: NewDistribution,
-    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, NewDistribution> {
+    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, NewDistribution, StargateT>
+    {
+        let AppBuilder {
+            wasm,
+            api,
+            storage,
+            custom,
+            block,
+            staking,
+            bank,
+            stargate,
+            ..
+        } = self;
+
+        AppBuilder {
+            api,
+            block,
+            storage,
+            bank,
+            wasm,
+            custom,
+            staking,
+            distribution,
+            stargate,
+        }
+    }
+
+    /// Overwrites default stargate interface, which answers `CosmosMsg::Stargate` and
+    /// `QueryRequest::Stargate` by their `type_url` or `path`
+    pub fn with_stargate<NewStargate: Stargate>(
+        self,
+        stargate: NewStargate,
+    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, NewStargate> {
         let AppBuilder {
             wasm,
             api,
@@ -449,6 +507,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             staking,
             bank,
+            distribution,
             ..
         } = self;
 
@@ -461,6 +520,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            stargate,
         }
     }
 
@@ -476,7 +536,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn build<F>(
         self,
         init_fn: F,
-    ) -> App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+    ) -> App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT>
     where
         BankT: Bank,
         ApiT: Api,
@@ -485,7 +545,12 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
         WasmT: Wasm<CustomT::ExecT, CustomT::QueryT>,
         StakingT: Staking,
         DistrT: Distribution,
-        F: FnOnce(&mut Router<BankT, CustomT, WasmT, StakingT, DistrT>, &dyn Api, &mut dyn Storage),
+        StargateT: Stargate,
+        F: FnOnce(
+            &mut Router<BankT, CustomT, WasmT, StakingT, DistrT, StargateT>,
+            &dyn Api,
+            &mut dyn Storage,
+        ),
     {
         let router = Router {
             wasm: self.wasm,
@@ -493,6 +558,7 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom: self.custom,
             staking: self.staking,
             distribution: self.distribution,
+            stargate: self.stargate,
         };
 
         let mut app = App {
@@ -506,8 +572,8 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     }
 }
 
-impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
-    App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT>
+    App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT>
 where
     WasmT: Wasm<CustomT::ExecT, CustomT::QueryT>,
     BankT: Bank,
@@ -516,11 +582,12 @@ where
     CustomT: Module,
     StakingT: Staking,
     DistrT: Distribution,
+    StargateT: Stargate,
 {
     pub fn init_modules<F, T>(&mut self, init_fn: F) -> T
     where
         F: FnOnce(
-            &mut Router<BankT, CustomT, WasmT, StakingT, DistrT>,
+            &mut Router<BankT, CustomT, WasmT, StakingT, DistrT, StargateT>,
             &dyn Api,
             &mut dyn Storage,
         ) -> T,
@@ -530,7 +597,11 @@ where
 
     pub fn read_module<F, T>(&self, query_fn: F) -> T
     where
-        F: FnOnce(&Router<BankT, CustomT, WasmT, StakingT, DistrT>, &dyn Api, &dyn Storage) -> T,
+        F: FnOnce(
+            &Router<BankT, CustomT, WasmT, StakingT, DistrT, StargateT>,
+            &dyn Api,
+            &dyn Storage,
+        ) -> T,
     {
         query_fn(&self.router, &self.api, &self.storage)
     }
@@ -538,7 +609,7 @@ where
 
 // Helper functions to call some custom WasmKeeper logic.
 // They show how we can easily add such calls to other custom keepers (CustomT, StakingT, etc)
-impl<BankT, ApiT, StorageT, CustomT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, StakingT, DistrT, StargateT>
     App<
         BankT,
         ApiT,
@@ -547,6 +618,7 @@ impl<BankT, ApiT, StorageT, CustomT, StakingT, DistrT>
         WasmKeeper<CustomT::ExecT, CustomT::QueryT>,
         StakingT,
         DistrT,
+        StargateT,
     >
 where
     BankT: Bank,
@@ -555,6 +627,7 @@ where
     CustomT: Module,
     StakingT: Staking,
     DistrT: Distribution,
+    StargateT: Stargate,
     CustomT::ExecT: Clone + fmt::Debug + PartialEq + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
 {
@@ -575,8 +648,8 @@ where
     }
 }
 
-impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
-    App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT>
+    App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, StargateT>
 where
     CustomT::ExecT: std::fmt::Debug + PartialEq + Clone + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
@@ -587,6 +660,7 @@ where
     CustomT: Module,
     StakingT: Staking,
     DistrT: Distribution,
+    StargateT: Stargate,
 {
     pub fn set_block(&mut self, block: BlockInfo) {
         self.block = block;
@@ -678,7 +752,7 @@ where
     }
 }
 
-pub struct Router<Bank, Custom, Wasm, Staking, Distr> {
+pub struct Router<Bank, Custom, Wasm, Staking, Distr, Stargate> {
     // this can remain crate-only as all special functions are wired up to app currently
     // we need to figure out another format for wasm, as some like sudo need to be called after init
     pub(crate) wasm: Wasm,
@@ -687,9 +761,11 @@ pub struct Router<Bank, Custom, Wasm, Staking, Distr> {
     pub custom: Custom,
     pub staking: Staking,
     pub distribution: Distr,
+    pub stargate: Stargate,
 }
 
-impl<BankT, CustomT, WasmT, StakingT, DistrT> Router<BankT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, CustomT, WasmT, StakingT, DistrT, StargateT>
+    Router<BankT, CustomT, WasmT, StakingT, DistrT, StargateT>
 where
     CustomT::ExecT: Clone + fmt::Debug + PartialEq + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
@@ -698,6 +774,7 @@ where
     BankT: Bank,
     StakingT: Staking,
     DistrT: Distribution,
+    StargateT: Stargate,
 {
     pub fn querier<'a>(
         &'a self,
@@ -771,8 +848,8 @@ pub trait CosmosRouter {
     ) -> AnyResult<AppResponse>;
 }
 
-impl<BankT, CustomT, WasmT, StakingT, DistrT> CosmosRouter
-    for Router<BankT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, CustomT, WasmT, StakingT, DistrT, StargateT> CosmosRouter
+    for Router<BankT, CustomT, WasmT, StakingT, DistrT, StargateT>
 where
     CustomT::ExecT: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
@@ -781,6 +858,7 @@ where
     BankT: Bank,
     StakingT: Staking,
     DistrT: Distribution,
+    StargateT: Stargate,
 {
     type ExecC = CustomT::ExecT;
     type QueryC = CustomT::QueryT;
@@ -801,6 +879,12 @@ where
             CosmosMsg::Distribution(msg) => self
                 .distribution
                 .execute(api, storage, self, block, sender, msg),
+            #[cfg(feature = "stargate")]
+            CosmosMsg::Stargate { type_url, value } => {
+                let msg = StargateMsg { type_url, value };
+                self.stargate
+                    .execute(api, storage, self, block, sender, msg)
+            }
             _ => bail!("Cannot execute {:?}", msg),
         }
     }
@@ -821,7 +905,12 @@ where
             QueryRequest::Bank(req) => self.bank.query(api, storage, &querier, block, req),
             QueryRequest::Custom(req) => self.custom.query(api, storage, &querier, block, req),
             QueryRequest::Staking(req) => self.staking.query(api, storage, &querier, block, req),
-            _ => unimplemented!(),
+            #[cfg(feature = "stargate")]
+            QueryRequest::Stargate { path, data } => {
+                let req = StargateQuery { path, data };
+                self.stargate.query(api, storage, &querier, block, req)
+            }
+            _ => bail!("Unsupported query request"),
         }
     }
 
@@ -1601,8 +1690,8 @@ mod test {
         // TODO: check error?
     }
 
-    fn query_router<BankT, CustomT, WasmT, StakingT, DistrT>(
-        router: &Router<BankT, CustomT, WasmT, StakingT, DistrT>,
+    fn query_router<BankT, CustomT, WasmT, StakingT, DistrT, StargateT>(
+        router: &Router<BankT, CustomT, WasmT, StakingT, DistrT, StargateT>,
         api: &dyn Api,
         storage: &dyn Storage,
         rcpt: &Addr,
@@ -1615,6 +1704,7 @@ mod test {
         CustomT: Module,
         StakingT: Staking,
         DistrT: Distribution,
+        StargateT: Stargate,
     {
         let query = BankQuery::AllBalances {
             address: rcpt.into(),
@@ -2509,6 +2599,151 @@ mod test {
         }
     }
 
+    #[cfg(feature = "stargate")]
+    mod stargate {
+        use super::*;
+
+        use cosmwasm_std::{IbcQuery, PortIdResponse};
+        use cw_storage_plus::Item;
+
+        use crate::stargate::{Stargate, StargateMsg, StargateQuery};
+
+        const COUNT: Item<u32> = Item::new("count");
+
+        /// Counts `MsgIncrement`s, the only message it knows.
+        struct CountingStargate {}
+
+        impl Stargate for CountingStargate {}
+
+        impl Module for CountingStargate {
+            type ExecT = StargateMsg;
+            type QueryT = StargateQuery;
+            type SudoT = Empty;
+
+            fn execute<ExecC, QueryC>(
+                &self,
+                _api: &dyn Api,
+                storage: &mut dyn Storage,
+                _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+                _block: &BlockInfo,
+                _sender: Addr,
+                msg: StargateMsg,
+            ) -> AnyResult<AppResponse> {
+                match msg.type_url.as_str() {
+                    "/test.v1.MsgIncrement" => {
+                        let count = COUNT.may_load(storage)?.unwrap_or_default();
+                        COUNT.save(storage, &(count + 1))?;
+                        Ok(AppResponse::default())
+                    }
+                    type_url => bail!("Unsupported stargate message {}", type_url),
+                }
+            }
+
+            fn sudo<ExecC, QueryC>(
+                &self,
+                _api: &dyn Api,
+                _storage: &mut dyn Storage,
+                _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+                _block: &BlockInfo,
+                _msg: Empty,
+            ) -> AnyResult<AppResponse> {
+                bail!("sudo not implemented for CountingStargate")
+            }
+
+            fn query(
+                &self,
+                _api: &dyn Api,
+                storage: &dyn Storage,
+                _querier: &dyn Querier,
+                _block: &BlockInfo,
+                request: StargateQuery,
+            ) -> AnyResult<Binary> {
+                match request.path.as_str() {
+                    "/test.v1.Query/Count" => {
+                        Ok(to_binary(&COUNT.may_load(storage)?.unwrap_or_default())?)
+                    }
+                    path => bail!("Unsupported stargate query {}", path),
+                }
+            }
+        }
+
+        fn increment() -> CosmosMsg {
+            CosmosMsg::Stargate {
+                type_url: "/test.v1.MsgIncrement".to_string(),
+                value: Binary::default(),
+            }
+        }
+
+        fn count() -> QueryRequest<Empty> {
+            QueryRequest::Stargate {
+                path: "/test.v1.Query/Count".to_string(),
+                data: Binary::default(),
+            }
+        }
+
+        #[test]
+        fn default_stargate_fails_cleanly() {
+            let mut app = App::default();
+
+            let err = app
+                .execute(Addr::unchecked("sender"), increment())
+                .unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Unsupported stargate message /test.v1.MsgIncrement from sender"
+            );
+
+            let err = app.wrap().query::<u32>(&count()).unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("Unsupported stargate query /test.v1.Query/Count"));
+        }
+
+        #[test]
+        fn stargate_module_dispatches_on_type_url() {
+            let mut app = AppBuilder::new()
+                .with_stargate(CountingStargate {})
+                .build(no_init);
+            let sender = Addr::unchecked("sender");
+
+            app.execute(sender.clone(), increment()).unwrap();
+            app.execute(sender.clone(), increment()).unwrap();
+            assert_eq!(app.wrap().query::<u32>(&count()).unwrap(), 2);
+
+            let msg = CosmosMsg::Stargate {
+                type_url: "/test.v1.MsgDecrement".to_string(),
+                value: Binary::default(),
+            };
+            let err = app.execute(sender, msg).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Unsupported stargate message /test.v1.MsgDecrement"
+            );
+            // the failed message left no trace
+            assert_eq!(app.wrap().query::<u32>(&count()).unwrap(), 2);
+
+            let query = QueryRequest::Stargate {
+                path: "/test.v1.Query/Total".to_string(),
+                data: Binary::default(),
+            };
+            let err = app.wrap().query::<u32>(&query).unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("Unsupported stargate query /test.v1.Query/Total"));
+        }
+
+        #[test]
+        fn unsupported_query_request_fails_cleanly() {
+            let app = AppBuilder::new()
+                .with_stargate(CountingStargate {})
+                .build(no_init);
+
+            let query: QueryRequest<Empty> = QueryRequest::Ibc(IbcQuery::PortId {});
+            let err = app.wrap().query::<PortIdResponse>(&query).unwrap_err();
+            assert!(err.to_string().contains("Unsupported query request"));
+        }
+    }
+
     mod protobuf_wrapped_data {
         use super::*;
         use crate::test_helpers::contracts::echo::EXECUTE_REPLY_BASE_ID;
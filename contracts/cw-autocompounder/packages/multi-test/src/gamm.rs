@@ -0,0 +1,1849 @@
+use std::convert::{TryFrom, TryInto};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, ensure, Result as AnyResult};
+use cosmwasm_std::{
+    to_binary, Addr, Api, BankMsg, Binary, BlockInfo, Coin, CustomQuery, Decimal, Decimal256,
+    Empty, Event, Querier, Storage, Uint128, Uint256, Uint512,
+};
+use cw_storage_plus::{Item, Map};
+use osmosis_std::shim::Any;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::gamm::poolmodels::balancer::v1beta1::{
+    MsgCreateBalancerPool, MsgCreateBalancerPoolResponse,
+};
+use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::{
+    MsgCreateStableswapPool, MsgCreateStableswapPoolResponse, Pool as StableSwapPool,
+    PoolParams as StableSwapPoolParams,
+};
+use osmosis_std::types::osmosis::gamm::v1beta1::{
+    MsgExitPool, MsgExitPoolResponse, MsgExitSwapShareAmountIn, MsgExitSwapShareAmountInResponse,
+    MsgJoinPool, MsgJoinPoolResponse, MsgJoinSwapExternAmountIn, MsgJoinSwapExternAmountInResponse,
+    MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, MsgSwapExactAmountOut,
+    MsgSwapExactAmountOutResponse, Pool as BalancerPool, PoolAsset, PoolParams,
+    QueryNumPoolsResponse, QueryPoolRequest, QueryPoolResponse, QueryPoolTypeRequest,
+    QueryPoolTypeResponse, QuerySpotPriceRequest, QuerySpotPriceResponse,
+    QuerySwapExactAmountInRequest, QuerySwapExactAmountInResponse, QuerySwapExactAmountOutRequest,
+    QuerySwapExactAmountOutResponse, QueryTotalPoolLiquidityRequest,
+    QueryTotalPoolLiquidityResponse, QueryTotalSharesRequest, QueryTotalSharesResponse,
+    SwapAmountInRoute, SwapAmountOutRoute,
+};
+use osmosis_std::types::osmosis::twap::v1beta1::{
+    ArithmeticTwapToNowRequest, ArithmeticTwapToNowResponse,
+};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::app::CosmosRouter;
+use crate::executor::AppResponse;
+use crate::prefixed_storage::{prefixed, prefixed_read};
+use crate::stargate::{Stargate, StargateMsg, StargateQuery};
+use crate::{BankSudo, Module};
+
+pub const NAMESPACE_GAMM: &[u8] = b"gamm";
+
+/// Shares minted to the creator of a pool, as on Osmosis
+const INIT_POOL_SHARES: u128 = 100_000_000_000_000_000_000;
+/// Osmosis scales the balancer weights it is given by 2^30, so that small weights keep their precision
+const WEIGHT_PRECISION: u128 = 1 << 30;
+/// Pools hold at most this many assets
+const MAX_POOL_ASSETS: usize = 8;
+/// The series `pow_approx` sums stops once its terms drop below 10^-8
+const POW_PRECISION: Decimal256 = Decimal256::raw(10_000_000_000);
+
+const POOLS: Map<u64, GammPool> = Map::new("pools");
+const POOL_COUNT: Item<u64> = Item::new("pool_count");
+
+/// The pool model, with the per asset parameters of its math
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolKind {
+    /// Weighted constant product pool, the weights are already scaled by 2^30
+    Balancer { weights: Vec<Uint128> },
+    /// Solidly style `xy(x² + y²)` curve, amounts are divided by their scaling factor
+    StableSwap { scaling_factors: Vec<u64> },
+}
+
+/// A pool as the gamm keeper stores it. `assets` keeps the order given at creation,
+/// which is the order of `weights` or `scaling_factors` too.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct GammPool {
+    pub id: u64,
+    pub kind: PoolKind,
+    pub assets: Vec<Coin>,
+    pub total_shares: Uint128,
+    pub swap_fee: Decimal,
+    pub exit_fee: Decimal,
+    pub future_pool_governor: String,
+}
+
+impl GammPool {
+    /// The account holding the pool liquidity
+    pub fn address(&self) -> Addr {
+        Addr::unchecked(format!("pool{}", self.id))
+    }
+
+    pub fn share_denom(&self) -> String {
+        format!("gamm/pool/{}", self.id)
+    }
+
+    /// The pool encoded the way `QueryPoolRequest` returns it
+    pub fn to_any(&self) -> Any {
+        let total_shares = Some(proto_coin(&self.share_denom(), self.total_shares));
+        match &self.kind {
+            PoolKind::Balancer { weights } => {
+                let pool = BalancerPool {
+                    address: self.address().to_string(),
+                    id: self.id,
+                    pool_params: Some(PoolParams {
+                        swap_fee: self.swap_fee.to_string(),
+                        exit_fee: self.exit_fee.to_string(),
+                        smooth_weight_change_params: None,
+                    }),
+                    future_pool_governor: self.future_pool_governor.clone(),
+                    total_shares,
+                    pool_assets: self
+                        .assets
+                        .iter()
+                        .zip(weights)
+                        .map(|(asset, weight)| PoolAsset {
+                            token: Some(proto_coin(&asset.denom, asset.amount)),
+                            weight: weight.to_string(),
+                        })
+                        .collect(),
+                    total_weight: weights.iter().sum::<Uint128>().to_string(),
+                };
+                Any {
+                    type_url: BalancerPool::TYPE_URL.to_string(),
+                    value: Binary::from(pool).to_vec(),
+                }
+            }
+            PoolKind::StableSwap { scaling_factors } => {
+                let pool = StableSwapPool {
+                    address: self.address().to_string(),
+                    id: self.id,
+                    pool_params: Some(StableSwapPoolParams {
+                        swap_fee: self.swap_fee.to_string(),
+                        exit_fee: self.exit_fee.to_string(),
+                    }),
+                    future_pool_governor: self.future_pool_governor.clone(),
+                    total_shares,
+                    pool_liquidity: self
+                        .assets
+                        .iter()
+                        .map(|asset| proto_coin(&asset.denom, asset.amount))
+                        .collect(),
+                    scaling_factor: scaling_factors.clone(),
+                    scaling_factor_controller: "".to_string(),
+                };
+                Any {
+                    type_url: StableSwapPool::TYPE_URL.to_string(),
+                    value: Binary::from(pool).to_vec(),
+                }
+            }
+        }
+    }
+
+    fn pool_type(&self) -> &'static str {
+        match self.kind {
+            PoolKind::Balancer { .. } => "Balancer",
+            PoolKind::StableSwap { .. } => "Stableswap",
+        }
+    }
+
+    fn asset_index(&self, denom: &str) -> AnyResult<usize> {
+        self.assets
+            .iter()
+            .position(|asset| asset.denom == denom)
+            .ok_or_else(|| anyhow!("Denom {} is not in pool {}", denom, self.id))
+    }
+
+    /// Price of `base` in `quote`, without the swap fee
+    pub fn spot_price(&self, base: &str, quote: &str) -> AnyResult<Decimal256> {
+        let (b, q) = (self.asset_index(base)?, self.asset_index(quote)?);
+        ensure!(b != q, "Cannot price {} in itself", base);
+        match &self.kind {
+            PoolKind::Balancer { weights } => Ok(Decimal256::checked_from_ratio(
+                Uint256::from(self.assets[q].amount) * Uint256::from(weights[b]),
+                Uint256::from(self.assets[b].amount) * Uint256::from(weights[q]),
+            )?),
+            PoolKind::StableSwap { scaling_factors } => {
+                let curve = StableCurve::new(&self.assets, scaling_factors)?;
+                curve.spot_price(b, q)
+            }
+        }
+    }
+
+    /// Adds liquidity for `shares` new shares, rounding the amounts it takes up
+    fn join(&mut self, shares: Uint128) -> AnyResult<Vec<Coin>> {
+        ensure!(
+            !shares.is_zero(),
+            "Cannot join pool {} for zero shares",
+            self.id
+        );
+        let mut tokens_in = vec![];
+        for asset in self.assets.iter_mut() {
+            let amount = mul_ratio_ceil(asset.amount, shares, self.total_shares)?;
+            asset.amount += amount;
+            tokens_in.push(Coin::new(amount.u128(), asset.denom.clone()));
+        }
+        self.total_shares += shares;
+        Ok(tokens_in)
+    }
+
+    /// Removes the liquidity `shares` are worth after the exit fee, rounding it down
+    fn exit(&mut self, shares: Uint128) -> AnyResult<Vec<Coin>> {
+        ensure!(
+            !shares.is_zero(),
+            "Cannot exit pool {} for zero shares",
+            self.id
+        );
+        ensure!(
+            shares < self.total_shares,
+            "Cannot exit all shares of pool {}",
+            self.id
+        );
+        let shares_after_fee = shares - shares * self.exit_fee;
+        let mut tokens_out = vec![];
+        for asset in self.assets.iter_mut() {
+            let amount = asset
+                .amount
+                .multiply_ratio(shares_after_fee, self.total_shares);
+            asset.amount -= amount;
+            tokens_out.push(Coin::new(amount.u128(), asset.denom.clone()));
+        }
+        self.total_shares -= shares;
+        Ok(tokens_out)
+    }
+
+    /// Swaps all of `token_in` for `denom_out`, the fee stays in the pool
+    fn swap_exact_in(&mut self, token_in: &Coin, denom_out: &str) -> AnyResult<Uint128> {
+        let (i, o) = (
+            self.asset_index(&token_in.denom)?,
+            self.asset_index(denom_out)?,
+        );
+        ensure!(i != o, "Cannot swap {} for itself", denom_out);
+        let in_after_fee =
+            dec(token_in.amount) * (Decimal256::one() - Decimal256::from(self.swap_fee));
+        let amount_out = match &self.kind {
+            PoolKind::Balancer { weights } => {
+                let balance_in = dec(self.assets[i].amount);
+                let y = balance_in / (balance_in + in_after_fee);
+                let factor = pow(y, ratio(weights[i], weights[o]))?;
+                floor(dec(self.assets[o].amount) * (Decimal256::one() - factor))?
+            }
+            PoolKind::StableSwap { scaling_factors } => {
+                let curve = StableCurve::new(&self.assets, scaling_factors)?;
+                curve.out_given_in(i, floor(in_after_fee)?, o)?
+            }
+        };
+        ensure!(
+            amount_out < self.assets[o].amount,
+            "Swap would drain pool {} of {}",
+            self.id,
+            denom_out
+        );
+        self.assets[i].amount += token_in.amount;
+        self.assets[o].amount -= amount_out;
+        Ok(amount_out)
+    }
+
+    /// Swaps as little `denom_in` as needed for `token_out`, rounding it up
+    fn swap_exact_out(&mut self, denom_in: &str, token_out: &Coin) -> AnyResult<Uint128> {
+        let (i, o) = (
+            self.asset_index(denom_in)?,
+            self.asset_index(&token_out.denom)?,
+        );
+        ensure!(i != o, "Cannot swap {} for itself", denom_in);
+        ensure!(
+            token_out.amount < self.assets[o].amount,
+            "Swap would drain pool {} of {}",
+            self.id,
+            token_out.denom
+        );
+        let in_before_fee = match &self.kind {
+            PoolKind::Balancer { weights } => {
+                let balance_out = dec(self.assets[o].amount);
+                let y = balance_out / (balance_out - dec(token_out.amount));
+                let factor = pow(y, ratio(weights[o], weights[i]))? - Decimal256::one();
+                dec(self.assets[i].amount) * factor
+            }
+            PoolKind::StableSwap { scaling_factors } => {
+                let curve = StableCurve::new(&self.assets, scaling_factors)?;
+                dec(curve.in_given_out(i, token_out.amount, o)?)
+            }
+        };
+        let amount_in =
+            ceil(in_before_fee / (Decimal256::one() - Decimal256::from(self.swap_fee)))?;
+        self.assets[i].amount += amount_in;
+        self.assets[o].amount -= token_out.amount;
+        Ok(amount_in)
+    }
+
+    /// Adds `token_in` alone, minting the shares the value it adds is worth
+    fn join_single(&mut self, token_in: &Coin) -> AnyResult<Uint128> {
+        let i = self.asset_index(&token_in.denom)?;
+        // only the part of the deposit that would have to be swapped pays the fee
+        let growth = match &self.kind {
+            PoolKind::Balancer { weights } => {
+                let weight = ratio(weights[i], weights.iter().sum());
+                let fee = (Decimal256::one() - weight) * Decimal256::from(self.swap_fee);
+                let in_after_fee = dec(token_in.amount) * (Decimal256::one() - fee);
+                let base = Decimal256::one() + in_after_fee / dec(self.assets[i].amount);
+                pow(base, weight)?
+            }
+            PoolKind::StableSwap { scaling_factors } => {
+                let n = self.assets.len() as u128;
+                let fee = Decimal256::from_ratio(n - 1, n) * Decimal256::from(self.swap_fee);
+                let in_after_fee = floor(dec(token_in.amount) * (Decimal256::one() - fee))?;
+                let curve = StableCurve::new(&self.assets, scaling_factors)?;
+                let base = curve.invariant_growth(i, in_after_fee)?;
+                // the invariant grows with the (n + 2)th power of the liquidity
+                pow(base, Decimal256::from_ratio(1u128, n + 2))?
+            }
+        };
+        let shares = floor(dec(self.total_shares) * (growth - Decimal256::one()))?;
+        ensure!(
+            !shares.is_zero(),
+            "Joining pool {} mints zero shares",
+            self.id
+        );
+        self.assets[i].amount += token_in.amount;
+        self.total_shares += shares;
+        Ok(shares)
+    }
+
+    /// Exits the pool and swaps everything but `denom_out` into it, in this pool
+    fn exit_single(&mut self, shares: Uint128, denom_out: &str) -> AnyResult<Uint128> {
+        self.asset_index(denom_out)?;
+        let mut amount_out = Uint128::zero();
+        for token in self.exit(shares)? {
+            if token.denom == denom_out {
+                amount_out += token.amount;
+            } else if !token.amount.is_zero() {
+                amount_out += self.swap_exact_in(&token, denom_out)?;
+            }
+        }
+        Ok(amount_out)
+    }
+}
+
+/// The stableswap curve `xy(x² + y² + w)v = k` for a swap between x and y, where `v` is the
+/// product and `w` the sum of squares of the other reserves. Reserves are in units of
+/// `amount * lcm / scaling_factor`, so that all of them stay integers.
+struct StableCurve {
+    reserves: Vec<Uint512>,
+    multipliers: Vec<Uint512>,
+}
+
+impl StableCurve {
+    fn new(assets: &[Coin], scaling_factors: &[u64]) -> AnyResult<Self> {
+        let lcm = scaling_factors
+            .iter()
+            .try_fold(1u128, |lcm, &factor| {
+                let factor = factor as u128;
+                (lcm / gcd(lcm, factor)).checked_mul(factor)
+            })
+            .ok_or_else(|| anyhow!("Scaling factors overflow"))?;
+        let multipliers: Vec<Uint512> = scaling_factors
+            .iter()
+            .map(|&factor| Uint512::from(lcm / factor as u128))
+            .collect();
+        let reserves = assets
+            .iter()
+            .zip(&multipliers)
+            .map(|(asset, &multiplier)| Uint512::from(asset.amount.u128()) * multiplier)
+            .collect();
+        Ok(StableCurve {
+            reserves,
+            multipliers,
+        })
+    }
+
+    /// Sum of squares of the reserves other than `x` and `y`
+    fn others(&self, x: usize, y: usize) -> Uint512 {
+        self.reserves
+            .iter()
+            .enumerate()
+            .filter(|(k, _)| *k != x && *k != y)
+            .map(|(_, r)| *r * *r)
+            .fold(Uint512::zero(), |sum, sq| sum + sq)
+    }
+
+    fn cfmm(x: Uint512, y: Uint512, w: Uint512) -> AnyResult<Uint512> {
+        let sum = x.checked_pow(2)?.checked_add(y.checked_pow(2)?)? + w;
+        Ok(x.checked_mul(y)?.checked_mul(sum)?)
+    }
+
+    /// Smallest `y` at or above which `cfmm(x, y, w) >= k`, `cfmm` grows with both x and y
+    fn solve(x: Uint512, w: Uint512, k: Uint512, mut hi: Uint512) -> AnyResult<Uint512> {
+        while Self::cfmm(x, hi, w)? < k {
+            hi = hi.checked_mul(Uint512::from(2u8))? + Uint512::one();
+        }
+        let mut lo = Uint512::zero();
+        while lo < hi {
+            let mid = (lo + hi) >> 1;
+            if Self::cfmm(x, mid, w)? >= k {
+                hi = mid;
+            } else {
+                lo = mid + Uint512::one();
+            }
+        }
+        Ok(hi)
+    }
+
+    fn out_given_in(&self, i: usize, amount_in: Uint128, o: usize) -> AnyResult<Uint128> {
+        let (x, y, w) = (self.reserves[i], self.reserves[o], self.others(i, o));
+        let k = Self::cfmm(x, y, w)?;
+        let x_after = x + Uint512::from(amount_in.u128()) * self.multipliers[i];
+        let y_after = Self::solve(x_after, w, k, y)?;
+        to_uint128((y - y_after) / self.multipliers[o])
+    }
+
+    fn in_given_out(&self, i: usize, amount_out: Uint128, o: usize) -> AnyResult<Uint128> {
+        let (x, y, w) = (self.reserves[i], self.reserves[o], self.others(i, o));
+        let k = Self::cfmm(x, y, w)?;
+        let y_after = y - Uint512::from(amount_out.u128()) * self.multipliers[o];
+        let x_after = Self::solve(y_after, w, k, x)?;
+        let units = x_after - x;
+        let multiplier = self.multipliers[i];
+        to_uint128((units + multiplier - Uint512::one()) / multiplier)
+    }
+
+    /// Ratio of the full invariant `Π r · Σ r²` after adding `amount` of asset `i` to before
+    fn invariant_growth(&self, i: usize, amount: Uint128) -> AnyResult<Decimal256> {
+        let x = self.reserves[i];
+        let x_after = x + Uint512::from(amount.u128()) * self.multipliers[i];
+        let squares = self.others(i, i);
+        let sum = squares + x * x;
+        let sum_after = squares + x_after * x_after;
+        Ok(ratio512(x_after, x)? * ratio512(sum_after, sum)?)
+    }
+
+    /// `dy/dx` along the curve, converted back from units to amounts
+    fn spot_price(&self, b: usize, q: usize) -> AnyResult<Decimal256> {
+        let (x, y, w) = (self.reserves[b], self.reserves[q], self.others(b, q));
+        let three = Uint512::from(3u8);
+        let numerator = y * (three * x * x + y * y + w) * self.multipliers[b];
+        let denominator = x * (x * x + three * y * y + w) * self.multipliers[q];
+        ratio512(numerator, denominator)
+    }
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn ratio512(numerator: Uint512, denominator: Uint512) -> AnyResult<Decimal256> {
+    ensure!(!denominator.is_zero(), "Division by zero");
+    let atomics = numerator.checked_mul(Uint512::from(10u128.pow(18)))? / denominator;
+    Ok(Decimal256::new(atomics.try_into()?))
+}
+
+fn to_uint128(value: Uint512) -> AnyResult<Uint128> {
+    let value: Uint256 = value.try_into()?;
+    Ok(value.try_into()?)
+}
+
+fn dec(amount: Uint128) -> Decimal256 {
+    Decimal256::from_ratio(amount, 1u128)
+}
+
+fn ratio(numerator: Uint128, denominator: Uint128) -> Decimal256 {
+    Decimal256::from_ratio(numerator, denominator)
+}
+
+fn floor(value: Decimal256) -> AnyResult<Uint128> {
+    let whole = value.atomics() / Uint256::from(10u128.pow(18));
+    Ok(whole.try_into()?)
+}
+
+fn ceil(value: Decimal256) -> AnyResult<Uint128> {
+    floor(value.checked_ceil()?)
+}
+
+fn mul_ratio_ceil(amount: Uint128, numerator: Uint128, denominator: Uint128) -> AnyResult<Uint128> {
+    let product = Uint256::from(amount) * Uint256::from(numerator);
+    let denominator = Uint256::from(denominator);
+    let result = (product + denominator - Uint256::one()) / denominator;
+    Ok(result.try_into()?)
+}
+
+/// `base^exp` for `0 < base < 2`, like the Osmosis `Pow`. The integer part of the exponent is
+/// exact, the fractional part comes from the binomial series of `(1 + (base - 1))^exp`.
+fn pow(base: Decimal256, exp: Decimal256) -> AnyResult<Decimal256> {
+    ensure!(
+        !base.is_zero() && base < Decimal256::percent(200),
+        "Base must be between zero and two, got {}",
+        base
+    );
+    let integer = exp.floor();
+    let fractional = exp - integer;
+    let integer_pow = base.checked_pow(u32::try_from(floor(integer)?.u128())?)?;
+    if fractional.is_zero() {
+        return Ok(integer_pow);
+    }
+    Ok(integer_pow.checked_mul(pow_approx(base, fractional))?)
+}
+
+fn pow_approx(base: Decimal256, exp: Decimal256) -> Decimal256 {
+    let one = Decimal256::one();
+    let (x, x_negative) = abs_diff_with_sign(base, one);
+    let mut term = one;
+    let mut sum = one;
+    let mut negative = false;
+    let mut k = 1u128;
+    while term >= POW_PRECISION {
+        let big_k = Decimal256::from_ratio(k, 1u128);
+        let (c, c_negative) = abs_diff_with_sign(exp, big_k - one);
+        term = term * c * x / big_k;
+        if term.is_zero() {
+            break;
+        }
+        if x_negative {
+            negative = !negative;
+        }
+        if c_negative {
+            negative = !negative;
+        }
+        if negative {
+            sum -= term;
+        } else {
+            sum += term;
+        }
+        k += 1;
+    }
+    sum
+}
+
+fn abs_diff_with_sign(a: Decimal256, b: Decimal256) -> (Decimal256, bool) {
+    if a >= b {
+        (a - b, false)
+    } else {
+        (b - a, true)
+    }
+}
+
+fn proto_coin(denom: &str, amount: Uint128) -> ProtoCoin {
+    ProtoCoin {
+        denom: denom.to_string(),
+        amount: amount.to_string(),
+    }
+}
+
+fn parse_coin(coin: Option<ProtoCoin>) -> AnyResult<Coin> {
+    let coin = coin.ok_or_else(|| anyhow!("Missing coin"))?;
+    Ok(Coin::try_from(coin)?)
+}
+
+/// Parses the `{amount}{denom}` strings of the estimate queries
+fn parse_coin_str(coin: &str) -> AnyResult<Coin> {
+    let split = coin
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("Invalid coin {}", coin))?;
+    let (amount, denom) = coin.split_at(split);
+    Ok(Coin::new(Uint128::from_str(amount)?.u128(), denom))
+}
+
+fn parse_amount(amount: &str) -> AnyResult<Uint128> {
+    Ok(Uint128::from_str(amount)?)
+}
+
+fn coins_to_string(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(|c| format!("{}{}", c.amount, c.denom))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Simulates the Osmosis gamm module: balancer and stableswap pools answering their
+/// `CosmosMsg::Stargate` messages and `GammQuerier` queries. Pool liquidity is kept
+/// in the bank, on the pool address, and LP shares are minted as `gamm/pool/{id}`.
+/// Unlike Osmosis, creating a pool charges no creation fee. Pools keep no price history,
+/// so the twap module's `ArithmeticTwapToNow` answers with the current spot price.
+#[derive(Default)]
+pub struct GammKeeper {}
+
+impl GammKeeper {
+    pub fn new() -> Self {
+        GammKeeper {}
+    }
+
+    /// Loads a pool, for tests that check its state directly
+    pub fn pool(&self, storage: &dyn Storage, pool_id: u64) -> AnyResult<GammPool> {
+        let storage = prefixed_read(storage, NAMESPACE_GAMM);
+        load_pool(&storage, pool_id)
+    }
+
+    fn create_pool<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        mut pool: GammPool,
+    ) -> AnyResult<(u64, Vec<Event>)>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        let assets = &pool.assets;
+        ensure!(
+            (2..=MAX_POOL_ASSETS).contains(&assets.len()),
+            "Pools hold between 2 and {} assets, got {}",
+            MAX_POOL_ASSETS,
+            assets.len()
+        );
+        for (k, asset) in assets.iter().enumerate() {
+            ensure!(
+                !asset.amount.is_zero(),
+                "Cannot create a pool without {}",
+                asset.denom
+            );
+            ensure!(
+                assets[..k].iter().all(|other| other.denom != asset.denom),
+                "Duplicate denom {} in pool",
+                asset.denom
+            );
+        }
+
+        let mut gamm_storage = prefixed(storage, NAMESPACE_GAMM);
+        let id = POOL_COUNT.may_load(&gamm_storage)?.unwrap_or_default() + 1;
+        POOL_COUNT.save(&mut gamm_storage, &id)?;
+        pool.id = id;
+        pool.total_shares = Uint128::new(INIT_POOL_SHARES);
+        POOLS.save(&mut gamm_storage, id, &pool)?;
+
+        send(
+            api,
+            storage,
+            router,
+            block,
+            &sender,
+            &pool.address(),
+            pool.assets.clone(),
+        )?;
+        mint(
+            api,
+            storage,
+            router,
+            block,
+            &sender,
+            &pool.share_denom(),
+            pool.total_shares,
+        )?;
+        let event = Event::new("pool_created").add_attribute("pool_id", id.to_string());
+        Ok((id, vec![event]))
+    }
+
+    fn swap_exact_in<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        routes: Vec<SwapAmountInRoute>,
+        token_in: Coin,
+    ) -> AnyResult<(Uint128, Vec<Event>)>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        ensure!(!routes.is_empty(), "Swap needs at least one route");
+        let mut holder = sender.clone();
+        let mut token = token_in;
+        let mut events = vec![];
+        for route in routes {
+            let mut gamm_storage = prefixed(storage, NAMESPACE_GAMM);
+            let mut pool = load_pool(&gamm_storage, route.pool_id)?;
+            let amount_out = pool.swap_exact_in(&token, &route.token_out_denom)?;
+            POOLS.save(&mut gamm_storage, pool.id, &pool)?;
+
+            let token_out = Coin::new(amount_out.u128(), route.token_out_denom);
+            send(
+                api,
+                storage,
+                router,
+                block,
+                &holder,
+                &pool.address(),
+                vec![token.clone()],
+            )?;
+            events.push(swap_event(&sender, &pool, &token, &token_out));
+            holder = pool.address();
+            token = token_out;
+        }
+        send(
+            api,
+            storage,
+            router,
+            block,
+            &holder,
+            &sender,
+            vec![token.clone()],
+        )?;
+        Ok((token.amount, events))
+    }
+
+    fn swap_exact_out<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        routes: Vec<SwapAmountOutRoute>,
+        token_out: Coin,
+    ) -> AnyResult<(Uint128, Vec<Event>)>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        ensure!(!routes.is_empty(), "Swap needs at least one route");
+        // the amounts are set from the last hop back, the tokens then move from the first
+        let mut hops = vec![];
+        let mut token = token_out;
+        for route in routes.into_iter().rev() {
+            let mut gamm_storage = prefixed(storage, NAMESPACE_GAMM);
+            let mut pool = load_pool(&gamm_storage, route.pool_id)?;
+            let amount_in = pool.swap_exact_out(&route.token_in_denom, &token)?;
+            POOLS.save(&mut gamm_storage, pool.id, &pool)?;
+
+            let token_in = Coin::new(amount_in.u128(), route.token_in_denom);
+            hops.push((pool, token_in.clone(), token));
+            token = token_in;
+        }
+        let amount_in = token.amount;
+
+        let mut holder = sender.clone();
+        let mut events = vec![];
+        for (pool, token_in, token_out) in hops.into_iter().rev() {
+            send(
+                api,
+                storage,
+                router,
+                block,
+                &holder,
+                &pool.address(),
+                vec![token_in.clone()],
+            )?;
+            events.push(swap_event(&sender, &pool, &token_in, &token_out));
+            holder = pool.address();
+            token = token_out;
+        }
+        send(api, storage, router, block, &holder, &sender, vec![token])?;
+        Ok((amount_in, events))
+    }
+}
+
+fn load_pool(storage: &dyn Storage, pool_id: u64) -> AnyResult<GammPool> {
+    POOLS
+        .may_load(storage, pool_id)?
+        .ok_or_else(|| anyhow!("Pool {} does not exist", pool_id))
+}
+
+fn swap_event(sender: &Addr, pool: &GammPool, token_in: &Coin, token_out: &Coin) -> Event {
+    Event::new("token_swapped")
+        .add_attribute("sender", sender)
+        .add_attribute("pool_id", pool.id.to_string())
+        .add_attribute("tokens_in", coins_to_string(&[token_in.clone()]))
+        .add_attribute("tokens_out", coins_to_string(&[token_out.clone()]))
+}
+
+/// Moves the non zero `coins` from `from` to `to` through the bank
+fn send<ExecC, QueryC>(
+    api: &dyn Api,
+    storage: &mut dyn Storage,
+    router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+    block: &BlockInfo,
+    from: &Addr,
+    to: &Addr,
+    coins: Vec<Coin>,
+) -> AnyResult<()>
+where
+    ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+    QueryC: CustomQuery + DeserializeOwned + 'static,
+{
+    let amount: Vec<Coin> = coins.into_iter().filter(|c| !c.amount.is_zero()).collect();
+    if !amount.is_empty() {
+        let msg = BankMsg::Send {
+            to_address: to.to_string(),
+            amount,
+        };
+        router.execute(api, storage, block, from.clone(), msg.into())?;
+    }
+    Ok(())
+}
+
+fn mint<ExecC, QueryC>(
+    api: &dyn Api,
+    storage: &mut dyn Storage,
+    router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+    block: &BlockInfo,
+    to: &Addr,
+    denom: &str,
+    amount: Uint128,
+) -> AnyResult<()>
+where
+    ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+    QueryC: CustomQuery + DeserializeOwned + 'static,
+{
+    let msg = BankSudo::Mint {
+        to_address: to.to_string(),
+        amount: vec![Coin::new(amount.u128(), denom)],
+    };
+    router.sudo(api, storage, block, msg.into())?;
+    Ok(())
+}
+
+fn burn<ExecC, QueryC>(
+    api: &dyn Api,
+    storage: &mut dyn Storage,
+    router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+    block: &BlockInfo,
+    from: &Addr,
+    denom: &str,
+    amount: Uint128,
+) -> AnyResult<()>
+where
+    ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+    QueryC: CustomQuery + DeserializeOwned + 'static,
+{
+    let msg = BankMsg::Burn {
+        amount: vec![Coin::new(amount.u128(), denom)],
+    };
+    router.execute(api, storage, block, from.clone(), msg.into())?;
+    Ok(())
+}
+
+fn ensure_signer(signer: &str, sender: &Addr) -> AnyResult<()> {
+    ensure!(
+        signer == sender.as_str(),
+        "Message signed by {} was sent by {}",
+        signer,
+        sender
+    );
+    Ok(())
+}
+
+fn parse_fee(fee: &str) -> AnyResult<Decimal> {
+    let fee = Decimal::from_str(fee)?;
+    ensure!(fee < Decimal::one(), "Fee {} must be below one", fee);
+    Ok(fee)
+}
+
+impl Stargate for GammKeeper {}
+
+impl Module for GammKeeper {
+    type ExecT = StargateMsg;
+    type QueryT = StargateQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: StargateMsg,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        match msg.type_url.as_str() {
+            MsgCreateBalancerPool::TYPE_URL => {
+                let msg = MsgCreateBalancerPool::try_from(msg.value)?;
+                ensure_signer(&msg.sender, &sender)?;
+                let params = msg
+                    .pool_params
+                    .ok_or_else(|| anyhow!("Missing pool params"))?;
+                ensure!(
+                    params.smooth_weight_change_params.is_none(),
+                    "Smooth weight changes are not supported"
+                );
+                let mut assets = vec![];
+                let mut weights = vec![];
+                for asset in msg.pool_assets {
+                    let weight = parse_amount(&asset.weight)?;
+                    ensure!(!weight.is_zero(), "Pool asset weights must be positive");
+                    weights.push(weight * Uint128::new(WEIGHT_PRECISION));
+                    assets.push(parse_coin(asset.token)?);
+                }
+                let pool = GammPool {
+                    id: 0,
+                    kind: PoolKind::Balancer { weights },
+                    assets,
+                    total_shares: Uint128::zero(),
+                    swap_fee: parse_fee(&params.swap_fee)?,
+                    exit_fee: parse_fee(&params.exit_fee)?,
+                    future_pool_governor: msg.future_pool_governor,
+                };
+                let (pool_id, events) =
+                    self.create_pool(api, storage, router, block, sender, pool)?;
+                let data = MsgCreateBalancerPoolResponse { pool_id };
+                Ok(AppResponse {
+                    events,
+                    data: Some(data.into()),
+                })
+            }
+            MsgCreateStableswapPool::TYPE_URL => {
+                let msg = MsgCreateStableswapPool::try_from(msg.value)?;
+                ensure_signer(&msg.sender, &sender)?;
+                let params = msg
+                    .pool_params
+                    .ok_or_else(|| anyhow!("Missing pool params"))?;
+                let assets = msg
+                    .initial_pool_liquidity
+                    .into_iter()
+                    .map(|coin| parse_coin(Some(coin)))
+                    .collect::<AnyResult<Vec<_>>>()?;
+                let scaling_factors = if msg.scaling_factors.is_empty() {
+                    vec![1; assets.len()]
+                } else {
+                    msg.scaling_factors
+                };
+                ensure!(
+                    scaling_factors.len() == assets.len(),
+                    "Got {} scaling factors for {} assets",
+                    scaling_factors.len(),
+                    assets.len()
+                );
+                ensure!(
+                    scaling_factors.iter().all(|factor| *factor > 0),
+                    "Scaling factors must be positive"
+                );
+                let pool = GammPool {
+                    id: 0,
+                    kind: PoolKind::StableSwap { scaling_factors },
+                    assets,
+                    total_shares: Uint128::zero(),
+                    swap_fee: parse_fee(&params.swap_fee)?,
+                    exit_fee: parse_fee(&params.exit_fee)?,
+                    future_pool_governor: msg.future_pool_governor,
+                };
+                let (pool_id, events) =
+                    self.create_pool(api, storage, router, block, sender, pool)?;
+                let data = MsgCreateStableswapPoolResponse { pool_id };
+                Ok(AppResponse {
+                    events,
+                    data: Some(data.into()),
+                })
+            }
+            MsgJoinPool::TYPE_URL => {
+                let msg = MsgJoinPool::try_from(msg.value)?;
+                ensure_signer(&msg.sender, &sender)?;
+                let share_out_amount = parse_amount(&msg.share_out_amount)?;
+                let mut gamm_storage = prefixed(storage, NAMESPACE_GAMM);
+                let mut pool = load_pool(&gamm_storage, msg.pool_id)?;
+                let token_in = pool.join(share_out_amount)?;
+                if !msg.token_in_maxs.is_empty() {
+                    for token in &token_in {
+                        let max = msg
+                            .token_in_maxs
+                            .iter()
+                            .find(|max| max.denom == token.denom)
+                            .ok_or_else(|| anyhow!("No maximum given for {}", token.denom))?;
+                        ensure!(
+                            token.amount <= parse_amount(&max.amount)?,
+                            "Joining takes {}{}, more than the maximum {}",
+                            token.amount,
+                            token.denom,
+                            max.amount
+                        );
+                    }
+                }
+                POOLS.save(&mut gamm_storage, pool.id, &pool)?;
+
+                send(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &sender,
+                    &pool.address(),
+                    token_in.clone(),
+                )?;
+                mint(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &sender,
+                    &pool.share_denom(),
+                    share_out_amount,
+                )?;
+                let event = Event::new("pool_joined")
+                    .add_attribute("sender", &sender)
+                    .add_attribute("pool_id", pool.id.to_string())
+                    .add_attribute("tokens_in", coins_to_string(&token_in));
+                let data = MsgJoinPoolResponse {
+                    share_out_amount: share_out_amount.to_string(),
+                    token_in: token_in
+                        .into_iter()
+                        .map(|c| proto_coin(&c.denom, c.amount))
+                        .collect(),
+                };
+                Ok(AppResponse {
+                    events: vec![event],
+                    data: Some(data.into()),
+                })
+            }
+            MsgExitPool::TYPE_URL => {
+                let msg = MsgExitPool::try_from(msg.value)?;
+                ensure_signer(&msg.sender, &sender)?;
+                let share_in_amount = parse_amount(&msg.share_in_amount)?;
+                let mut gamm_storage = prefixed(storage, NAMESPACE_GAMM);
+                let mut pool = load_pool(&gamm_storage, msg.pool_id)?;
+                let token_out = pool.exit(share_in_amount)?;
+                for min in &msg.token_out_mins {
+                    let amount = token_out
+                        .iter()
+                        .find(|token| token.denom == min.denom)
+                        .map(|token| token.amount)
+                        .unwrap_or_default();
+                    ensure!(
+                        amount >= parse_amount(&min.amount)?,
+                        "Exiting returns {}{}, less than the minimum {}",
+                        amount,
+                        min.denom,
+                        min.amount
+                    );
+                }
+                POOLS.save(&mut gamm_storage, pool.id, &pool)?;
+
+                burn(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &sender,
+                    &pool.share_denom(),
+                    share_in_amount,
+                )?;
+                send(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &pool.address(),
+                    &sender,
+                    token_out.clone(),
+                )?;
+                let event = Event::new("pool_exited")
+                    .add_attribute("sender", &sender)
+                    .add_attribute("pool_id", pool.id.to_string())
+                    .add_attribute("tokens_out", coins_to_string(&token_out));
+                let data = MsgExitPoolResponse {
+                    token_out: token_out
+                        .into_iter()
+                        .map(|c| proto_coin(&c.denom, c.amount))
+                        .collect(),
+                };
+                Ok(AppResponse {
+                    events: vec![event],
+                    data: Some(data.into()),
+                })
+            }
+            MsgSwapExactAmountIn::TYPE_URL => {
+                let msg = MsgSwapExactAmountIn::try_from(msg.value)?;
+                ensure_signer(&msg.sender, &sender)?;
+                let token_in = parse_coin(msg.token_in)?;
+                let (token_out_amount, events) =
+                    self.swap_exact_in(api, storage, router, block, sender, msg.routes, token_in)?;
+                let min = parse_amount(&msg.token_out_min_amount)?;
+                ensure!(
+                    token_out_amount >= min,
+                    "Swap returns {}, less than the minimum {}",
+                    token_out_amount,
+                    min
+                );
+                let data = MsgSwapExactAmountInResponse {
+                    token_out_amount: token_out_amount.to_string(),
+                };
+                Ok(AppResponse {
+                    events,
+                    data: Some(data.into()),
+                })
+            }
+            MsgSwapExactAmountOut::TYPE_URL => {
+                let msg = MsgSwapExactAmountOut::try_from(msg.value)?;
+                ensure_signer(&msg.sender, &sender)?;
+                let token_out = parse_coin(msg.token_out)?;
+                let (token_in_amount, events) = self
+                    .swap_exact_out(api, storage, router, block, sender, msg.routes, token_out)?;
+                let max = parse_amount(&msg.token_in_max_amount)?;
+                ensure!(
+                    token_in_amount <= max,
+                    "Swap takes {}, more than the maximum {}",
+                    token_in_amount,
+                    max
+                );
+                let data = MsgSwapExactAmountOutResponse {
+                    token_in_amount: token_in_amount.to_string(),
+                };
+                Ok(AppResponse {
+                    events,
+                    data: Some(data.into()),
+                })
+            }
+            MsgJoinSwapExternAmountIn::TYPE_URL => {
+                let msg = MsgJoinSwapExternAmountIn::try_from(msg.value)?;
+                ensure_signer(&msg.sender, &sender)?;
+                let token_in = parse_coin(msg.token_in)?;
+                let mut gamm_storage = prefixed(storage, NAMESPACE_GAMM);
+                let mut pool = load_pool(&gamm_storage, msg.pool_id)?;
+                let share_out_amount = pool.join_single(&token_in)?;
+                let min = parse_amount(&msg.share_out_min_amount)?;
+                ensure!(
+                    share_out_amount >= min,
+                    "Joining mints {} shares, less than the minimum {}",
+                    share_out_amount,
+                    min
+                );
+                POOLS.save(&mut gamm_storage, pool.id, &pool)?;
+
+                send(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &sender,
+                    &pool.address(),
+                    vec![token_in.clone()],
+                )?;
+                mint(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &sender,
+                    &pool.share_denom(),
+                    share_out_amount,
+                )?;
+                let event = Event::new("pool_joined")
+                    .add_attribute("sender", &sender)
+                    .add_attribute("pool_id", pool.id.to_string())
+                    .add_attribute("tokens_in", coins_to_string(&[token_in]));
+                let data = MsgJoinSwapExternAmountInResponse {
+                    share_out_amount: share_out_amount.to_string(),
+                };
+                Ok(AppResponse {
+                    events: vec![event],
+                    data: Some(data.into()),
+                })
+            }
+            MsgExitSwapShareAmountIn::TYPE_URL => {
+                let msg = MsgExitSwapShareAmountIn::try_from(msg.value)?;
+                ensure_signer(&msg.sender, &sender)?;
+                let share_in_amount = parse_amount(&msg.share_in_amount)?;
+                let mut gamm_storage = prefixed(storage, NAMESPACE_GAMM);
+                let mut pool = load_pool(&gamm_storage, msg.pool_id)?;
+                let token_out_amount = pool.exit_single(share_in_amount, &msg.token_out_denom)?;
+                let min = parse_amount(&msg.token_out_min_amount)?;
+                ensure!(
+                    token_out_amount >= min,
+                    "Exiting returns {}, less than the minimum {}",
+                    token_out_amount,
+                    min
+                );
+                POOLS.save(&mut gamm_storage, pool.id, &pool)?;
+
+                let token_out = Coin::new(token_out_amount.u128(), msg.token_out_denom);
+                burn(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &sender,
+                    &pool.share_denom(),
+                    share_in_amount,
+                )?;
+                send(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &pool.address(),
+                    &sender,
+                    vec![token_out.clone()],
+                )?;
+                let event = Event::new("pool_exited")
+                    .add_attribute("sender", &sender)
+                    .add_attribute("pool_id", pool.id.to_string())
+                    .add_attribute("tokens_out", coins_to_string(&[token_out]));
+                let data = MsgExitSwapShareAmountInResponse {
+                    token_out_amount: token_out_amount.to_string(),
+                };
+                Ok(AppResponse {
+                    events: vec![event],
+                    data: Some(data.into()),
+                })
+            }
+            type_url => bail!("Unsupported stargate message {} from {}", type_url, sender),
+        }
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        msg: Empty,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!("Unexpected sudo msg {:?}", msg)
+    }
+
+    fn query(
+        &self,
+        _api: &dyn Api,
+        storage: &dyn Storage,
+        _querier: &dyn Querier,
+        _block: &BlockInfo,
+        request: StargateQuery,
+    ) -> AnyResult<Binary> {
+        let storage = prefixed_read(storage, NAMESPACE_GAMM);
+        let data = request.data;
+        match request.path.as_str() {
+            "/osmosis.gamm.v1beta1.Query/Pool" => {
+                let request = QueryPoolRequest::try_from(data)?;
+                let pool = load_pool(&storage, request.pool_id)?;
+                Ok(to_binary(&QueryPoolResponse {
+                    pool: Some(pool.to_any()),
+                })?)
+            }
+            "/osmosis.gamm.v1beta1.Query/NumPools" => {
+                let num_pools = POOL_COUNT.may_load(&storage)?.unwrap_or_default();
+                Ok(to_binary(&QueryNumPoolsResponse { num_pools })?)
+            }
+            "/osmosis.gamm.v1beta1.Query/PoolType" => {
+                let request = QueryPoolTypeRequest::try_from(data)?;
+                let pool = load_pool(&storage, request.pool_id)?;
+                Ok(to_binary(&QueryPoolTypeResponse {
+                    pool_type: pool.pool_type().to_string(),
+                })?)
+            }
+            "/osmosis.gamm.v1beta1.Query/TotalShares" => {
+                let request = QueryTotalSharesRequest::try_from(data)?;
+                let pool = load_pool(&storage, request.pool_id)?;
+                Ok(to_binary(&QueryTotalSharesResponse {
+                    total_shares: Some(proto_coin(&pool.share_denom(), pool.total_shares)),
+                })?)
+            }
+            "/osmosis.gamm.v1beta1.Query/TotalPoolLiquidity" => {
+                let request = QueryTotalPoolLiquidityRequest::try_from(data)?;
+                let pool = load_pool(&storage, request.pool_id)?;
+                Ok(to_binary(&QueryTotalPoolLiquidityResponse {
+                    liquidity: pool
+                        .assets
+                        .iter()
+                        .map(|c| proto_coin(&c.denom, c.amount))
+                        .collect(),
+                })?)
+            }
+            "/osmosis.gamm.v1beta1.Query/SpotPrice" => {
+                let request = QuerySpotPriceRequest::try_from(data)?;
+                let pool = load_pool(&storage, request.pool_id)?;
+                let spot_price =
+                    pool.spot_price(&request.base_asset_denom, &request.quote_asset_denom)?;
+                Ok(to_binary(&QuerySpotPriceResponse {
+                    spot_price: spot_price.to_string(),
+                })?)
+            }
+            "/osmosis.twap.v1beta1.Query/ArithmeticTwapToNow" => {
+                let request = ArithmeticTwapToNowRequest::try_from(data)?;
+                let pool = load_pool(&storage, request.pool_id)?;
+                let twap = pool.spot_price(&request.base_asset, &request.quote_asset)?;
+                Ok(to_binary(&ArithmeticTwapToNowResponse {
+                    arithmetic_twap: twap.to_string(),
+                })?)
+            }
+            "/osmosis.gamm.v1beta1.Query/EstimateSwapExactAmountIn" => {
+                let request = QuerySwapExactAmountInRequest::try_from(data)?;
+                ensure!(!request.routes.is_empty(), "Swap needs at least one route");
+                let mut token = parse_coin_str(&request.token_in)?;
+                for route in request.routes {
+                    let mut pool = load_pool(&storage, route.pool_id)?;
+                    let amount = pool.swap_exact_in(&token, &route.token_out_denom)?;
+                    token = Coin::new(amount.u128(), route.token_out_denom);
+                }
+                Ok(to_binary(&QuerySwapExactAmountInResponse {
+                    token_out_amount: token.amount.to_string(),
+                })?)
+            }
+            "/osmosis.gamm.v1beta1.Query/EstimateSwapExactAmountOut" => {
+                let request = QuerySwapExactAmountOutRequest::try_from(data)?;
+                ensure!(!request.routes.is_empty(), "Swap needs at least one route");
+                let mut token = parse_coin_str(&request.token_out)?;
+                for route in request.routes.into_iter().rev() {
+                    let mut pool = load_pool(&storage, route.pool_id)?;
+                    let amount = pool.swap_exact_out(&route.token_in_denom, &token)?;
+                    token = Coin::new(amount.u128(), route.token_in_denom);
+                }
+                Ok(to_binary(&QuerySwapExactAmountOutResponse {
+                    token_in_amount: token.amount.to_string(),
+                })?)
+            }
+            path => bail!("Unsupported stargate query {}", path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cosmwasm_std::testing::{MockApi, MockStorage};
+    use cosmwasm_std::{coin, CosmosMsg};
+
+    use crate::{
+        App, AppBuilder, BankKeeper, DistributionKeeper, Executor, FailingModule, StakeKeeper,
+        WasmKeeper,
+    };
+
+    type GammApp = App<
+        BankKeeper,
+        MockApi,
+        MockStorage,
+        FailingModule<Empty, Empty, Empty>,
+        WasmKeeper<Empty, Empty>,
+        StakeKeeper,
+        DistributionKeeper,
+        GammKeeper,
+    >;
+
+    const OWNER: &str = "owner";
+
+    fn setup() -> GammApp {
+        AppBuilder::new()
+            .with_stargate(GammKeeper::new())
+            .build(|router, _, storage| {
+                let funds = ["uatom", "uosmo", "ustable", "uusdc"]
+                    .iter()
+                    .map(|denom| coin(1_000_000_000_000_000, *denom))
+                    .collect();
+                router
+                    .bank
+                    .init_balance(storage, &Addr::unchecked(OWNER), funds)
+                    .unwrap();
+            })
+    }
+
+    fn create_balancer(app: &mut GammApp, assets: &[(u128, &str, u64)], swap_fee: &str) -> u64 {
+        let msg = MsgCreateBalancerPool {
+            sender: OWNER.to_string(),
+            pool_params: Some(PoolParams {
+                swap_fee: swap_fee.to_string(),
+                exit_fee: "0.01".to_string(),
+                smooth_weight_change_params: None,
+            }),
+            pool_assets: assets
+                .iter()
+                .map(|(amount, denom, weight)| PoolAsset {
+                    token: Some(proto_coin(denom, Uint128::new(*amount))),
+                    weight: weight.to_string(),
+                })
+                .collect(),
+            future_pool_governor: "".to_string(),
+        };
+        let res = app.execute(Addr::unchecked(OWNER), msg.into()).unwrap();
+        MsgCreateBalancerPoolResponse::try_from(res.data.unwrap())
+            .unwrap()
+            .pool_id
+    }
+
+    fn create_stableswap(app: &mut GammApp, assets: &[(u128, &str, u64)]) -> u64 {
+        let msg = MsgCreateStableswapPool {
+            sender: OWNER.to_string(),
+            pool_params: Some(StableSwapPoolParams {
+                swap_fee: "0".to_string(),
+                exit_fee: "0".to_string(),
+            }),
+            initial_pool_liquidity: assets
+                .iter()
+                .map(|(amount, denom, _)| proto_coin(denom, Uint128::new(*amount)))
+                .collect(),
+            scaling_factors: assets.iter().map(|(_, _, factor)| *factor).collect(),
+            future_pool_governor: "".to_string(),
+            scaling_factor_controller: "".to_string(),
+        };
+        let res = app.execute(Addr::unchecked(OWNER), msg.into()).unwrap();
+        MsgCreateStableswapPoolResponse::try_from(res.data.unwrap())
+            .unwrap()
+            .pool_id
+    }
+
+    fn balance(app: &GammApp, address: &str, denom: &str) -> u128 {
+        app.wrap()
+            .query_balance(address, denom)
+            .unwrap()
+            .amount
+            .u128()
+    }
+
+    fn spot_price(app: &GammApp, pool_id: u64, base: &str, quote: &str) -> String {
+        let request = QuerySpotPriceRequest {
+            pool_id,
+            base_asset_denom: base.to_string(),
+            quote_asset_denom: quote.to_string(),
+        };
+        let res: QuerySpotPriceResponse = app.wrap().query(&request.into()).unwrap();
+        res.spot_price
+    }
+
+    fn swap_in(route: &[(u64, &str)], token_in: Coin, min: u128) -> CosmosMsg {
+        MsgSwapExactAmountIn {
+            sender: OWNER.to_string(),
+            routes: route
+                .iter()
+                .map(|(pool_id, denom)| SwapAmountInRoute {
+                    pool_id: *pool_id,
+                    token_out_denom: denom.to_string(),
+                })
+                .collect(),
+            token_in: Some(proto_coin(&token_in.denom, token_in.amount)),
+            token_out_min_amount: min.to_string(),
+        }
+        .into()
+    }
+
+    fn estimate_in(app: &GammApp, route: &[(u64, &str)], token_in: &str) -> u128 {
+        let request = QuerySwapExactAmountInRequest {
+            sender: OWNER.to_string(),
+            pool_id: route[0].0,
+            token_in: token_in.to_string(),
+            routes: route
+                .iter()
+                .map(|(pool_id, denom)| SwapAmountInRoute {
+                    pool_id: *pool_id,
+                    token_out_denom: denom.to_string(),
+                })
+                .collect(),
+        };
+        let res: QuerySwapExactAmountInResponse = app.wrap().query(&request.into()).unwrap();
+        res.token_out_amount.parse().unwrap()
+    }
+
+    #[test]
+    fn create_balancer_pool() {
+        let mut app = setup();
+        let pool_id = create_balancer(
+            &mut app,
+            &[(1_000_000, "uosmo", 1), (2_000_000, "uatom", 1)],
+            "0.01",
+        );
+        assert_eq!(pool_id, 1);
+
+        // liquidity moved to the pool, shares to the creator
+        assert_eq!(balance(&app, "pool1", "uosmo"), 1_000_000);
+        assert_eq!(balance(&app, "pool1", "uatom"), 2_000_000);
+        assert_eq!(balance(&app, OWNER, "gamm/pool/1"), INIT_POOL_SHARES);
+
+        let res: QueryPoolResponse = app
+            .wrap()
+            .query(&QueryPoolRequest { pool_id }.into())
+            .unwrap();
+        let any = res.pool.unwrap();
+        assert_eq!(any.type_url, BalancerPool::TYPE_URL);
+        let pool = BalancerPool::try_from(Binary::from(any.value)).unwrap();
+        assert_eq!(pool.address, "pool1");
+        assert_eq!(pool.pool_assets[0].weight, "1073741824");
+        assert_eq!(pool.total_weight, "2147483648");
+        assert_eq!(pool.pool_params.unwrap().swap_fee, "0.01");
+
+        let res: QueryNumPoolsResponse = app
+            .wrap()
+            .query(&osmosis_std::types::osmosis::gamm::v1beta1::QueryNumPoolsRequest {}.into())
+            .unwrap();
+        assert_eq!(res.num_pools, 1);
+        let res: QueryTotalSharesResponse = app
+            .wrap()
+            .query(&QueryTotalSharesRequest { pool_id }.into())
+            .unwrap();
+        assert_eq!(
+            res.total_shares.unwrap(),
+            proto_coin("gamm/pool/1", Uint128::new(INIT_POOL_SHARES))
+        );
+
+        // an uosmo is worth two uatom, and the other way around
+        assert_eq!(spot_price(&app, pool_id, "uosmo", "uatom"), "2");
+        assert_eq!(spot_price(&app, pool_id, "uatom", "uosmo"), "0.5");
+        // without price history the twap is the spot price
+        let request = ArithmeticTwapToNowRequest {
+            pool_id,
+            base_asset: "uosmo".to_string(),
+            quote_asset: "uatom".to_string(),
+            start_time: None,
+        };
+        let res: ArithmeticTwapToNowResponse = app.wrap().query(&request.into()).unwrap();
+        assert_eq!(res.arithmetic_twap, "2");
+
+        // a pool needs two distinct assets
+        let err = app
+            .execute(
+                Addr::unchecked(OWNER),
+                MsgCreateBalancerPool {
+                    sender: OWNER.to_string(),
+                    pool_params: Some(PoolParams {
+                        swap_fee: "0".to_string(),
+                        exit_fee: "0".to_string(),
+                        smooth_weight_change_params: None,
+                    }),
+                    pool_assets: vec![PoolAsset {
+                        token: Some(proto_coin("uosmo", Uint128::new(100))),
+                        weight: "1".to_string(),
+                    }],
+                    future_pool_governor: "".to_string(),
+                }
+                .into(),
+            )
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Pools hold between 2 and 8 assets, got 1");
+    }
+
+    #[test]
+    fn join_and_exit_pool() {
+        let mut app = setup();
+        let pool_id = create_balancer(
+            &mut app,
+            &[(1_000_000, "uosmo", 1), (2_000_000, "uatom", 1)],
+            "0.01",
+        );
+        let shares = INIT_POOL_SHARES / 10;
+
+        let join = |max_osmo: u128| -> CosmosMsg {
+            MsgJoinPool {
+                sender: OWNER.to_string(),
+                pool_id,
+                share_out_amount: shares.to_string(),
+                token_in_maxs: vec![
+                    proto_coin("uosmo", Uint128::new(max_osmo)),
+                    proto_coin("uatom", Uint128::new(200_000)),
+                ],
+            }
+            .into()
+        };
+        let err = app
+            .execute(Addr::unchecked(OWNER), join(99_999))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Joining takes 100000uosmo, more than the maximum 99999"
+        );
+
+        // a tenth of the shares takes a tenth of the liquidity
+        let res = app.execute(Addr::unchecked(OWNER), join(100_000)).unwrap();
+        let res = MsgJoinPoolResponse::try_from(res.data.unwrap()).unwrap();
+        assert_eq!(
+            res.token_in,
+            vec![
+                proto_coin("uosmo", Uint128::new(100_000)),
+                proto_coin("uatom", Uint128::new(200_000))
+            ]
+        );
+        assert_eq!(balance(&app, "pool1", "uosmo"), 1_100_000);
+        assert_eq!(
+            balance(&app, OWNER, "gamm/pool/1"),
+            INIT_POOL_SHARES + shares
+        );
+
+        // the 1% exit fee stays in the pool
+        let exit = MsgExitPool {
+            sender: OWNER.to_string(),
+            pool_id,
+            share_in_amount: shares.to_string(),
+            token_out_mins: vec![proto_coin("uatom", Uint128::new(198_000))],
+        };
+        let res = app.execute(Addr::unchecked(OWNER), exit.into()).unwrap();
+        let res = MsgExitPoolResponse::try_from(res.data.unwrap()).unwrap();
+        assert_eq!(
+            res.token_out,
+            vec![
+                proto_coin("uosmo", Uint128::new(99_000)),
+                proto_coin("uatom", Uint128::new(198_000))
+            ]
+        );
+        assert_eq!(balance(&app, "pool1", "uosmo"), 1_001_000);
+        assert_eq!(balance(&app, "pool1", "uatom"), 2_002_000);
+        assert_eq!(balance(&app, OWNER, "gamm/pool/1"), INIT_POOL_SHARES);
+        let pool = app.read_module(|router, _, storage| router.stargate.pool(storage, pool_id));
+        assert_eq!(pool.unwrap().total_shares.u128(), INIT_POOL_SHARES);
+    }
+
+    #[test]
+    fn swap_exact_amount_in() {
+        let mut app = setup();
+        let pool_id = create_balancer(
+            &mut app,
+            &[(1_000_000, "uosmo", 1), (1_000_000, "uatom", 1)],
+            "0.01",
+        );
+
+        // 1% of 10_000 is kept as fee, the rest trades against x * y = k
+        assert_eq!(
+            estimate_in(&app, &[(pool_id, "uatom")], "10000uosmo"),
+            9_802
+        );
+        let err = app
+            .execute(
+                Addr::unchecked(OWNER),
+                swap_in(&[(pool_id, "uatom")], coin(10_000, "uosmo"), 9_803),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Swap returns 9802, less than the minimum 9803"
+        );
+
+        let res = app
+            .execute(
+                Addr::unchecked(OWNER),
+                swap_in(&[(pool_id, "uatom")], coin(10_000, "uosmo"), 9_802),
+            )
+            .unwrap();
+        let res = MsgSwapExactAmountInResponse::try_from(res.data.unwrap()).unwrap();
+        assert_eq!(res.token_out_amount, "9802");
+        assert_eq!(balance(&app, "pool1", "uosmo"), 1_010_000);
+        assert_eq!(balance(&app, "pool1", "uatom"), 990_198);
+    }
+
+    #[test]
+    fn weighted_swaps() {
+        let mut app = setup();
+        let pool_id = create_balancer(
+            &mut app,
+            &[(1_000_000, "uosmo", 1), (1_000_000, "uatom", 3)],
+            "0",
+        );
+
+        // out = 1_000_000 * (1 - (1 / 1.01)^(1/3)) = 3311.28
+        assert_eq!(
+            estimate_in(&app, &[(pool_id, "uatom")], "10000uosmo"),
+            3_311
+        );
+
+        // and buying it back the other way takes about as much uosmo
+        let request = QuerySwapExactAmountOutRequest {
+            sender: OWNER.to_string(),
+            pool_id,
+            routes: vec![SwapAmountOutRoute {
+                pool_id,
+                token_in_denom: "uosmo".to_string(),
+            }],
+            token_out: "3311uatom".to_string(),
+        };
+        let res: QuerySwapExactAmountOutResponse = app.wrap().query(&request.into()).unwrap();
+        let token_in: u128 = res.token_in_amount.parse().unwrap();
+        assert!((9_990..=10_000).contains(&token_in), "{}", token_in);
+    }
+
+    #[test]
+    fn multi_hop_swap_exact_amount_out() {
+        let mut app = setup();
+        create_balancer(
+            &mut app,
+            &[(1_000_000, "uosmo", 1), (1_000_000, "uatom", 1)],
+            "0.01",
+        );
+        create_balancer(
+            &mut app,
+            &[(1_000_000, "uatom", 1), (4_000_000, "uusdc", 1)],
+            "0.01",
+        );
+        let routes = vec![
+            SwapAmountOutRoute {
+                pool_id: 1,
+                token_in_denom: "uosmo".to_string(),
+            },
+            SwapAmountOutRoute {
+                pool_id: 2,
+                token_in_denom: "uatom".to_string(),
+            },
+        ];
+
+        let request = QuerySwapExactAmountOutRequest {
+            sender: OWNER.to_string(),
+            pool_id: 1,
+            routes: routes.clone(),
+            token_out: "20000uusdc".to_string(),
+        };
+        let res: QuerySwapExactAmountOutResponse = app.wrap().query(&request.into()).unwrap();
+        let estimate: u128 = res.token_in_amount.parse().unwrap();
+
+        let swap = |max: u128| -> CosmosMsg {
+            MsgSwapExactAmountOut {
+                sender: OWNER.to_string(),
+                routes: routes.clone(),
+                token_in_max_amount: max.to_string(),
+                token_out: Some(proto_coin("uusdc", Uint128::new(20_000))),
+            }
+            .into()
+        };
+        app.execute(Addr::unchecked(OWNER), swap(estimate - 1))
+            .unwrap_err();
+
+        let osmo_before = balance(&app, OWNER, "uosmo");
+        let usdc_before = balance(&app, OWNER, "uusdc");
+        let res = app.execute(Addr::unchecked(OWNER), swap(estimate)).unwrap();
+        let res = MsgSwapExactAmountOutResponse::try_from(res.data.unwrap()).unwrap();
+        assert_eq!(res.token_in_amount, estimate.to_string());
+        assert_eq!(balance(&app, OWNER, "uosmo"), osmo_before - estimate);
+        assert_eq!(balance(&app, OWNER, "uusdc"), usdc_before + 20_000);
+
+        // the uatom bought in the first pool was sold in the second
+        let atom_in_pools = balance(&app, "pool1", "uatom") + balance(&app, "pool2", "uatom");
+        assert_eq!(atom_in_pools, 2_000_000);
+        // about 5_000 uatom, at 4 uusdc each with 1% fees twice
+        assert!((5_100..5_200).contains(&estimate), "{}", estimate);
+    }
+
+    #[test]
+    fn join_swap_extern_amount_in() {
+        let mut app = setup();
+        let pool_id = create_balancer(
+            &mut app,
+            &[(1_000_000, "uosmo", 1), (1_000_000, "uatom", 1)],
+            "0",
+        );
+
+        // shares = total * (sqrt(1 + 21_000 / 1_000_000) - 1) = 1.0445446e18
+        let join = MsgJoinSwapExternAmountIn {
+            sender: OWNER.to_string(),
+            pool_id,
+            token_in: Some(proto_coin("uosmo", Uint128::new(21_000))),
+            share_out_min_amount: "1044544000000000000".to_string(),
+        };
+        let res = app.execute(Addr::unchecked(OWNER), join.into()).unwrap();
+        let res = MsgJoinSwapExternAmountInResponse::try_from(res.data.unwrap()).unwrap();
+        let shares: u128 = res.share_out_amount.parse().unwrap();
+        assert!(
+            (1_044_544_000_000_000_000..1_044_546_000_000_000_000).contains(&shares),
+            "{}",
+            shares
+        );
+        assert_eq!(balance(&app, "pool1", "uosmo"), 1_021_000);
+        assert_eq!(
+            balance(&app, OWNER, "gamm/pool/1"),
+            INIT_POOL_SHARES + shares
+        );
+
+        let join = MsgJoinSwapExternAmountIn {
+            sender: OWNER.to_string(),
+            pool_id,
+            token_in: Some(proto_coin("uosmo", Uint128::new(100))),
+            share_out_min_amount: INIT_POOL_SHARES.to_string(),
+        };
+        app.execute(Addr::unchecked(OWNER), join.into())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn stableswap_pools() {
+        let mut app = setup();
+        let pool_id = create_stableswap(
+            &mut app,
+            &[(1_000_000, "uusdc", 1), (1_000_000, "ustable", 1)],
+        );
+        assert_eq!(spot_price(&app, pool_id, "uusdc", "ustable"), "1");
+        let res: QueryPoolResponse = app
+            .wrap()
+            .query(&QueryPoolRequest { pool_id }.into())
+            .unwrap();
+        assert_eq!(res.pool.unwrap().type_url, StableSwapPool::TYPE_URL);
+
+        // close to one for one around the peg, rounded in favour of the pool
+        assert_eq!(estimate_in(&app, &[(pool_id, "ustable")], "1000uusdc"), 999);
+
+        // exiting 1% returns 10_000 of each, the ustable is then sold in the pool
+        let exit = MsgExitSwapShareAmountIn {
+            sender: OWNER.to_string(),
+            pool_id,
+            token_out_denom: "uusdc".to_string(),
+            share_in_amount: (INIT_POOL_SHARES / 100).to_string(),
+            token_out_min_amount: "19999".to_string(),
+        };
+        let res = app.execute(Addr::unchecked(OWNER), exit.into()).unwrap();
+        let res = MsgExitSwapShareAmountInResponse::try_from(res.data.unwrap()).unwrap();
+        assert_eq!(res.token_out_amount, "19999");
+        assert_eq!(balance(&app, "pool1", "uusdc"), 980_001);
+        assert_eq!(balance(&app, "pool1", "ustable"), 1_000_000);
+
+        // a scaling factor of 1000 prices each uusdc at 1000 ustable
+        let pool_id = create_stableswap(
+            &mut app,
+            &[(1_000_000, "uusdc", 1), (1_000_000_000, "ustable", 1000)],
+        );
+        assert_eq!(spot_price(&app, pool_id, "uusdc", "ustable"), "1000");
+        assert_eq!(
+            estimate_in(&app, &[(pool_id, "ustable")], "1000uusdc"),
+            999_999
+        );
+    }
+
+    #[test]
+    fn messages_are_signed_by_their_sender() {
+        let mut app = setup();
+        create_balancer(
+            &mut app,
+            &[(1_000_000, "uosmo", 1), (1_000_000, "uatom", 1)],
+            "0.01",
+        );
+        let err = app
+            .execute(
+                Addr::unchecked("mallory"),
+                swap_in(&[(1, "uatom")], coin(10_000, "uosmo"), 0),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Message signed by owner was sent by mallory"
+        );
+
+        let err = app
+            .execute(
+                Addr::unchecked(OWNER),
+                swap_in(&[(2, "uatom")], coin(10_000, "uosmo"), 0),
+            )
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Pool 2 does not exist");
+    }
+
+    #[test]
+    fn fractional_powers() {
+        let pow = |base: &str, exp: &str| {
+            pow(
+                Decimal256::from_str(base).unwrap(),
+                Decimal256::from_str(exp).unwrap(),
+            )
+            .unwrap()
+        };
+        let close = |value: Decimal256, expected: &str| {
+            let expected = Decimal256::from_str(expected).unwrap();
+            assert!(
+                value.abs_diff(expected) < Decimal256::from_str("0.00000001").unwrap(),
+                "{} != {}",
+                value,
+                expected
+            );
+        };
+        close(pow("1.5", "0.5"), "1.224744871391589049");
+        close(pow("0.5", "2.5"), "0.176776695296636881");
+        close(pow("1.9", "0.25"), "1.174054885944018500");
+        assert_eq!(pow("1.1", "2"), Decimal256::from_str("1.21").unwrap());
+        assert!(super::pow(Decimal256::percent(200), Decimal256::one()).is_err());
+    }
+}
@@ -13,9 +13,16 @@ mod contracts;
 pub mod custom_handler;
 pub mod error;
 mod executor;
+#[cfg(feature = "osmosis")]
+mod gamm;
+#[cfg(feature = "osmosis")]
+mod lockup;
 mod module;
+#[cfg(feature = "osmosis")]
+mod osmosis;
 mod prefixed_storage;
 mod staking;
+mod stargate;
 mod test_helpers;
 mod transactions;
 mod wasm;
@@ -27,6 +34,13 @@ pub use crate::app::{
 pub use crate::bank::{Bank, BankKeeper, BankSudo};
 pub use crate::contracts::{Contract, ContractWrapper};
 pub use crate::executor::{AppResponse, Executor};
+#[cfg(feature = "osmosis")]
+pub use crate::gamm::{GammKeeper, GammPool, PoolKind};
+#[cfg(feature = "osmosis")]
+pub use crate::lockup::{IncentivesInfo, LockupKeeper};
 pub use crate::module::{FailingModule, Module};
+#[cfg(feature = "osmosis")]
+pub use crate::osmosis::OsmosisKeeper;
 pub use crate::staking::{DistributionKeeper, StakeKeeper, Staking, StakingInfo, StakingSudo};
+pub use crate::stargate::{Stargate, StargateFailing, StargateMsg, StargateQuery};
 pub use crate::wasm::{Wasm, WasmKeeper, WasmSudo};
@@ -0,0 +1,1280 @@
+use std::convert::TryFrom;
+
+use anyhow::{anyhow, bail, ensure, Result as AnyResult};
+use cosmwasm_std::{
+    to_binary, Addr, Api, BankMsg, Binary, BlockInfo, Coin, CustomQuery, Empty, Event, Order,
+    Querier, Storage, Timestamp, Uint128,
+};
+use cw_storage_plus::{Item, Map};
+use osmosis_std::shim::{Duration, Timestamp as ProtoTimestamp};
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::epochs::v1beta1::{
+    EpochInfo, QueryCurrentEpochRequest, QueryCurrentEpochResponse, QueryEpochsInfoResponse,
+};
+use osmosis_std::types::osmosis::incentives::{
+    Gauge, GaugeByIdRequest, GaugeByIdResponse, GaugesResponse, MsgAddToGauge,
+    MsgAddToGaugeResponse, MsgCreateGauge, MsgCreateGaugeResponse,
+};
+use osmosis_std::types::osmosis::lockup::{
+    AccountLockedCoinsRequest, AccountLockedCoinsResponse, AccountLockedLongerDurationDenomRequest,
+    AccountLockedLongerDurationDenomResponse, AccountLockedLongerDurationNotUnlockingOnlyRequest,
+    AccountLockedLongerDurationNotUnlockingOnlyResponse, AccountLockedLongerDurationRequest,
+    AccountLockedLongerDurationResponse, AccountLockedPastTimeNotUnlockingOnlyRequest,
+    AccountLockedPastTimeNotUnlockingOnlyResponse, AccountLockedPastTimeRequest,
+    AccountLockedPastTimeResponse, AccountUnlockableCoinsRequest, AccountUnlockableCoinsResponse,
+    AccountUnlockingCoinsRequest, AccountUnlockingCoinsResponse, LockedRequest, LockedResponse,
+    MsgBeginUnlocking, MsgBeginUnlockingAll, MsgBeginUnlockingAllResponse,
+    MsgBeginUnlockingResponse, MsgLockTokens, MsgLockTokensResponse, PeriodLock, QueryCondition,
+};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::app::CosmosRouter;
+use crate::executor::AppResponse;
+use crate::module::Module;
+use crate::prefixed_storage::{prefixed, prefixed_read};
+use crate::stargate::{Stargate, StargateMsg, StargateQuery};
+
+/// The only `QueryCondition::lock_query_type` gauges support, locks at least as long as a duration
+const BY_DURATION: i32 = 0;
+
+// Contains the epoch the incentives are paid at
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct IncentivesInfo {
+    /// Identifier of the epoch, as the epochs module reports it
+    pub epoch_identifier: String,
+    /// Length of an epoch in seconds
+    pub epoch_duration: u64,
+}
+
+impl Default for IncentivesInfo {
+    fn default() -> Self {
+        IncentivesInfo {
+            epoch_identifier: "day".to_string(),
+            epoch_duration: 24 * 60 * 60,
+        }
+    }
+}
+
+/// Coins locked by `owner` for `duration` seconds. `end_time` is set once the lock begins
+/// unlocking, the coins go back to the owner at the first `end_block` past it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+struct Lock {
+    id: u64,
+    owner: Addr,
+    duration: u64,
+    end_time: Option<Timestamp>,
+    coins: Vec<Coin>,
+}
+
+impl Lock {
+    fn is_unlocking(&self) -> bool {
+        self.end_time.is_some()
+    }
+
+    fn amount(&self, denom: &str) -> Uint128 {
+        amount_of(&self.coins, denom)
+    }
+
+    /// Whether the lock still holds its coins at `time`
+    fn locked_past(&self, time: Timestamp) -> bool {
+        match self.end_time {
+            Some(end_time) => end_time > time,
+            None => true,
+        }
+    }
+
+    fn to_period_lock(&self) -> PeriodLock {
+        PeriodLock {
+            id: self.id,
+            owner: self.owner.to_string(),
+            duration: Some(to_duration(self.duration)),
+            end_time: self.end_time.map(to_proto_timestamp),
+            coins: to_proto_coins(&self.coins),
+        }
+    }
+}
+
+/// Rewards paid each epoch to the locks of `denom` at least `duration` seconds long,
+/// in proportion to the amount they lock.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+struct GaugeInfo {
+    id: u64,
+    is_perpetual: bool,
+    denom: String,
+    duration: u64,
+    coins: Vec<Coin>,
+    start_time: Timestamp,
+    num_epochs_paid_over: u64,
+    filled_epochs: u64,
+    distributed_coins: Vec<Coin>,
+}
+
+impl GaugeInfo {
+    fn is_active(&self, time: Timestamp) -> bool {
+        self.start_time <= time
+            && (self.is_perpetual || self.filled_epochs < self.num_epochs_paid_over)
+    }
+
+    /// What is left to pay out this epoch: everything for a perpetual gauge, an even share
+    /// of the remaining epochs otherwise
+    fn epoch_rewards(&self) -> Vec<Coin> {
+        self.coins
+            .iter()
+            .map(|coin| {
+                let left = coin.amount - amount_of(&self.distributed_coins, &coin.denom);
+                let amount = if self.is_perpetual {
+                    left
+                } else {
+                    left / Uint128::from(self.num_epochs_paid_over - self.filled_epochs)
+                };
+                Coin::new(amount.u128(), coin.denom.clone())
+            })
+            .filter(|coin| !coin.amount.is_zero())
+            .collect()
+    }
+
+    fn to_gauge(&self) -> Gauge {
+        Gauge {
+            id: self.id,
+            is_perpetual: self.is_perpetual,
+            distribute_to: Some(QueryCondition {
+                lock_query_type: BY_DURATION,
+                denom: self.denom.clone(),
+                duration: Some(to_duration(self.duration)),
+                timestamp: None,
+            }),
+            coins: to_proto_coins(&self.coins),
+            start_time: Some(to_proto_timestamp(self.start_time)),
+            num_epochs_paid_over: self.num_epochs_paid_over,
+            filled_epochs: self.filled_epochs,
+            distributed_coins: to_proto_coins(&self.distributed_coins),
+        }
+    }
+}
+
+/// The epoch counting, started by the first `end_block`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+struct EpochState {
+    start_time: Timestamp,
+    current_epoch: u64,
+    current_epoch_start_time: Timestamp,
+    current_epoch_start_height: u64,
+}
+
+const INCENTIVES_INFO: Item<IncentivesInfo> = Item::new("incentives_info");
+const EPOCH: Item<EpochState> = Item::new("epoch");
+const LOCKS: Map<u64, Lock> = Map::new("locks");
+const LAST_LOCK_ID: Item<u64> = Item::new("last_lock_id");
+const GAUGES: Map<u64, GaugeInfo> = Map::new("gauges");
+const GAUGE_COUNT: Item<u64> = Item::new("gauge_count");
+
+pub const NAMESPACE_LOCKUP: &[u8] = b"lockup";
+
+fn to_duration(seconds: u64) -> Duration {
+    Duration {
+        seconds: seconds as i64,
+        nanos: 0,
+    }
+}
+
+fn to_proto_timestamp(time: Timestamp) -> ProtoTimestamp {
+    ProtoTimestamp {
+        seconds: time.seconds() as i64,
+        nanos: time.subsec_nanos() as i32,
+    }
+}
+
+fn from_proto_timestamp(time: Option<ProtoTimestamp>) -> Timestamp {
+    let time = time.unwrap_or_default();
+    Timestamp::from_seconds(time.seconds.max(0) as u64).plus_nanos(time.nanos.max(0) as u64)
+}
+
+fn duration_seconds(duration: Option<Duration>) -> u64 {
+    duration
+        .map(|d| d.seconds.max(0) as u64)
+        .unwrap_or_default()
+}
+
+fn to_proto_coins(coins: &[Coin]) -> Vec<ProtoCoin> {
+    coins
+        .iter()
+        .map(|coin| ProtoCoin {
+            denom: coin.denom.clone(),
+            amount: coin.amount.to_string(),
+        })
+        .collect()
+}
+
+fn parse_coins(coins: Vec<ProtoCoin>) -> AnyResult<Vec<Coin>> {
+    let coins = coins
+        .into_iter()
+        .map(Coin::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    ensure!(
+        coins.iter().all(|coin| !coin.amount.is_zero()),
+        "Coins must be positive"
+    );
+    Ok(coins)
+}
+
+fn amount_of(coins: &[Coin], denom: &str) -> Uint128 {
+    coins
+        .iter()
+        .filter(|coin| coin.denom == denom)
+        .map(|coin| coin.amount)
+        .sum()
+}
+
+fn add_coins(coins: &mut Vec<Coin>, more: &[Coin]) {
+    for coin in more {
+        match coins.iter_mut().find(|c| c.denom == coin.denom) {
+            Some(existing) => existing.amount += coin.amount,
+            None => coins.push(coin.clone()),
+        }
+    }
+}
+
+fn coins_to_string(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(|c| format!("{}{}", c.amount, c.denom))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn ensure_owner(owner: &str, sender: &Addr) -> AnyResult<()> {
+    ensure!(
+        owner == sender.as_str(),
+        "Message signed by {} was sent by {}",
+        owner,
+        sender
+    );
+    Ok(())
+}
+
+fn load_lock(storage: &dyn Storage, id: u64) -> AnyResult<Lock> {
+    LOCKS
+        .may_load(storage, id)?
+        .ok_or_else(|| anyhow!("Lock {} does not exist", id))
+}
+
+fn account_locks(storage: &dyn Storage, owner: &str) -> AnyResult<Vec<Lock>> {
+    let locks = LOCKS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, lock)| lock))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(locks
+        .into_iter()
+        .filter(|lock| lock.owner == owner)
+        .collect())
+}
+
+fn total_coins<'a>(locks: impl Iterator<Item = &'a Lock>) -> Vec<ProtoCoin> {
+    let mut total = vec![];
+    for lock in locks {
+        add_coins(&mut total, &lock.coins);
+    }
+    to_proto_coins(&total)
+}
+
+fn period_locks(locks: impl Iterator<Item = Lock>) -> Vec<PeriodLock> {
+    locks.map(|lock| lock.to_period_lock()).collect()
+}
+
+/// Simulates the Osmosis lockup module, with the incentives and epochs modules that pay
+/// rewards to the locks. Locked coins are held in the bank by the lockup module account,
+/// gauge rewards by the incentives module account.
+///
+/// Nothing happens on its own when blocks pass: `end_block` ends the epochs the block time
+/// went past, paying out the gauges, and then hands back the coins of matured locks.
+pub struct LockupKeeper {
+    lockup_addr: Addr,
+    incentives_addr: Addr,
+}
+
+impl Default for LockupKeeper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LockupKeeper {
+    pub fn new() -> Self {
+        LockupKeeper {
+            // The accounts of the modules, holding the locked coins and the gauge rewards.
+            lockup_addr: Addr::unchecked("lockup_module"),
+            incentives_addr: Addr::unchecked("incentives_module"),
+        }
+    }
+
+    /// Provides the epoch the gauges pay at, a day by default
+    pub fn setup(&self, storage: &mut dyn Storage, info: IncentivesInfo) -> AnyResult<()> {
+        let mut storage = prefixed(storage, NAMESPACE_LOCKUP);
+
+        INCENTIVES_INFO.save(&mut storage, &info)?;
+        Ok(())
+    }
+
+    /// Runs what Osmosis does between blocks at `block`. The first call starts counting
+    /// epochs, later ones end every epoch that is over by then, paying the gauges at each.
+    /// Then the locks that finished unlocking return their coins to their owner.
+    pub fn end_block<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        let mut events = vec![];
+
+        let lockup_storage = prefixed_read(storage, NAMESPACE_LOCKUP);
+        let info = INCENTIVES_INFO
+            .may_load(&lockup_storage)?
+            .unwrap_or_default();
+        let epoch = EPOCH.may_load(&lockup_storage)?;
+        match epoch {
+            None => {
+                let epoch = EpochState {
+                    start_time: block.time,
+                    current_epoch: 1,
+                    current_epoch_start_time: block.time,
+                    current_epoch_start_height: block.height,
+                };
+                EPOCH.save(&mut prefixed(storage, NAMESPACE_LOCKUP), &epoch)?;
+                events.push(epoch_event("epoch_start", &info, 1));
+            }
+            Some(mut epoch) => {
+                let mut next_start = epoch
+                    .current_epoch_start_time
+                    .plus_seconds(info.epoch_duration);
+                while block.time >= next_start {
+                    events.push(epoch_event("epoch_end", &info, epoch.current_epoch));
+                    events.extend(self.distribute(api, storage, router, block, next_start)?);
+                    epoch.current_epoch += 1;
+                    epoch.current_epoch_start_time = next_start;
+                    epoch.current_epoch_start_height = block.height;
+                    events.push(epoch_event("epoch_start", &info, epoch.current_epoch));
+                    next_start = next_start.plus_seconds(info.epoch_duration);
+                }
+                EPOCH.save(&mut prefixed(storage, NAMESPACE_LOCKUP), &epoch)?;
+            }
+        }
+
+        let lockup_storage = prefixed_read(storage, NAMESPACE_LOCKUP);
+        let matured = LOCKS
+            .range(&lockup_storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, lock)| lock))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|lock| !lock.locked_past(block.time))
+            .collect::<Vec<_>>();
+        for lock in matured {
+            LOCKS.remove(&mut prefixed(storage, NAMESPACE_LOCKUP), lock.id);
+            send(
+                api,
+                storage,
+                router,
+                block,
+                &self.lockup_addr,
+                &lock.owner,
+                lock.coins.clone(),
+            )?;
+            events.push(
+                Event::new("unlock")
+                    .add_attribute("period_lock_id", lock.id.to_string())
+                    .add_attribute("owner", &lock.owner)
+                    .add_attribute("amount", coins_to_string(&lock.coins)),
+            );
+        }
+
+        Ok(AppResponse { events, data: None })
+    }
+
+    /// Pays the rewards of this epoch of every active gauge to the locks it targets
+    fn distribute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        epoch_end: Timestamp,
+    ) -> AnyResult<Vec<Event>>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        let lockup_storage = prefixed_read(storage, NAMESPACE_LOCKUP);
+        let gauges = GAUGES
+            .range(&lockup_storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, gauge)| gauge))
+            .collect::<Result<Vec<_>, _>>()?;
+        let locks = LOCKS
+            .range(&lockup_storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, lock)| lock))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut events = vec![];
+        for mut gauge in gauges.into_iter().filter(|g| g.is_active(epoch_end)) {
+            let eligible: Vec<&Lock> = locks
+                .iter()
+                .filter(|lock| lock.duration >= gauge.duration && lock.locked_past(epoch_end))
+                .filter(|lock| !lock.amount(&gauge.denom).is_zero())
+                .collect();
+            let total: Uint128 = eligible.iter().map(|lock| lock.amount(&gauge.denom)).sum();
+
+            // the rewards of each owner, summed over their locks
+            let mut payouts: Vec<(Addr, Vec<Coin>)> = vec![];
+            if !total.is_zero() {
+                let rewards = gauge.epoch_rewards();
+                for lock in eligible {
+                    let share: Vec<Coin> = rewards
+                        .iter()
+                        .map(|reward| {
+                            let amount = reward
+                                .amount
+                                .multiply_ratio(lock.amount(&gauge.denom), total);
+                            Coin::new(amount.u128(), reward.denom.clone())
+                        })
+                        .filter(|coin| !coin.amount.is_zero())
+                        .collect();
+                    match payouts.iter_mut().find(|(owner, _)| *owner == lock.owner) {
+                        Some((_, coins)) => add_coins(coins, &share),
+                        None => payouts.push((lock.owner.clone(), share)),
+                    }
+                }
+            }
+            for (_, coins) in &payouts {
+                add_coins(&mut gauge.distributed_coins, coins);
+            }
+            gauge.filled_epochs += 1;
+            GAUGES.save(&mut prefixed(storage, NAMESPACE_LOCKUP), gauge.id, &gauge)?;
+
+            for (owner, coins) in payouts {
+                send(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &self.incentives_addr,
+                    &owner,
+                    coins.clone(),
+                )?;
+                events.push(
+                    Event::new("distribution")
+                        .add_attribute("gauge_id", gauge.id.to_string())
+                        .add_attribute("receiver", &owner)
+                        .add_attribute("amount", coins_to_string(&coins)),
+                );
+            }
+        }
+        Ok(events)
+    }
+
+    fn lock_tokens<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: MsgLockTokens,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        ensure_owner(&msg.owner, &sender)?;
+        let coins = parse_coins(msg.coins)?;
+        ensure!(!coins.is_empty(), "Cannot lock no coins");
+        let duration = duration_seconds(msg.duration);
+
+        let mut lockup_storage = prefixed(storage, NAMESPACE_LOCKUP);
+        // like Osmosis, a single coin joins the lock of that denom and duration if there is one
+        let existing = match coins.as_slice() {
+            [coin] => account_locks(&lockup_storage, sender.as_str())?
+                .into_iter()
+                .find(|lock| {
+                    !lock.is_unlocking()
+                        && lock.duration == duration
+                        && lock.coins.len() == 1
+                        && lock.coins[0].denom == coin.denom
+                }),
+            _ => None,
+        };
+        let lock = match existing {
+            Some(mut lock) => {
+                add_coins(&mut lock.coins, &coins);
+                lock
+            }
+            None => {
+                let id = LAST_LOCK_ID.may_load(&lockup_storage)?.unwrap_or_default() + 1;
+                LAST_LOCK_ID.save(&mut lockup_storage, &id)?;
+                Lock {
+                    id,
+                    owner: sender.clone(),
+                    duration,
+                    end_time: None,
+                    coins: coins.clone(),
+                }
+            }
+        };
+        LOCKS.save(&mut lockup_storage, lock.id, &lock)?;
+
+        send(
+            api,
+            storage,
+            router,
+            block,
+            &sender,
+            &self.lockup_addr,
+            coins.clone(),
+        )?;
+        let event = Event::new("lock_tokens")
+            .add_attribute("period_lock_id", lock.id.to_string())
+            .add_attribute("owner", &sender)
+            .add_attribute("amount", coins_to_string(&coins))
+            .add_attribute("duration", duration.to_string());
+        let data = MsgLockTokensResponse { id: lock.id };
+        Ok(AppResponse {
+            events: vec![event],
+            data: Some(data.into()),
+        })
+    }
+
+    /// Starts unlocking `coins` of lock `id`, all of it when `coins` is empty. A partial
+    /// unlock splits the unlocking coins off into a new lock, as Osmosis does.
+    fn begin_unlocking(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        sender: &Addr,
+        id: u64,
+        coins: Vec<Coin>,
+    ) -> AnyResult<Lock> {
+        let mut lockup_storage = prefixed(storage, NAMESPACE_LOCKUP);
+        let mut lock = load_lock(&lockup_storage, id)?;
+        ensure!(
+            lock.owner == *sender,
+            "Lock {} is owned by {}, not {}",
+            id,
+            lock.owner,
+            sender
+        );
+        ensure!(!lock.is_unlocking(), "Lock {} is already unlocking", id);
+        let end_time = Some(block.time.plus_seconds(lock.duration));
+
+        let partial = !coins.is_empty() && {
+            let mut all = lock.coins.clone();
+            all.sort_by(|a, b| a.denom.cmp(&b.denom));
+            let mut requested = coins.clone();
+            requested.sort_by(|a, b| a.denom.cmp(&b.denom));
+            all != requested
+        };
+        if !partial {
+            lock.end_time = end_time;
+            LOCKS.save(&mut lockup_storage, id, &lock)?;
+            return Ok(lock);
+        }
+
+        for coin in &coins {
+            let locked = lock
+                .coins
+                .iter_mut()
+                .find(|c| c.denom == coin.denom)
+                .filter(|c| c.amount >= coin.amount)
+                .ok_or_else(|| anyhow!("Lock {} holds less than {}", id, coin))?;
+            locked.amount -= coin.amount;
+        }
+        lock.coins.retain(|coin| !coin.amount.is_zero());
+        LOCKS.save(&mut lockup_storage, id, &lock)?;
+
+        let new_id = LAST_LOCK_ID.may_load(&lockup_storage)?.unwrap_or_default() + 1;
+        LAST_LOCK_ID.save(&mut lockup_storage, &new_id)?;
+        let unlocking = Lock {
+            id: new_id,
+            owner: lock.owner,
+            duration: lock.duration,
+            end_time,
+            coins,
+        };
+        LOCKS.save(&mut lockup_storage, new_id, &unlocking)?;
+        Ok(unlocking)
+    }
+
+    fn create_gauge<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: MsgCreateGauge,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        ensure_owner(&msg.owner, &sender)?;
+        let condition = msg
+            .distribute_to
+            .ok_or_else(|| anyhow!("Missing distribute_to"))?;
+        ensure!(
+            condition.lock_query_type == BY_DURATION,
+            "Only gauges distributing by duration are supported"
+        );
+        ensure!(
+            msg.is_perpetual || msg.num_epochs_paid_over > 0,
+            "Non perpetual gauges pay over at least one epoch"
+        );
+        let coins = parse_coins(msg.coins)?;
+        let start_time = match msg.start_time {
+            Some(time) if time.seconds > 0 => from_proto_timestamp(Some(time)),
+            _ => block.time,
+        };
+
+        let mut lockup_storage = prefixed(storage, NAMESPACE_LOCKUP);
+        let id = GAUGE_COUNT.may_load(&lockup_storage)?.unwrap_or_default() + 1;
+        GAUGE_COUNT.save(&mut lockup_storage, &id)?;
+        let gauge = GaugeInfo {
+            id,
+            is_perpetual: msg.is_perpetual,
+            denom: condition.denom,
+            duration: duration_seconds(condition.duration),
+            coins: coins.clone(),
+            start_time,
+            num_epochs_paid_over: if msg.is_perpetual {
+                1
+            } else {
+                msg.num_epochs_paid_over
+            },
+            filled_epochs: 0,
+            distributed_coins: vec![],
+        };
+        GAUGES.save(&mut lockup_storage, id, &gauge)?;
+
+        send(
+            api,
+            storage,
+            router,
+            block,
+            &sender,
+            &self.incentives_addr,
+            coins,
+        )?;
+        let event = Event::new("create_gauge").add_attribute("gauge_id", id.to_string());
+        Ok(AppResponse {
+            events: vec![event],
+            data: Some(MsgCreateGaugeResponse {}.into()),
+        })
+    }
+
+    fn add_to_gauge<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: MsgAddToGauge,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        ensure_owner(&msg.owner, &sender)?;
+        let gauge_id = msg.gauge_id;
+        let rewards = parse_coins(msg.rewards)?;
+        let mut lockup_storage = prefixed(storage, NAMESPACE_LOCKUP);
+        let mut gauge = GAUGES
+            .may_load(&lockup_storage, gauge_id)?
+            .ok_or_else(|| anyhow!("Gauge {} does not exist", gauge_id))?;
+        ensure!(
+            gauge.is_perpetual || gauge.filled_epochs < gauge.num_epochs_paid_over,
+            "Gauge {} is finished",
+            gauge.id
+        );
+        add_coins(&mut gauge.coins, &rewards);
+        GAUGES.save(&mut lockup_storage, gauge.id, &gauge)?;
+
+        send(
+            api,
+            storage,
+            router,
+            block,
+            &sender,
+            &self.incentives_addr,
+            rewards,
+        )?;
+        let event = Event::new("add_to_gauge").add_attribute("gauge_id", gauge.id.to_string());
+        Ok(AppResponse {
+            events: vec![event],
+            data: Some(MsgAddToGaugeResponse {}.into()),
+        })
+    }
+
+    fn epoch_info(&self, storage: &dyn Storage, block: &BlockInfo) -> AnyResult<EpochInfo> {
+        let info = INCENTIVES_INFO.may_load(storage)?.unwrap_or_default();
+        let epoch = EPOCH.may_load(storage)?;
+        let (start, current_epoch, current_start, height, started) = match epoch {
+            Some(epoch) => (
+                epoch.start_time,
+                epoch.current_epoch,
+                epoch.current_epoch_start_time,
+                epoch.current_epoch_start_height,
+                true,
+            ),
+            None => (block.time, 0, block.time, 0, false),
+        };
+        Ok(EpochInfo {
+            identifier: info.epoch_identifier,
+            start_time: Some(to_proto_timestamp(start)),
+            duration: Some(to_duration(info.epoch_duration)),
+            current_epoch: current_epoch as i64,
+            current_epoch_start_time: Some(to_proto_timestamp(current_start)),
+            epoch_counting_started: started,
+            current_epoch_start_height: height as i64,
+        })
+    }
+}
+
+fn epoch_event(ty: &str, info: &IncentivesInfo, epoch: u64) -> Event {
+    Event::new(ty)
+        .add_attribute("epoch_number", epoch.to_string())
+        .add_attribute("identifier", &info.epoch_identifier)
+}
+
+/// Moves the non zero `coins` from `from` to `to` through the bank
+fn send<ExecC, QueryC>(
+    api: &dyn Api,
+    storage: &mut dyn Storage,
+    router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+    block: &BlockInfo,
+    from: &Addr,
+    to: &Addr,
+    coins: Vec<Coin>,
+) -> AnyResult<()>
+where
+    ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+    QueryC: CustomQuery + DeserializeOwned + 'static,
+{
+    let amount: Vec<Coin> = coins.into_iter().filter(|c| !c.amount.is_zero()).collect();
+    if !amount.is_empty() {
+        let msg = BankMsg::Send {
+            to_address: to.to_string(),
+            amount,
+        };
+        router.execute(api, storage, block, from.clone(), msg.into())?;
+    }
+    Ok(())
+}
+
+impl Stargate for LockupKeeper {}
+
+impl Module for LockupKeeper {
+    type ExecT = StargateMsg;
+    type QueryT = StargateQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: StargateMsg,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        match msg.type_url.as_str() {
+            MsgLockTokens::TYPE_URL => {
+                let msg = MsgLockTokens::try_from(msg.value)?;
+                self.lock_tokens(api, storage, router, block, sender, msg)
+            }
+            MsgBeginUnlocking::TYPE_URL => {
+                let msg = MsgBeginUnlocking::try_from(msg.value)?;
+                ensure_owner(&msg.owner, &sender)?;
+                let coins = parse_coins(msg.coins)?;
+                let lock = self.begin_unlocking(storage, block, &sender, msg.id, coins)?;
+                let event = Event::new("begin_unlock")
+                    .add_attribute("period_lock_id", lock.id.to_string())
+                    .add_attribute("owner", &sender)
+                    .add_attribute("amount", coins_to_string(&lock.coins));
+                let data = MsgBeginUnlockingResponse { success: true };
+                Ok(AppResponse {
+                    events: vec![event],
+                    data: Some(data.into()),
+                })
+            }
+            MsgBeginUnlockingAll::TYPE_URL => {
+                let msg = MsgBeginUnlockingAll::try_from(msg.value)?;
+                ensure_owner(&msg.owner, &sender)?;
+                let lockup_storage = prefixed_read(storage, NAMESPACE_LOCKUP);
+                let ids: Vec<u64> = account_locks(&lockup_storage, sender.as_str())?
+                    .into_iter()
+                    .filter(|lock| !lock.is_unlocking())
+                    .map(|lock| lock.id)
+                    .collect();
+                let mut unlocks = vec![];
+                let mut events = vec![];
+                for id in ids {
+                    let lock = self.begin_unlocking(storage, block, &sender, id, vec![])?;
+                    events.push(
+                        Event::new("begin_unlock")
+                            .add_attribute("period_lock_id", lock.id.to_string())
+                            .add_attribute("owner", &sender)
+                            .add_attribute("amount", coins_to_string(&lock.coins)),
+                    );
+                    unlocks.push(lock.to_period_lock());
+                }
+                let data = MsgBeginUnlockingAllResponse { unlocks };
+                Ok(AppResponse {
+                    events,
+                    data: Some(data.into()),
+                })
+            }
+            MsgCreateGauge::TYPE_URL => {
+                let msg = MsgCreateGauge::try_from(msg.value)?;
+                self.create_gauge(api, storage, router, block, sender, msg)
+            }
+            MsgAddToGauge::TYPE_URL => {
+                let msg = MsgAddToGauge::try_from(msg.value)?;
+                self.add_to_gauge(api, storage, router, block, sender, msg)
+            }
+            type_url => bail!("Unsupported stargate message {} from {}", type_url, sender),
+        }
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        msg: Empty,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!("Unexpected sudo msg {:?}", msg)
+    }
+
+    fn query(
+        &self,
+        _api: &dyn Api,
+        storage: &dyn Storage,
+        _querier: &dyn Querier,
+        block: &BlockInfo,
+        request: StargateQuery,
+    ) -> AnyResult<Binary> {
+        let storage = prefixed_read(storage, NAMESPACE_LOCKUP);
+        let data = request.data;
+        match request.path.as_str() {
+            "/osmosis.lockup.Query/LockedByID" => {
+                let request = LockedRequest::try_from(data)?;
+                let lock = load_lock(&storage, request.lock_id)?;
+                Ok(to_binary(&LockedResponse {
+                    lock: Some(lock.to_period_lock()),
+                })?)
+            }
+            "/osmosis.lockup.Query/AccountLockedCoins" => {
+                let request = AccountLockedCoinsRequest::try_from(data)?;
+                let locks = account_locks(&storage, &request.owner)?;
+                Ok(to_binary(&AccountLockedCoinsResponse {
+                    coins: total_coins(locks.iter()),
+                })?)
+            }
+            "/osmosis.lockup.Query/AccountUnlockingCoins" => {
+                let request = AccountUnlockingCoinsRequest::try_from(data)?;
+                let locks = account_locks(&storage, &request.owner)?;
+                Ok(to_binary(&AccountUnlockingCoinsResponse {
+                    coins: total_coins(
+                        locks
+                            .iter()
+                            .filter(|lock| lock.is_unlocking() && lock.locked_past(block.time)),
+                    ),
+                })?)
+            }
+            "/osmosis.lockup.Query/AccountUnlockableCoins" => {
+                let request = AccountUnlockableCoinsRequest::try_from(data)?;
+                let locks = account_locks(&storage, &request.owner)?;
+                Ok(to_binary(&AccountUnlockableCoinsResponse {
+                    coins: total_coins(locks.iter().filter(|lock| !lock.locked_past(block.time))),
+                })?)
+            }
+            "/osmosis.lockup.Query/AccountLockedPastTime" => {
+                let request = AccountLockedPastTimeRequest::try_from(data)?;
+                let time = from_proto_timestamp(request.timestamp);
+                let locks = account_locks(&storage, &request.owner)?;
+                Ok(to_binary(&AccountLockedPastTimeResponse {
+                    locks: period_locks(locks.into_iter().filter(|lock| lock.locked_past(time))),
+                })?)
+            }
+            "/osmosis.lockup.Query/AccountLockedPastTimeNotUnlockingOnly" => {
+                let request = AccountLockedPastTimeNotUnlockingOnlyRequest::try_from(data)?;
+                let locks = account_locks(&storage, &request.owner)?;
+                Ok(to_binary(&AccountLockedPastTimeNotUnlockingOnlyResponse {
+                    locks: period_locks(locks.into_iter().filter(|lock| !lock.is_unlocking())),
+                })?)
+            }
+            "/osmosis.lockup.Query/AccountLockedLongerDuration" => {
+                let request = AccountLockedLongerDurationRequest::try_from(data)?;
+                let duration = duration_seconds(request.duration);
+                let locks = account_locks(&storage, &request.owner)?;
+                Ok(to_binary(&AccountLockedLongerDurationResponse {
+                    locks: period_locks(locks.into_iter().filter(|lock| lock.duration >= duration)),
+                })?)
+            }
+            "/osmosis.lockup.Query/AccountLockedLongerDurationNotUnlockingOnly" => {
+                let request = AccountLockedLongerDurationNotUnlockingOnlyRequest::try_from(data)?;
+                let duration = duration_seconds(request.duration);
+                let locks = account_locks(&storage, &request.owner)?;
+                Ok(to_binary(
+                    &AccountLockedLongerDurationNotUnlockingOnlyResponse {
+                        locks: period_locks(
+                            locks
+                                .into_iter()
+                                .filter(|lock| !lock.is_unlocking() && lock.duration >= duration),
+                        ),
+                    },
+                )?)
+            }
+            "/osmosis.lockup.Query/AccountLockedLongerDurationDenom" => {
+                let request = AccountLockedLongerDurationDenomRequest::try_from(data)?;
+                let duration = duration_seconds(request.duration.clone());
+                let locks = account_locks(&storage, &request.owner)?;
+                Ok(to_binary(&AccountLockedLongerDurationDenomResponse {
+                    locks: period_locks(locks.into_iter().filter(|lock| {
+                        lock.duration >= duration && !lock.amount(&request.denom).is_zero()
+                    })),
+                })?)
+            }
+            "/osmosis.incentives.Query/GaugeByID" => {
+                let request = GaugeByIdRequest::try_from(data)?;
+                let gauge = GAUGES
+                    .may_load(&storage, request.id)?
+                    .ok_or_else(|| anyhow!("Gauge {} does not exist", request.id))?;
+                Ok(to_binary(&GaugeByIdResponse {
+                    gauge: Some(gauge.to_gauge()),
+                })?)
+            }
+            "/osmosis.incentives.Query/Gauges" => {
+                let gauges = GAUGES
+                    .range(&storage, None, None, Order::Ascending)
+                    .map(|item| item.map(|(_, gauge)| gauge.to_gauge()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(to_binary(&GaugesResponse {
+                    data: gauges,
+                    pagination: None,
+                })?)
+            }
+            "/osmosis.epochs.v1beta1.Query/EpochInfos" => {
+                let epoch = self.epoch_info(&storage, block)?;
+                Ok(to_binary(&QueryEpochsInfoResponse {
+                    epochs: vec![epoch],
+                })?)
+            }
+            "/osmosis.epochs.v1beta1.Query/CurrentEpoch" => {
+                let request = QueryCurrentEpochRequest::try_from(data)?;
+                let epoch = self.epoch_info(&storage, block)?;
+                ensure!(
+                    epoch.identifier == request.identifier,
+                    "Epoch {} does not exist",
+                    request.identifier
+                );
+                Ok(to_binary(&QueryCurrentEpochResponse {
+                    current_epoch: epoch.current_epoch,
+                })?)
+            }
+            path => bail!("Unsupported stargate query {}", path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cosmwasm_std::testing::{MockApi, MockStorage};
+    use cosmwasm_std::{coin, coins, QueryRequest};
+
+    use crate::{
+        App, AppBuilder, BankKeeper, DistributionKeeper, Executor, FailingModule, StakeKeeper,
+        WasmKeeper,
+    };
+
+    type LockupApp = App<
+        BankKeeper,
+        MockApi,
+        MockStorage,
+        FailingModule<Empty, Empty, Empty>,
+        WasmKeeper<Empty, Empty>,
+        StakeKeeper,
+        DistributionKeeper,
+        LockupKeeper,
+    >;
+
+    const ALICE: &str = "alice";
+    const BOB: &str = "bob";
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn setup() -> LockupApp {
+        let mut app =
+            AppBuilder::new()
+                .with_stargate(LockupKeeper::new())
+                .build(|router, _, storage| {
+                    for owner in [ALICE, BOB] {
+                        let funds = vec![coin(1_000_000, "ulp"), coin(1_000_000, "uosmo")];
+                        router
+                            .bank
+                            .init_balance(storage, &Addr::unchecked(owner), funds)
+                            .unwrap();
+                    }
+                });
+        end_block(&mut app);
+        app
+    }
+
+    fn end_block(app: &mut LockupApp) -> AppResponse {
+        let block = app.block_info();
+        app.init_modules(|router, api, storage| {
+            router.stargate.end_block(api, storage, &*router, &block)
+        })
+        .unwrap()
+    }
+
+    fn pass_days(app: &mut LockupApp, days: u64) -> AppResponse {
+        app.update_block(|block| block.time = block.time.plus_seconds(days * DAY));
+        end_block(app)
+    }
+
+    fn lock(app: &mut LockupApp, owner: &str, amount: u128, days: u64) -> u64 {
+        let msg = MsgLockTokens {
+            owner: owner.to_string(),
+            duration: Some(to_duration(days * DAY)),
+            coins: to_proto_coins(&coins(amount, "ulp")),
+        };
+        let res = app.execute(Addr::unchecked(owner), msg.into()).unwrap();
+        MsgLockTokensResponse::try_from(res.data.unwrap())
+            .unwrap()
+            .id
+    }
+
+    fn begin_unlocking(app: &mut LockupApp, owner: &str, id: u64, amount: Option<u128>) {
+        let msg = MsgBeginUnlocking {
+            owner: owner.to_string(),
+            id,
+            coins: to_proto_coins(&amount.map(|a| coins(a, "ulp")).unwrap_or_default()),
+        };
+        app.execute(Addr::unchecked(owner), msg.into()).unwrap();
+    }
+
+    fn locked_by_id(app: &LockupApp, id: u64) -> PeriodLock {
+        let res: LockedResponse = app
+            .wrap()
+            .query(&QueryRequest::from(LockedRequest { lock_id: id }))
+            .unwrap();
+        res.lock.unwrap()
+    }
+
+    fn balance(app: &LockupApp, owner: &str, denom: &str) -> u128 {
+        app.wrap()
+            .query_balance(owner, denom)
+            .unwrap()
+            .amount
+            .u128()
+    }
+
+    #[test]
+    fn lock_tokens_merges_locks() {
+        let mut app = setup();
+
+        let id = lock(&mut app, ALICE, 1_000, 14);
+        assert_eq!(balance(&app, ALICE, "ulp"), 999_000);
+        assert_eq!(balance(&app, "lockup_module", "ulp"), 1_000);
+
+        // the same denom and duration adds to the lock, another duration makes a new one
+        assert_eq!(lock(&mut app, ALICE, 500, 14), id);
+        assert_ne!(lock(&mut app, ALICE, 500, 7), id);
+        assert_ne!(lock(&mut app, BOB, 500, 14), id);
+
+        let period_lock = locked_by_id(&app, id);
+        assert_eq!(period_lock.owner, ALICE);
+        assert_eq!(period_lock.coins, to_proto_coins(&coins(1_500, "ulp")));
+        assert_eq!(period_lock.duration, Some(to_duration(14 * DAY)));
+        assert_eq!(period_lock.end_time, None);
+
+        let res: AccountLockedCoinsResponse = app
+            .wrap()
+            .query(&QueryRequest::from(AccountLockedCoinsRequest {
+                owner: ALICE.to_string(),
+            }))
+            .unwrap();
+        assert_eq!(res.coins, to_proto_coins(&coins(2_000, "ulp")));
+
+        // locks are signed by their owner
+        let msg = MsgLockTokens {
+            owner: ALICE.to_string(),
+            duration: Some(to_duration(DAY)),
+            coins: to_proto_coins(&coins(1, "ulp")),
+        };
+        let err = app.execute(Addr::unchecked(BOB), msg.into()).unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            "Message signed by alice was sent by bob"
+        );
+    }
+
+    #[test]
+    fn locks_mature_with_block_time() {
+        let mut app = setup();
+        let id = lock(&mut app, ALICE, 1_000, 14);
+
+        // a partial unlock splits the lock
+        begin_unlocking(&mut app, ALICE, id, Some(400));
+        let res: AccountLockedLongerDurationNotUnlockingOnlyResponse = app
+            .wrap()
+            .query(&QueryRequest::from(
+                AccountLockedLongerDurationNotUnlockingOnlyRequest {
+                    owner: ALICE.to_string(),
+                    duration: Some(to_duration(14 * DAY)),
+                },
+            ))
+            .unwrap();
+        assert_eq!(res.locks.len(), 1);
+        assert_eq!(res.locks[0].coins, to_proto_coins(&coins(600, "ulp")));
+        let unlocking = locked_by_id(&app, id + 1);
+        assert_eq!(unlocking.coins, to_proto_coins(&coins(400, "ulp")));
+        assert!(unlocking.end_time.is_some());
+
+        pass_days(&mut app, 13);
+        assert_eq!(balance(&app, ALICE, "ulp"), 999_000);
+        let res: AccountUnlockingCoinsResponse = app
+            .wrap()
+            .query(&QueryRequest::from(AccountUnlockingCoinsRequest {
+                owner: ALICE.to_string(),
+            }))
+            .unwrap();
+        assert_eq!(res.coins, to_proto_coins(&coins(400, "ulp")));
+
+        let res = pass_days(&mut app, 1);
+        assert!(res.events.iter().any(|e| e.ty == "unlock"));
+        assert_eq!(balance(&app, ALICE, "ulp"), 999_400);
+        app.wrap()
+            .query::<LockedResponse>(&QueryRequest::from(LockedRequest { lock_id: id + 1 }))
+            .unwrap_err();
+
+        // unlocking all of them
+        let other = lock(&mut app, ALICE, 100, 1);
+        let msg = MsgBeginUnlockingAll {
+            owner: ALICE.to_string(),
+        };
+        let res = app.execute(Addr::unchecked(ALICE), msg.into()).unwrap();
+        let res = MsgBeginUnlockingAllResponse::try_from(res.data.unwrap()).unwrap();
+        let ids: Vec<u64> = res.unlocks.iter().map(|lock| lock.id).collect();
+        assert_eq!(ids, vec![id, other]);
+
+        pass_days(&mut app, 1);
+        assert_eq!(balance(&app, ALICE, "ulp"), 999_400);
+        pass_days(&mut app, 13);
+        assert_eq!(balance(&app, ALICE, "ulp"), 1_000_000);
+        assert_eq!(balance(&app, "lockup_module", "ulp"), 0);
+
+        // only the owner unlocks
+        let id = lock(&mut app, ALICE, 100, 1);
+        let msg = MsgBeginUnlocking {
+            owner: BOB.to_string(),
+            id,
+            coins: vec![],
+        };
+        let err = app.execute(Addr::unchecked(BOB), msg.into()).unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            format!("Lock {} is owned by alice, not bob", id)
+        );
+    }
+
+    #[test]
+    fn gauges_pay_locks_each_epoch() {
+        let mut app = setup();
+        lock(&mut app, ALICE, 3_000, 14);
+        lock(&mut app, BOB, 1_000, 14);
+        // too short to be paid
+        lock(&mut app, BOB, 5_000, 1);
+
+        let msg = MsgCreateGauge {
+            is_perpetual: false,
+            owner: BOB.to_string(),
+            distribute_to: Some(QueryCondition {
+                lock_query_type: BY_DURATION,
+                denom: "ulp".to_string(),
+                duration: Some(to_duration(7 * DAY)),
+                timestamp: None,
+            }),
+            coins: to_proto_coins(&coins(8_000, "uosmo")),
+            start_time: None,
+            num_epochs_paid_over: 2,
+        };
+        app.execute(Addr::unchecked(BOB), msg.into()).unwrap();
+        assert_eq!(balance(&app, BOB, "uosmo"), 992_000);
+
+        let res: QueryCurrentEpochResponse = app
+            .wrap()
+            .query(&QueryRequest::from(QueryCurrentEpochRequest {
+                identifier: "day".to_string(),
+            }))
+            .unwrap();
+        assert_eq!(res.current_epoch, 1);
+
+        // nothing happens before the epoch ends
+        app.update_block(|block| block.time = block.time.plus_seconds(DAY - 1));
+        end_block(&mut app);
+        assert_eq!(balance(&app, ALICE, "uosmo"), 1_000_000);
+
+        app.update_block(|block| block.time = block.time.plus_seconds(1));
+        end_block(&mut app);
+        assert_eq!(balance(&app, ALICE, "uosmo"), 1_003_000);
+        assert_eq!(balance(&app, BOB, "uosmo"), 993_000);
+
+        let msg = MsgAddToGauge {
+            owner: ALICE.to_string(),
+            gauge_id: 1,
+            rewards: to_proto_coins(&coins(2_000, "uosmo")),
+        };
+        app.execute(Addr::unchecked(ALICE), msg.into()).unwrap();
+
+        // the last epoch pays out the rest, and then the gauge is finished
+        pass_days(&mut app, 3);
+        assert_eq!(balance(&app, ALICE, "uosmo"), 1_005_500);
+        assert_eq!(balance(&app, BOB, "uosmo"), 994_500);
+
+        let res: GaugeByIdResponse = app
+            .wrap()
+            .query(&QueryRequest::from(GaugeByIdRequest { id: 1 }))
+            .unwrap();
+        let gauge = res.gauge.unwrap();
+        assert_eq!(gauge.filled_epochs, 2);
+        assert_eq!(
+            gauge.distributed_coins,
+            to_proto_coins(&coins(10_000, "uosmo"))
+        );
+
+        let res: QueryEpochsInfoResponse = app
+            .wrap()
+            .query(&QueryRequest::from(
+                osmosis_std::types::osmosis::epochs::v1beta1::QueryEpochsInfoRequest {},
+            ))
+            .unwrap();
+        assert_eq!(res.epochs[0].current_epoch, 5);
+        assert!(res.epochs[0].epoch_counting_started);
+    }
+}
@@ -0,0 +1,254 @@
+use anyhow::{bail, Result as AnyResult};
+use cosmwasm_std::{Addr, Api, Binary, BlockInfo, CustomQuery, Empty, Querier, Storage};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+
+use crate::app::CosmosRouter;
+use crate::executor::AppResponse;
+use crate::gamm::GammKeeper;
+use crate::lockup::LockupKeeper;
+use crate::module::Module;
+use crate::stargate::{Stargate, StargateMsg, StargateQuery};
+
+const GAMM_PREFIXES: [&str; 2] = ["/osmosis.gamm.", "/osmosis.twap."];
+const LOCKUP_PREFIXES: [&str; 3] = [
+    "/osmosis.lockup.",
+    "/osmosis.incentives.",
+    "/osmosis.epochs.",
+];
+
+/// The Osmosis modules a vault talks to, behind a single Stargate slot: gamm pools with their
+/// twap, and the lockup module with the incentives and epochs paying rewards to locks.
+#[derive(Default)]
+pub struct OsmosisKeeper {
+    pub gamm: GammKeeper,
+    pub lockup: LockupKeeper,
+}
+
+impl OsmosisKeeper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See `LockupKeeper::end_block`
+    pub fn end_block<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        self.lockup.end_block(api, storage, router, block)
+    }
+
+    fn is_gamm(route: &str) -> bool {
+        GAMM_PREFIXES.iter().any(|prefix| route.starts_with(prefix))
+    }
+
+    fn is_lockup(route: &str) -> bool {
+        LOCKUP_PREFIXES
+            .iter()
+            .any(|prefix| route.starts_with(prefix))
+    }
+}
+
+impl Stargate for OsmosisKeeper {}
+
+impl Module for OsmosisKeeper {
+    type ExecT = StargateMsg;
+    type QueryT = StargateQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: StargateMsg,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        if Self::is_gamm(&msg.type_url) {
+            self.gamm.execute(api, storage, router, block, sender, msg)
+        } else if Self::is_lockup(&msg.type_url) {
+            self.lockup
+                .execute(api, storage, router, block, sender, msg)
+        } else {
+            bail!(
+                "Unsupported stargate message {} from {}",
+                msg.type_url,
+                sender
+            )
+        }
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        msg: Empty,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!("Unexpected sudo msg {:?}", msg)
+    }
+
+    fn query(
+        &self,
+        api: &dyn Api,
+        storage: &dyn Storage,
+        querier: &dyn Querier,
+        block: &BlockInfo,
+        request: StargateQuery,
+    ) -> AnyResult<Binary> {
+        if Self::is_gamm(&request.path) {
+            self.gamm.query(api, storage, querier, block, request)
+        } else if Self::is_lockup(&request.path) {
+            self.lockup.query(api, storage, querier, block, request)
+        } else {
+            bail!("Unsupported stargate query {}", request.path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    use cosmwasm_std::testing::{MockApi, MockStorage};
+    use cosmwasm_std::{coin, CosmosMsg};
+    use osmosis_std::shim::Duration;
+    use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+    use osmosis_std::types::osmosis::gamm::poolmodels::balancer::v1beta1::{
+        MsgCreateBalancerPool, MsgCreateBalancerPoolResponse,
+    };
+    use osmosis_std::types::osmosis::gamm::v1beta1::{PoolAsset, PoolParams};
+    use osmosis_std::types::osmosis::incentives::MsgCreateGauge;
+    use osmosis_std::types::osmosis::lockup::{MsgLockTokens, QueryCondition};
+
+    use crate::{
+        App, AppBuilder, BankKeeper, DistributionKeeper, Executor, FailingModule, StakeKeeper,
+        WasmKeeper,
+    };
+
+    type OsmosisApp = App<
+        BankKeeper,
+        MockApi,
+        MockStorage,
+        FailingModule<Empty, Empty, Empty>,
+        WasmKeeper<Empty, Empty>,
+        StakeKeeper,
+        DistributionKeeper,
+        OsmosisKeeper,
+    >;
+
+    const OWNER: &str = "owner";
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn proto_coin(amount: u128, denom: &str) -> ProtoCoin {
+        ProtoCoin {
+            denom: denom.to_string(),
+            amount: amount.to_string(),
+        }
+    }
+
+    fn end_block(app: &mut OsmosisApp) {
+        let block = app.block_info();
+        app.init_modules(|router, api, storage| {
+            router.stargate.end_block(api, storage, &*router, &block)
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn gauges_pay_locked_pool_shares() {
+        let mut app: OsmosisApp =
+            AppBuilder::new()
+                .with_stargate(OsmosisKeeper::new())
+                .build(|router, _, storage| {
+                    let funds = vec![coin(1_000_000_000, "uatom"), coin(1_000_000_000, "uosmo")];
+                    router
+                        .bank
+                        .init_balance(storage, &Addr::unchecked(OWNER), funds)
+                        .unwrap();
+                });
+        let owner = Addr::unchecked(OWNER);
+        end_block(&mut app);
+
+        let msg = MsgCreateBalancerPool {
+            sender: OWNER.to_string(),
+            pool_params: Some(PoolParams {
+                swap_fee: "0".to_string(),
+                exit_fee: "0".to_string(),
+                smooth_weight_change_params: None,
+            }),
+            pool_assets: ["uatom", "uosmo"]
+                .iter()
+                .map(|denom| PoolAsset {
+                    token: Some(proto_coin(1_000_000, denom)),
+                    weight: "1".to_string(),
+                })
+                .collect(),
+            future_pool_governor: "".to_string(),
+        };
+        let res = app.execute(owner.clone(), msg.into()).unwrap();
+        let pool_id = MsgCreateBalancerPoolResponse::try_from(res.data.unwrap())
+            .unwrap()
+            .pool_id;
+        let shares = format!("gamm/pool/{}", pool_id);
+
+        let msg = MsgLockTokens {
+            owner: OWNER.to_string(),
+            duration: Some(Duration {
+                seconds: DAY as i64,
+                nanos: 0,
+            }),
+            coins: vec![proto_coin(1_000, &shares)],
+        };
+        app.execute(owner.clone(), msg.into()).unwrap();
+        let msg = MsgCreateGauge {
+            is_perpetual: false,
+            owner: OWNER.to_string(),
+            distribute_to: Some(QueryCondition {
+                lock_query_type: 0,
+                denom: shares,
+                duration: Some(Duration {
+                    seconds: DAY as i64,
+                    nanos: 0,
+                }),
+                timestamp: None,
+            }),
+            coins: vec![proto_coin(700, "uosmo")],
+            start_time: None,
+            num_epochs_paid_over: 7,
+        };
+        app.execute(owner.clone(), msg.into()).unwrap();
+        let before = app.wrap().query_balance(&owner, "uosmo").unwrap();
+
+        app.update_block(|block| block.time = block.time.plus_seconds(DAY));
+        end_block(&mut app);
+        let after = app.wrap().query_balance(&owner, "uosmo").unwrap();
+        assert_eq!((after.amount - before.amount).u128(), 100);
+
+        // other modules are still not simulated
+        let msg = CosmosMsg::Stargate {
+            type_url: "/osmosis.superfluid.MsgSuperfluidDelegate".to_string(),
+            value: Binary::default(),
+        };
+        app.execute(owner, msg).unwrap_err();
+    }
+}
@@ -864,7 +864,7 @@ impl Module for DistributionKeeper {
 
 #[cfg(test)]
 mod test {
-    use crate::{app::MockRouter, BankKeeper, FailingModule, Router, WasmKeeper};
+    use crate::{app::MockRouter, BankKeeper, FailingModule, Router, StargateFailing, WasmKeeper};
 
     use super::*;
 
@@ -881,6 +881,7 @@ mod test {
         WasmKeeper<ExecC, QueryC>,
         StakeKeeper,
         DistributionKeeper,
+        StargateFailing,
     >;
 
     fn mock_router() -> BasicRouter {
@@ -890,6 +891,7 @@ mod test {
             custom: FailingModule::new(),
             staking: StakeKeeper::new(),
             distribution: DistributionKeeper::new(),
+            stargate: StargateFailing::new(),
         }
     }
 
@@ -0,0 +1,92 @@
+use anyhow::{bail, Result as AnyResult};
+use cosmwasm_std::{Addr, Api, Binary, BlockInfo, CustomQuery, Empty, Querier, Storage};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::app::CosmosRouter;
+use crate::executor::AppResponse;
+use crate::module::Module;
+
+/// A `CosmosMsg::Stargate`, `value` is the protobuf encoding of the message `type_url` names.
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, Serialize, Deserialize)]
+pub struct StargateMsg {
+    pub type_url: String,
+    pub value: Binary,
+}
+
+/// A `QueryRequest::Stargate`, `data` is the protobuf encoding of the request to the gRPC
+/// method at `path`. The response has to be protobuf encoded too.
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, Serialize, Deserialize)]
+pub struct StargateQuery {
+    pub path: String,
+    pub data: Binary,
+}
+
+/// Answers the Stargate messages and queries of the chain modules an `App` simulates.
+/// Implementations dispatch on the `type_url` of the message or the `path` of the query.
+pub trait Stargate: Module<ExecT = StargateMsg, QueryT = StargateQuery, SudoT = Empty> {}
+
+/// Default Stargate module, rejects every message and query with an error naming it.
+#[derive(Default)]
+pub struct StargateFailing {}
+
+impl StargateFailing {
+    pub fn new() -> Self {
+        StargateFailing {}
+    }
+}
+
+impl Stargate for StargateFailing {}
+
+impl Module for StargateFailing {
+    type ExecT = StargateMsg;
+    type QueryT = StargateQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        sender: Addr,
+        msg: StargateMsg,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!(
+            "Unsupported stargate message {} from {}",
+            msg.type_url,
+            sender
+        )
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        msg: Empty,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!("Unexpected sudo msg {:?}", msg)
+    }
+
+    fn query(
+        &self,
+        _api: &dyn Api,
+        _storage: &dyn Storage,
+        _querier: &dyn Querier,
+        _block: &BlockInfo,
+        request: StargateQuery,
+    ) -> AnyResult<Binary> {
+        bail!("Unsupported stargate query {}", request.path)
+    }
+}
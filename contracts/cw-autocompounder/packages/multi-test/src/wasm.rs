@@ -942,6 +942,7 @@ mod test {
     use crate::bank::BankKeeper;
     use crate::module::FailingModule;
     use crate::staking::{DistributionKeeper, StakeKeeper};
+    use crate::stargate::StargateFailing;
     use crate::test_helpers::contracts::{caller, error, payout};
     use crate::test_helpers::EmptyMsg;
     use crate::transactions::StorageTransaction;
@@ -955,6 +956,7 @@ mod test {
         WasmKeeper<ExecC, QueryC>,
         StakeKeeper,
         DistributionKeeper,
+        StargateFailing,
     >;
 
     fn mock_router() -> BasicRouter {
@@ -964,6 +966,7 @@ mod test {
             custom: FailingModule::new(),
             staking: StakeKeeper::new(),
             distribution: DistributionKeeper::new(),
+            stargate: StargateFailing::new(),
         }
     }
 
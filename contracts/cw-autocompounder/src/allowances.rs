@@ -256,12 +256,13 @@ pub fn query_allowance(deps: Deps, owner: String, spender: String) -> StdResult<
 mod tests {
     use super::*;
 
-    use cosmwasm_std::testing::{mock_dependencies_with_balance, mock_env, mock_info};
+    use cosmwasm_std::testing::{mock_env, mock_info};
     use cosmwasm_std::{coins, CosmosMsg, SubMsg, Timestamp, WasmMsg};
-    use cw20::{Cw20Coin, TokenInfoResponse};
+    use cw20::TokenInfoResponse;
 
     use crate::contract::{execute, instantiate, query_balance, query_token_info};
     use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::testing::{mock_dependencies_with_balance, mock_instantiate_msg, mock_shares};
 
     fn get_balance<T: Into<String>>(deps: Deps, address: T) -> Uint128 {
         query_balance(deps, address.into()).unwrap().balance
@@ -274,25 +275,17 @@ mod tests {
         amount: Uint128,
     ) -> TokenInfoResponse {
         let instantiate_msg = InstantiateMsg {
-            name: "Auto Gen".to_string(),
-            symbol: "AUTO".to_string(),
-            id: 1, 
-            denom_1: "uosmo".to_string(),
-            denom_2: "stake".to_string(),
-            fee: 0,
-            fee_collector_address: "someCollectorAddresss".to_string(),
+            denom_1: Some("uosmo".to_string()),
+            denom_2: None,
+            fee_collector_address: "fee_collector".to_string(),
             white_list_denoms: Vec::from(["uosmo".to_string()]),
             decimals: 3,
-            initial_balances: vec![Cw20Coin {
-                address: addr.into(),
-                amount,
-            }],
-            mint: None,
-            marketing: None,
+            ..mock_instantiate_msg()
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
         instantiate(deps.branch(), env, info, instantiate_msg).unwrap();
+        mock_shares(deps.branch(), &addr.into(), amount);
         query_token_info(deps.as_ref()).unwrap()
     }
 
@@ -6,5 +6,6 @@ fn main() {
     write_api! {
         instantiate: InstantiateMsg,
         execute: ExecuteMsg,
+        query: QueryMsg,
     }
 }
@@ -0,0 +1,842 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use cosmwasm_std::{
+    Attribute, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Reply, Response,
+    StdError, StdResult, SubMsg, Uint128,
+};
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::gamm::v1beta1::{
+    MsgJoinPool, MsgJoinPoolResponse, MsgJoinSwapExternAmountIn, MsgJoinSwapExternAmountInResponse,
+    MsgSwapExactAmountIn, MsgSwapExactAmountInResponse,
+};
+
+use crate::epochs::assert_new_compound_epoch;
+use crate::error::ContractError;
+use crate::history::record_harvest;
+use crate::keeper::{assert_keeper_rewards, keeper_bounty};
+use crate::locks::lock_msgs;
+use crate::metrics::current_share_price;
+use crate::pool::{query_pool, PoolAsset, VaultPool};
+use crate::state::{
+    CompoundState, State, COMPOUND, LAST_COMPOUND_EPOCH, POOL_INFO, ROUTES, STATE,
+    SUPERFLUID_OSMO_BASE,
+};
+use crate::swap::min_out_amount;
+use crate::unbonding::take_released_lp;
+
+pub const SWAP_REPLY_ID: u64 = 1;
+pub const JOIN_POOL_REPLY_ID: u64 = 2;
+pub const JOIN_SWAP_EXTERN_REPLY_ID: u64 = 3;
+pub const CONVERT_SWAP_REPLY_ID: u64 = 12;
+
+/// Denom superfluid staking rewards are paid in
+pub const SUPERFLUID_REWARD_DENOM: &str = "uosmo";
+
+/// Lock duration used until the owner configures one, 14 days
+pub const DEFAULT_LOCK_DURATION_SECONDS: u64 = 1_209_600;
+
+/// Rewards taken from the vault balance by a single harvest.
+pub struct Harvest {
+    /// Swaps of the rewards left after the performance fee, one per reward denom
+    pub swaps: Vec<MsgSwapExactAmountIn>,
+    pub harvested: Vec<Coin>,
+    pub fees: Vec<Coin>,
+    /// Keeper bounty, only taken when a keeper runs the harvest
+    pub bounty: Vec<Coin>,
+    /// Superfluid mode only, the uosmo the vault keeps once the fee and bounty are paid
+    pub osmo_left: Option<Uint128>,
+}
+
+impl Harvest {
+    /// Takes the performance `fee` and the keeper `bounty` off `rewards`, returns the rest.
+    fn take(&mut self, rewards: Coin, fee: Decimal, bounty: Decimal) -> Coin {
+        let (fee, reward) = split_performance_fee(&rewards, fee);
+        // the bounty comes out of what is left after the fee, same as a fee on it
+        let (keeper_bounty, reward) = split_performance_fee(&reward, bounty);
+        self.harvested.push(rewards);
+        if !fee.amount.is_zero() {
+            self.fees.push(fee);
+        }
+        if !keeper_bounty.amount.is_zero() {
+            self.bounty.push(keeper_bounty);
+        }
+        reward
+    }
+
+    pub fn attributes(&self) -> Vec<Attribute> {
+        let mut attributes = vec![
+            Attribute::new("harvested", coins_to_string(&self.harvested)),
+            Attribute::new("fee", coins_to_string(&self.fees)),
+        ];
+        if !self.bounty.is_empty() {
+            attributes.push(Attribute::new("bounty", coins_to_string(&self.bounty)));
+        }
+        attributes
+    }
+
+    /// Output denoms of the swaps, in execution order
+    pub fn swap_out_denoms(&self) -> Vec<String> {
+        self.swaps
+            .iter()
+            .filter_map(|swap| swap.routes.last())
+            .map(|hop| hop.token_out_denom.clone())
+            .collect()
+    }
+
+    /// Payment of the performance fee to the fee collector, if anything was taken.
+    pub fn fee_msg(&self, state: &State) -> Option<BankMsg> {
+        if self.fees.is_empty() {
+            return None;
+        }
+        Some(BankMsg::Send {
+            to_address: state.fee_collector_address.to_string(),
+            amount: self.fees.clone(),
+        })
+    }
+}
+
+/// Takes the performance fee and the keeper `bounty` off every whitelisted reward that
+/// has a route and prices the swap of the rest into the pool assets. In superfluid mode
+/// the uosmo the vault got since the last harvest is a reward too, left to the joins.
+pub fn harvest(
+    deps: Deps,
+    env: &Env,
+    state: &State,
+    max_slippage_bps: u64,
+    bounty: Decimal,
+) -> Result<Harvest, ContractError> {
+    let balances = deps
+        .querier
+        .query_all_balances(&env.contract.address)
+        .map_err(|_| ContractError::NoValidAddress {})?;
+    if balances.is_empty() {
+        return Err(ContractError::NoBalancesFound {});
+    }
+
+    let info = POOL_INFO.load(deps.storage)?;
+    let mut harvest = Harvest {
+        swaps: vec![],
+        harvested: vec![],
+        fees: vec![],
+        bounty: vec![],
+        osmo_left: None,
+    };
+    for coin in &balances {
+        if coin.denom == info.denom_1
+            || coin.denom == info.denom_2
+            || !info.white_list_denoms.contains(&coin.denom)
+        {
+            continue;
+        }
+        // rewards without a configured route are left in the vault
+        let routes = match ROUTES.may_load(deps.storage, &coin.denom)? {
+            Some(routes) => routes,
+            None => continue,
+        };
+
+        let reward = harvest.take(coin.clone(), state.fee, bounty);
+        let min_out = min_out_amount(deps, env, &reward, &routes, max_slippage_bps)?;
+        harvest.swaps.push(MsgSwapExactAmountIn {
+            sender: env.contract.address.to_string(),
+            routes,
+            token_in: Some(ProtoCoin {
+                denom: reward.denom,
+                amount: reward.amount.to_string(),
+            }),
+            token_out_min_amount: min_out.to_string(),
+        });
+    }
+
+    if state.superfluid_validator.is_some() {
+        let balance = balances
+            .iter()
+            .find(|coin| coin.denom == SUPERFLUID_REWARD_DENOM)
+            .map(|coin| coin.amount)
+            .unwrap_or_default();
+        let base = SUPERFLUID_OSMO_BASE
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+        let rewards = balance.saturating_sub(base);
+        let mut osmo_left = balance;
+        if !rewards.is_zero() {
+            let reward = harvest.take(
+                Coin::new(rewards.u128(), SUPERFLUID_REWARD_DENOM),
+                state.fee,
+                bounty,
+            );
+            osmo_left = base + reward.amount;
+        }
+        harvest.osmo_left = Some(osmo_left);
+    }
+    Ok(harvest)
+}
+
+/// Superfluid mode only, remembers the uosmo the vault holds once a harvest is done, the
+/// next harvest takes what the superfluid staking rewards added to it since.
+pub fn save_osmo_base(deps: DepsMut, env: &Env) -> StdResult<()> {
+    if SUPERFLUID_OSMO_BASE.may_load(deps.storage)?.is_none() {
+        return Ok(());
+    }
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, SUPERFLUID_REWARD_DENOM)?;
+    SUPERFLUID_OSMO_BASE.save(deps.storage, &balance.amount)
+}
+
+/// Splits harvested `rewards` into the performance fee owed to the fee collector
+/// and the part left to compound.
+pub fn split_performance_fee(rewards: &Coin, fee: Decimal) -> (Coin, Coin) {
+    let fee_amount = rewards.amount * fee;
+    (
+        Coin::new(fee_amount.u128(), &rewards.denom),
+        Coin::new((rewards.amount - fee_amount).u128(), &rewards.denom),
+    )
+}
+
+fn coins_to_string(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(|coin| coin.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Starts a harvest-and-compound cycle. The reward swaps, the pool joins and the final
+/// lock are chained through submessage replies, see `reply`.
+pub fn execute_compound(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let bounty = keeper_bounty(deps.as_ref(), &env, &info)?;
+    let epoch = assert_new_compound_epoch(deps.as_ref())?;
+    let state = STATE.load(deps.storage)?;
+    let harvest = harvest(
+        deps.as_ref(),
+        &env,
+        &state,
+        state.max_slippage_bps,
+        bounty.unwrap_or_default(),
+    )?;
+    if bounty.is_some() {
+        assert_keeper_rewards(deps.as_ref(), &harvest)?;
+    }
+    if let Some(osmo_left) = harvest.osmo_left {
+        SUPERFLUID_OSMO_BASE.save(deps.storage, &osmo_left)?;
+    }
+
+    let mut res = Response::new()
+        .add_attribute("action", "compound")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attributes(harvest.attributes());
+    if let Some(fee_msg) = harvest.fee_msg(&state) {
+        res = res.add_message(fee_msg);
+    }
+    if !harvest.bounty.is_empty() {
+        res = res.add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: harvest.bounty.clone(),
+        });
+    }
+
+    if harvest.swaps.is_empty() {
+        // nothing to swap, the pool assets the vault holds can be joined right away,
+        // less the superfluid rewards fee and bounty paid before the joins run
+        let paid_out = [harvest.fees.as_slice(), harvest.bounty.as_slice()].concat();
+        let joins = join_msgs(deps.as_ref(), &env, &paid_out)?;
+        if joins.is_empty() {
+            return Err(ContractError::NoBalancesFound {});
+        }
+        LAST_COMPOUND_EPOCH.save(deps.storage, &epoch)?;
+        COMPOUND.save(
+            deps.storage,
+            &CompoundState {
+                pending_swaps: vec![],
+                pending_joins: joins.len() as u32,
+                shares_out: Uint128::zero(),
+                harvested: harvest.harvested,
+                fees: harvest.fees,
+                bounty: harvest.bounty,
+                swap_outputs: vec![],
+            },
+        )?;
+        return Ok(res.add_submessages(joins));
+    }
+
+    let pending_swaps = harvest.swap_out_denoms();
+    LAST_COMPOUND_EPOCH.save(deps.storage, &epoch)?;
+    COMPOUND.save(
+        deps.storage,
+        &CompoundState {
+            pending_swaps,
+            pending_joins: 0,
+            shares_out: Uint128::zero(),
+            harvested: harvest.harvested,
+            fees: harvest.fees,
+            bounty: harvest.bounty,
+            swap_outputs: vec![],
+        },
+    )?;
+    Ok(res.add_submessages(
+        harvest
+            .swaps
+            .into_iter()
+            .map(|swap| SubMsg::reply_on_success(swap, SWAP_REPLY_ID)),
+    ))
+}
+
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        SWAP_REPLY_ID => {
+            let res = MsgSwapExactAmountInResponse::try_from(msg.result)?;
+            reply_swap(deps, env, res.token_out_amount)
+        }
+        CONVERT_SWAP_REPLY_ID => {
+            let res = MsgSwapExactAmountInResponse::try_from(msg.result)?;
+            reply_convert_swap(deps, env, res.token_out_amount)
+        }
+        JOIN_POOL_REPLY_ID => {
+            let res = MsgJoinPoolResponse::try_from(msg.result)?;
+            reply_join(deps, env, res.share_out_amount)
+        }
+        JOIN_SWAP_EXTERN_REPLY_ID => {
+            let res = MsgJoinSwapExternAmountInResponse::try_from(msg.result)?;
+            reply_join(deps, env, res.share_out_amount)
+        }
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+/// Moves the first pending reward swap to the swap outputs, with what it returned.
+fn record_swap_output(
+    compound: &mut CompoundState,
+    token_out_amount: &str,
+) -> Result<Coin, ContractError> {
+    if compound.pending_swaps.is_empty() {
+        return Err(StdError::generic_err("No reward swap pending").into());
+    }
+    let denom = compound.pending_swaps.remove(0);
+    let swapped = Coin::new(Uint128::from_str(token_out_amount)?.u128(), denom);
+    compound.swap_outputs.push(swapped.clone());
+    Ok(swapped)
+}
+
+/// Joins the pool once the last reward swap came back.
+fn reply_swap(
+    mut deps: DepsMut,
+    env: Env,
+    token_out_amount: String,
+) -> Result<Response, ContractError> {
+    let mut compound = COMPOUND.load(deps.storage)?;
+    let swapped = record_swap_output(&mut compound, &token_out_amount)?;
+    let res = Response::new()
+        .add_attribute("action", "compound_swap")
+        .add_attribute("swapped", swapped.to_string());
+    if !compound.pending_swaps.is_empty() {
+        COMPOUND.save(deps.storage, &compound)?;
+        return Ok(res);
+    }
+
+    let joins = join_msgs(deps.as_ref(), &env, &[])?;
+    if joins.is_empty() {
+        COMPOUND.remove(deps.storage);
+        save_osmo_base(deps.branch(), &env)?;
+        let share_price = current_share_price(deps.as_ref(), &env)?;
+        record_harvest(deps.storage, &env, compound, share_price)?;
+        return Ok(res);
+    }
+    compound.pending_joins = joins.len() as u32;
+    COMPOUND.save(deps.storage, &compound)?;
+    Ok(res.add_submessages(joins))
+}
+
+/// Records the `ConvertRewards` harvest once its last reward swap came back.
+fn reply_convert_swap(
+    mut deps: DepsMut,
+    env: Env,
+    token_out_amount: String,
+) -> Result<Response, ContractError> {
+    let mut compound = COMPOUND.load(deps.storage)?;
+    let swapped = record_swap_output(&mut compound, &token_out_amount)?;
+    let res = Response::new()
+        .add_attribute("action", "convert_rewards_swap")
+        .add_attribute("swapped", swapped.to_string());
+    if !compound.pending_swaps.is_empty() {
+        COMPOUND.save(deps.storage, &compound)?;
+        return Ok(res);
+    }
+    COMPOUND.remove(deps.storage);
+    save_osmo_base(deps.branch(), &env)?;
+    let share_price = current_share_price(deps.as_ref(), &env)?;
+    record_harvest(deps.storage, &env, compound, share_price)?;
+    Ok(res)
+}
+
+/// Locks the LP shares minted by the compound once the last join came back.
+fn reply_join(
+    mut deps: DepsMut,
+    env: Env,
+    share_out_amount: String,
+) -> Result<Response, ContractError> {
+    let mut compound = COMPOUND.load(deps.storage)?;
+    compound.shares_out += Uint128::from_str(&share_out_amount)?;
+    compound.pending_joins = compound.pending_joins.saturating_sub(1);
+    let res = Response::new()
+        .add_attribute("action", "compound_join")
+        .add_attribute("share_out_amount", share_out_amount);
+    if compound.pending_joins > 0 {
+        COMPOUND.save(deps.storage, &compound)?;
+        return Ok(res);
+    }
+    COMPOUND.remove(deps.storage);
+    save_osmo_base(deps.branch(), &env)?;
+    let share_price = current_share_price(deps.as_ref(), &env)?;
+
+    let state = STATE.load(deps.storage)?;
+    let shares_out = compound.shares_out;
+    // superfluid unbonds release LP beyond their claims, it goes back in with the compound
+    let relocked = take_released_lp(deps.branch(), &env, shares_out)?;
+    let lp = Coin::new(
+        (shares_out + relocked).u128(),
+        POOL_INFO.load(deps.storage)?.lp_denom,
+    );
+    record_harvest(deps.storage, &env, compound, share_price)?;
+    Ok(res
+        .add_submessages(lock_msgs(&env, &state, lp))
+        .add_attribute("locked", shares_out)
+        .add_attribute("relocked", relocked))
+}
+
+/// Builds the joins putting the pool assets held by the vault into the pool: a
+/// `MsgJoinPool` for the part matching the pool ratio, then a single sided join
+/// for whatever is left of each asset. `paid_out` is sent from the vault before the joins.
+fn join_msgs(deps: Deps, env: &Env, paid_out: &[Coin]) -> StdResult<Vec<SubMsg>> {
+    let pool_id = POOL_INFO.load(deps.storage)?.id;
+    let pool = query_pool(deps, pool_id)?;
+    let mut assets = pool.assets()?;
+    let mut total_shares = pool.total_shares()?.amount;
+    if total_shares.is_zero() {
+        return Err(StdError::generic_err("Pool has no shares issued"));
+    }
+
+    let held = assets
+        .iter()
+        .map(|asset| {
+            let mut held = deps
+                .querier
+                .query_balance(&env.contract.address, &asset.denom)?;
+            for coin in paid_out.iter().filter(|coin| coin.denom == asset.denom) {
+                held.amount = held.amount.saturating_sub(coin.amount);
+            }
+            Ok(held)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut msgs = vec![];
+    let mut left: Vec<Uint128> = held.iter().map(|coin| coin.amount).collect();
+    let (shares_out, token_in) = pool.simulate_join(&held)?;
+    if !shares_out.is_zero() {
+        let join: CosmosMsg = MsgJoinPool {
+            sender: env.contract.address.to_string(),
+            pool_id,
+            share_out_amount: shares_out.to_string(),
+            token_in_maxs: held
+                .iter()
+                .map(|coin| ProtoCoin {
+                    denom: coin.denom.clone(),
+                    amount: coin.amount.to_string(),
+                })
+                .collect(),
+        }
+        .into();
+        msgs.push(SubMsg::reply_on_success(join, JOIN_POOL_REPLY_ID));
+
+        // the pool takes up to a rounded up share of every asset
+        for ((asset, left), used) in assets.iter_mut().zip(left.iter_mut()).zip(&token_in) {
+            *left = left.saturating_sub(used.amount);
+            asset.balance += used.amount;
+        }
+        total_shares += shares_out;
+    }
+
+    for (asset, amount) in assets.iter().zip(left) {
+        let share_out_min = single_sided_shares_min(&pool, asset, amount, total_shares)?;
+        // dust that wouldn't mint a single share waits for the next compound
+        if share_out_min.is_zero() {
+            continue;
+        }
+        let join: CosmosMsg = MsgJoinSwapExternAmountIn {
+            sender: env.contract.address.to_string(),
+            pool_id,
+            token_in: Some(ProtoCoin {
+                denom: asset.denom.clone(),
+                amount: amount.to_string(),
+            }),
+            share_out_min_amount: share_out_min.to_string(),
+        }
+        .into();
+        msgs.push(SubMsg::reply_on_success(join, JOIN_SWAP_EXTERN_REPLY_ID));
+    }
+    Ok(msgs)
+}
+
+/// Lower bound of the LP shares a single sided join of `amount` mints. The balancer
+/// formula `total * ((1 + a / balance) ^ weight - 1)` needs a fractional power, but for
+/// weights under 1 it is never below `total * weight * a / (balance + a)`. `a` is the
+/// amount net of the swap fee charged on the part swapped into the other assets.
+/// Stableswap curves swap with less slippage than a balancer pool of the same value
+/// split, so the bound holds for them with the asset's value share as its weight.
+pub(crate) fn single_sided_shares_min(
+    pool: &VaultPool,
+    asset: &PoolAsset,
+    amount: Uint128,
+    total_shares: Uint128,
+) -> StdResult<Uint128> {
+    if amount.is_zero() {
+        return Ok(Uint128::zero());
+    }
+    let weight = pool.value_share(asset)?;
+    let swap_fee = pool.swap_fee()?;
+    let amount = amount * (Decimal::one() - (Decimal::one() - weight) * swap_fee);
+    Ok((total_shares * weight).multiply_ratio(amount, asset.balance + amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coin, Binary, DepsMut, SubMsgResponse, SubMsgResult};
+    use osmosis_std::shim::Duration;
+    use osmosis_std::types::osmosis::gamm::v1beta1::SwapAmountInRoute;
+    use osmosis_std::types::osmosis::lockup::MsgLockTokens;
+
+    use crate::contract::{execute, instantiate};
+    use crate::locks::LOCK_REPLY_ID;
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::state::HARVESTS;
+    use crate::testing::{
+        mock_balancer_pool, mock_instantiate_msg, mock_osmosis_dependencies, mock_route_pool,
+        mock_stableswap_pool,
+    };
+
+    fn do_instantiate(deps: DepsMut) {
+        let msg = InstantiateMsg {
+            white_list_denoms: vec!["uion".to_string(), "ustrd".to_string()],
+            ..mock_instantiate_msg()
+        };
+        instantiate(deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+    }
+
+    fn set_route(deps: DepsMut, denom: &str, pool_id: u64, token_out_denom: &str) {
+        let msg = ExecuteMsg::SetRoute {
+            denom: denom.to_string(),
+            route: vec![SwapAmountInRoute {
+                pool_id,
+                token_out_denom: token_out_denom.to_string(),
+            }],
+        };
+        execute(deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+    }
+
+    fn reply_ok(id: u64, data: impl Into<Binary>) -> Reply {
+        Reply {
+            id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(data.into()),
+            }),
+        }
+    }
+
+    fn decode<T: TryFrom<Binary, Error = StdError>>(msg: &SubMsg) -> T {
+        match &msg.msg {
+            CosmosMsg::Stargate { value, .. } => T::try_from(value.clone()).unwrap(),
+            msg => panic!("unexpected message {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn compound_swaps_joins_and_locks() {
+        let mut deps = mock_osmosis_dependencies(&[coin(1_000, "ustrd"), coin(500, "uion")]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+            100_000,
+        ));
+        deps.querier.set_twap(3, "ustrd", "uatom", "1");
+        deps.querier.set_twap(2, "uion", "uosmo", "1");
+        deps.querier.set_pool(mock_route_pool(3, "ustrd", "uatom"));
+        deps.querier.set_pool(mock_route_pool(2, "uion", "uosmo"));
+        do_instantiate(deps.as_mut());
+        set_route(deps.as_mut(), "ustrd", 3, "uatom");
+        set_route(deps.as_mut(), "uion", 2, "uosmo");
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("rando", &[]),
+            ExecuteMsg::Compound {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::Compound {},
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert!(res
+            .messages
+            .iter()
+            .all(|msg| msg.id == SWAP_REPLY_ID && msg.reply_on == cosmwasm_std::ReplyOn::Success));
+
+        // the first swap reply only records its output
+        deps.querier.update_balance(
+            MOCK_CONTRACT_ADDR,
+            vec![coin(500, "uion"), coin(1_000, "uatom")],
+        );
+        let swapped = MsgSwapExactAmountInResponse {
+            token_out_amount: "1000".to_string(),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_ok(SWAP_REPLY_ID, swapped)).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(res.attributes[1], Attribute::new("swapped", "1000uatom"));
+
+        // the last one joins the pool, balanced first and single sided with the rest
+        deps.querier.update_balance(
+            MOCK_CONTRACT_ADDR,
+            vec![coin(1_000, "uatom"), coin(500, "uosmo")],
+        );
+        let swapped = MsgSwapExactAmountInResponse {
+            token_out_amount: "500".to_string(),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_ok(SWAP_REPLY_ID, swapped)).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(res.messages[0].id, JOIN_POOL_REPLY_ID);
+        let join: MsgJoinPool = decode(&res.messages[0]);
+        assert_eq!(join.share_out_amount, "50");
+        assert_eq!(
+            join.token_in_maxs,
+            vec![
+                ProtoCoin {
+                    denom: "uatom".to_string(),
+                    amount: "1000".to_string(),
+                },
+                ProtoCoin {
+                    denom: "uosmo".to_string(),
+                    amount: "500".to_string(),
+                },
+            ]
+        );
+        assert_eq!(res.messages[1].id, JOIN_SWAP_EXTERN_REPLY_ID);
+        let join: MsgJoinSwapExternAmountIn = decode(&res.messages[1]);
+        assert_eq!(
+            join.token_in,
+            Some(ProtoCoin {
+                denom: "uatom".to_string(),
+                amount: "500".to_string(),
+            })
+        );
+        assert_eq!(join.share_out_min_amount, "24");
+
+        let joined = MsgJoinPoolResponse {
+            share_out_amount: "50".to_string(),
+            token_in: vec![],
+        };
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            reply_ok(JOIN_POOL_REPLY_ID, joined),
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+
+        let joined = MsgJoinSwapExternAmountInResponse {
+            share_out_amount: "24".to_string(),
+        };
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            reply_ok(JOIN_SWAP_EXTERN_REPLY_ID, joined),
+        )
+        .unwrap();
+        let lock: CosmosMsg = MsgLockTokens {
+            owner: MOCK_CONTRACT_ADDR.to_string(),
+            duration: Some(Duration {
+                seconds: DEFAULT_LOCK_DURATION_SECONDS as i64,
+                nanos: 0,
+            }),
+            coins: vec![ProtoCoin {
+                denom: "gamm/pool/1".to_string(),
+                amount: "74".to_string(),
+            }],
+        }
+        .into();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(lock, LOCK_REPLY_ID)]
+        );
+        assert_eq!(COMPOUND.may_load(deps.as_ref().storage).unwrap(), None);
+
+        let harvest = HARVESTS.back(deps.as_ref().storage).unwrap().unwrap();
+        assert_eq!(
+            harvest.rewards,
+            vec![coin(1_000, "ustrd"), coin(500, "uion")]
+        );
+        assert_eq!(
+            harvest.swap_outputs,
+            vec![coin(1_000, "uatom"), coin(500, "uosmo")]
+        );
+        assert_eq!(harvest.lp_added, Uint128::new(74));
+    }
+
+    #[test]
+    fn compound_without_rewards_joins_held_assets() {
+        let mut deps = mock_osmosis_dependencies(&[coin(2_000, "uatom"), coin(2_000, "uosmo")]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+            100_000,
+        ));
+        do_instantiate(deps.as_mut());
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::Compound {},
+        )
+        .unwrap();
+        // a perfectly balanced join leaves nothing for a single sided one
+        assert_eq!(res.messages.len(), 1);
+        let join: MsgJoinPool = decode(&res.messages[0]);
+        assert_eq!(join.share_out_amount, "200");
+
+        let mut deps = mock_osmosis_dependencies(&[coin(400, "ujuno")]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+            100_000,
+        ));
+        do_instantiate(deps.as_mut());
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::Compound {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoBalancesFound {});
+    }
+
+    #[test]
+    fn compound_joins_stableswap_pool() {
+        let mut deps = mock_osmosis_dependencies(&[coin(3_000, "uatom"), coin(1_000, "uosmo")]);
+        deps.querier.set_stableswap_pool(mock_stableswap_pool(
+            1,
+            &[("uatom", 1_000_000, 1), ("uosmo", 1_000_000, 1)],
+            100_000,
+        ));
+        do_instantiate(deps.as_mut());
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::Compound {},
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 2);
+        let join: MsgJoinPool = decode(&res.messages[0]);
+        assert_eq!(join.share_out_amount, "100");
+        // the uatom left after the balanced join goes in single sided
+        let join: MsgJoinSwapExternAmountIn = decode(&res.messages[1]);
+        assert_eq!(
+            join.token_in,
+            Some(ProtoCoin {
+                denom: "uatom".to_string(),
+                amount: "2000".to_string(),
+            })
+        );
+        assert_eq!(join.share_out_min_amount, "99");
+    }
+
+    #[test]
+    fn superfluid_rewards_pay_the_fee() {
+        let mut deps = mock_osmosis_dependencies(&[coin(1_000, "uosmo")]);
+        let msg = InstantiateMsg {
+            fee: Decimal::percent(10),
+            superfluid_validator: Some("osmovaloper1xyz".to_string()),
+            ..mock_instantiate_msg()
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let fee_msg = |amount: u128| {
+            SubMsg::new(BankMsg::Send {
+                to_address: "collector".to_string(),
+                amount: vec![coin(amount, "uosmo")],
+            })
+        };
+        let compound = |deps: DepsMut| {
+            execute(
+                deps,
+                mock_env(),
+                mock_info("owner", &[]),
+                ExecuteMsg::Compound {},
+            )
+            .unwrap()
+        };
+
+        // the uosmo paid to the vault locks is a reward, joined once the fee is paid
+        let res = compound(deps.as_mut());
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(res.messages[0], fee_msg(100));
+        let join: MsgJoinSwapExternAmountIn = decode(&res.messages[1]);
+        assert_eq!(
+            join.token_in,
+            Some(ProtoCoin {
+                denom: "uosmo".to_string(),
+                amount: "900".to_string(),
+            })
+        );
+
+        // what the joins leave over is no reward of the next harvest
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(3, "uosmo")]);
+        let joined = MsgJoinSwapExternAmountInResponse {
+            share_out_amount: "44".to_string(),
+        };
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            reply_ok(JOIN_SWAP_EXTERN_REPLY_ID, joined),
+        )
+        .unwrap();
+        assert_eq!(
+            SUPERFLUID_OSMO_BASE.load(deps.as_ref().storage).unwrap(),
+            Uint128::new(3)
+        );
+        let harvest = HARVESTS.back(deps.as_ref().storage).unwrap().unwrap();
+        assert_eq!(harvest.rewards, vec![coin(1_000, "uosmo")]);
+        assert_eq!(harvest.fees, vec![coin(100, "uosmo")]);
+
+        deps.querier.set_day_epoch(2, 1_571_797_000 + 86_400);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(503, "uosmo")]);
+        let res = compound(deps.as_mut());
+        assert_eq!(res.messages[0], fee_msg(50));
+        let join: MsgJoinSwapExternAmountIn = decode(&res.messages[1]);
+        assert_eq!(join.token_in.unwrap().amount, "453");
+    }
+
+    #[test]
+    fn unknown_reply_id() {
+        let mut deps = mock_osmosis_dependencies(&[]);
+        let err = reply(deps.as_mut(), mock_env(), reply_ok(42, Binary::default())).unwrap_err();
+        assert_eq!(err, ContractError::UnknownReplyId { id: 42 });
+    }
+}
@@ -1,37 +1,67 @@
 
-use std::convert::TryInto;
-
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::Order::Ascending;
 use cosmwasm_std::{
-    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128, CosmosMsg, BankMsg
+    to_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Reply, Response, SubMsg, StdError, StdResult, Uint128, CosmosMsg, BankMsg
 };
 
 use cw2::set_contract_version;
 use cw20::{
-    BalanceResponse, Pair, Cw20Coin, Cw20ReceiveMsg, DownloadLogoResponse, EmbeddedLogo, Logo, LogoInfo,
+    BalanceResponse, Pair, Cw20ReceiveMsg, DownloadLogoResponse, EmbeddedLogo, Logo, LogoInfo,
     MarketingInfoResponse, MinterResponse, TokenInfoResponse, AddBondReceiveMsg, AddLiquidityReceiveMsg,
-    PoolInfoResponse
+    PoolInfoResponse, RouteInfo, RoutesResponse
 };
+use cosmwasm_schema::cw_serde;
+use cw_storage_plus::Item;
 use cw_utils::ensure_from_older_version;
 use osmosis_std::shim::Duration;
-use osmosis_std::types::cosmos::base::query::v1beta1::PageRequest;
-use prost::DecodeError;
 
 use crate::allowances::{
     execute_burn_from, execute_decrease_allowance, execute_increase_allowance, execute_send_from,
     execute_transfer_from, query_allowance,
 };
+use crate::emergency::{
+    assert_not_halted, execute_emergency_exit, execute_pause, execute_unpause,
+    query_vault_status, reply_emergency_exit, EMERGENCY_EXIT_REPLY_ID,
+};
 use crate::enumerable::{query_all_accounts, query_owner_allowances, query_spender_allowances};
 use crate::error::ContractError;
 use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 use crate::state::{
-    MinterData, TokenInfo, PoolInfo, ALLOWANCES, ALLOWANCES_SPENDER, BALANCES, LOGO, MARKETING_INFO,
-    TOKEN_INFO, POOL_INFO, State, STATE,
+    TokenInfo, ALLOWANCES, ALLOWANCES_SPENDER, BALANCES, LOGO, MARKETING_INFO,
+    TOKEN_INFO, POOL_INFO, CompoundState, State, STATE, OWNER, OPERATOR, DEPOSITOR, GUARDIAN,
+    ROUTES, COMPOUND, SUPERFLUID_OSMO_BASE,
+};
+use crate::roles::{
+    assert_owner, assert_owner_or_operator, execute_accept_ownership, execute_update_depositor,
+    execute_update_guardian, execute_update_operator, execute_update_owner, query_roles,
+};
+use crate::compound::{
+    execute_compound, harvest, CONVERT_SWAP_REPLY_ID, DEFAULT_LOCK_DURATION_SECONDS,
 };
-use osmosis_std::types::osmosis::gamm::v1beta1::{MsgJoinPool, GammQuerier, SwapAmountInRoute, QueryPoolResponse, MsgSwapExactAmountIn};
-use osmosis_std::types::osmosis::lockup::{MsgLockTokens, MsgBeginUnlockingAll, MsgBeginUnlocking};
+use crate::epochs::query_next_compound_epoch;
+use crate::history::{query_harvest_history, record_harvest};
+use crate::keeper::{execute_update_keeper, query_keeper};
+use crate::metrics::{current_share_price, query_apy, query_share_price, query_tvl};
+use crate::pool::{load_pool_info, parse_amount, query_pool, query_simulate_join};
+use crate::shares::{assert_cw20_shares, create_share_denom, share_balance};
+use crate::swap::{validate_slippage, DEFAULT_MAX_SLIPPAGE_BPS};
+use crate::locks::{
+    query_lock, query_locks, record_unlock, record_unlock_all, reply_lock, unlock_msgs,
+    LOCK_REPLY_ID, SUPERFLUID_LOCK_REPLY_ID,
+};
+use crate::unbonding::{
+    execute_claim, execute_request_unbond, query_claims, query_pending_unbonds, reply_exit_pool,
+    EXIT_POOL_REPLY_ID, EXIT_SWAP_SHARE_REPLY_ID,
+};
+use crate::vault::{execute_deposit, execute_withdraw};
+use crate::zap::{
+    execute_deposit_single, execute_zap, DEPOSIT_SINGLE_REPLY_ID, ZAP_JOIN_REPLY_ID,
+    ZAP_SWAP_REPLY_ID,
+};
+use osmosis_std::types::osmosis::gamm::v1beta1::{MsgJoinPool, SwapAmountInRoute};
+use osmosis_std::types::osmosis::lockup::{MsgLockTokens, MsgBeginUnlockingAll};
 use osmosis_std::types::cosmos::base::v1beta1::Coin;
 // use osmosis_testing::{Account, Module, OsmosisTestApp, Wasm, Gamm};
 
@@ -105,55 +135,41 @@ fn verify_logo(logo: &Logo) -> Result<(), ContractError> {
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     mut deps: DepsMut,
-    _env: Env,
+    env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     // check valid token info
     msg.validate()?;
-    // create initial accounts
-    let total_supply = create_accounts(&mut deps, &msg.initial_balances)?;
-
-    if let Some(limit) = msg.get_cap() {
-        if total_supply > limit {
-            return Err(StdError::generic_err("Initial supply greater than cap").into());
-        }
-    }
-
-    let mint = match msg.mint {
-        Some(m) => Some(MinterData {
-            minter: deps.api.addr_validate(&m.minter)?,
-            cap: m.cap,
-        }),
-        None => None,
-    };
 
     // store state info
     let state = State { 
-        owner: _info.sender.clone(),
         fee: msg.fee,
-        fee_collector_address: msg.fee_collector_address,
+        fee_collector_address: deps.api.addr_validate(&msg.fee_collector_address)?,
+        max_slippage_bps: DEFAULT_MAX_SLIPPAGE_BPS,
+        lock_duration_seconds: DEFAULT_LOCK_DURATION_SECONDS,
+        superfluid_validator: msg.superfluid_validator,
     };
     STATE.save(deps.storage, &state)?;
+    OWNER.set(deps.branch(), Some(_info.sender.clone()))?;
+    OPERATOR.set(deps.branch(), None)?;
+    DEPOSITOR.set(deps.branch(), None)?;
+    GUARDIAN.set(deps.branch(), None)?;
 
-    // store token info
+    // store token info, vault shares are only minted for deposits
     let data = TokenInfo {
         name: msg.name,
         symbol: msg.symbol,
         decimals: msg.decimals,
-        total_supply,
-        mint,
+        total_supply: Uint128::zero(),
+        mint: None,
     };
     TOKEN_INFO.save(deps.storage, &data)?;
 
-    // store pool info
-    let pool_info = PoolInfo {
-        id: msg.id,
-        denom_1: msg.denom_1,
-        denom_2: msg.denom_2,
-        white_list_denoms: msg.white_list_denoms,
-    };
+    // store pool info, checked against the pool on chain
+    let denoms = msg.denom_1.into_iter().chain(msg.denom_2).collect();
+    let pool_info = load_pool_info(deps.as_ref(), msg.id, denoms, msg.white_list_denoms)?;
     POOL_INFO.save(deps.storage, &pool_info)?;
 
     if let Some(marketing) = msg.marketing {
@@ -181,38 +197,13 @@ pub fn instantiate(
         MARKETING_INFO.save(deps.storage, &data)?;
     }
 
-    Ok(Response::default())
-}
-
-pub fn create_accounts(
-    deps: &mut DepsMut,
-    accounts: &[Cw20Coin],
-) -> Result<Uint128, ContractError> {
-    validate_accounts(accounts)?;
-
-    let mut total_supply = Uint128::zero();
-    for row in accounts {
-        let address = deps.api.addr_validate(&row.address)?;
-        BALANCES.save(deps.storage, &address, &row.amount)?;
-        total_supply += row.amount;
-    }
-
-    Ok(total_supply)
-}
-
-pub fn validate_accounts(accounts: &[Cw20Coin]) -> Result<(), ContractError> {
-    let mut addresses = accounts.iter().map(|c| &c.address).collect::<Vec<_>>();
-    addresses.sort();
-    addresses.dedup();
-
-    if addresses.len() != accounts.len() {
-        Err(ContractError::DuplicateInitialBalanceAddresses {})
-    } else {
-        Ok(())
+    let mut res = Response::default();
+    if let Some(subdenom) = msg.share_subdenom {
+        res = res.add_message(create_share_denom(deps.storage, &env, subdenom)?);
     }
+    Ok(res)
 }
 
-
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
@@ -220,33 +211,67 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    assert_not_halted(deps.as_ref(), &msg)?;
+    assert_cw20_shares(deps.as_ref(), &msg)?;
     match msg {
 
         ExecuteMsg::Transfer { recipient, amount } => {
             execute_transfer(deps, env, info, recipient, amount)
         }
 
-        ExecuteMsg::JoinPool { pool_id, amount, token_in_maxs } => execute_join_pool(env, pool_id, amount, token_in_maxs),
+        ExecuteMsg::JoinPool { pool_id, token_in_maxs } => execute_join_pool(deps, env, info, pool_id, token_in_maxs),
         
-        ExecuteMsg::AddBond { owner, duration, coins} => execute_bond(owner, duration, coins),
+        ExecuteMsg::AddBond { owner, duration, coins} => execute_bond(deps, info, owner, duration, coins),
 
-        ExecuteMsg::UnbondAll { } => execute_unbond_all(env),
+        ExecuteMsg::UnbondAll { } => execute_unbond_all(deps, env, info),
         
-        ExecuteMsg::Unbond { id } => execute_unbond(env, id),
+        ExecuteMsg::Unbond { id, coins } => execute_unbond(deps, env, info, id, coins),
 
-        ExecuteMsg::ConvertRewards { } => execute_convert_rewards(env, deps, info),
+        ExecuteMsg::ConvertRewards { max_slippage_bps } => execute_convert_rewards(env, deps, info, max_slippage_bps),
 
-        ExecuteMsg::WithdrawTokens {to_address, tokens} => execute_withdraw_tokens(to_address, tokens),
+        ExecuteMsg::WithdrawTokens {to_address, tokens} => execute_withdraw_tokens(deps, info, to_address, tokens),
 
         ExecuteMsg::UpdateWhiteList { coins } => execute_white_list_update(deps, info, coins),
 
+        ExecuteMsg::SetRoute { denom, route } => execute_set_route(deps, info, denom, route),
+        ExecuteMsg::RemoveRoute { denom } => execute_remove_route(deps, info, denom),
+        ExecuteMsg::UpdateMaxSlippage { max_slippage_bps } => {
+            execute_update_max_slippage(deps, info, max_slippage_bps)
+        }
+        ExecuteMsg::UpdateLockDuration { seconds } => {
+            execute_update_lock_duration(deps, info, seconds)
+        }
+
+        ExecuteMsg::Compound {} => execute_compound(deps, env, info),
+        ExecuteMsg::UpdateKeeper { keeper } => execute_update_keeper(deps, info, keeper),
+
+        ExecuteMsg::UpdateOwner { owner } => execute_update_owner(deps, info, owner),
+        ExecuteMsg::AcceptOwnership {} => execute_accept_ownership(deps, info),
+        ExecuteMsg::UpdateOperator { operator } => execute_update_operator(deps, info, operator),
+        ExecuteMsg::UpdateDepositor { depositor } => {
+            execute_update_depositor(deps, info, depositor)
+        }
+        ExecuteMsg::UpdateGuardian { guardian } => execute_update_guardian(deps, info, guardian),
+
+        ExecuteMsg::Pause {} => execute_pause(deps, info),
+        ExecuteMsg::Unpause {} => execute_unpause(deps, info),
+        ExecuteMsg::EmergencyExit {} => execute_emergency_exit(deps, env, info),
+
+        ExecuteMsg::Deposit {} => execute_deposit(deps, env, info),
+        ExecuteMsg::DepositSingle {} => execute_deposit_single(deps, env, info),
+        ExecuteMsg::Zap {} => execute_zap(deps, env, info),
+        ExecuteMsg::Withdraw { shares } => execute_withdraw(deps, env, info, shares),
+        ExecuteMsg::RequestUnbond { shares } => execute_request_unbond(deps, env, info, shares),
+        ExecuteMsg::Claim { exit } => execute_claim(deps, env, info, exit),
+
         ExecuteMsg::Burn { amount } => execute_burn(deps, env, info, amount),
         ExecuteMsg::Send {
             contract,
             amount,
             msg,
         } => execute_send(deps, env, info, contract, amount, msg),
-        ExecuteMsg::Mint { recipient, amount } => execute_mint(deps, env, info, recipient, amount),
+        // vault shares are only minted for deposited LP, see `issue_shares`
+        ExecuteMsg::Mint { .. } => Err(ContractError::SharesOnlyForDeposits {}),
         ExecuteMsg::IncreaseAllowance {
             spender,
             amount,
@@ -275,19 +300,14 @@ pub fn execute(
             marketing,
         } => execute_update_marketing(deps, env, info, project, description, marketing),
         ExecuteMsg::UploadLogo(logo) => execute_upload_logo(deps, env, info, logo),
-        ExecuteMsg::UpdateMinter { new_minter } => {
-            execute_update_minter(deps, env, info, new_minter)
-        }
+        ExecuteMsg::UpdateMinter { .. } => Err(ContractError::SharesOnlyForDeposits {}),
     }
 }
 
 
 pub fn execute_white_list_update(deps: DepsMut, info: MessageInfo, coins: Vec<String>) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
+    assert_owner(deps.as_ref(), &info.sender)?;
     POOL_INFO.update(deps.storage, |mut pool_info| -> Result<_, ContractError> {
-        if info.sender != state.owner {
-            return Err(ContractError::Unauthorized {});
-        }
         pool_info.white_list_denoms = coins;
         
         Ok(pool_info)
@@ -295,112 +315,136 @@ pub fn execute_white_list_update(deps: DepsMut, info: MessageInfo, coins: Vec<St
     Ok(Response::new().add_attribute("method", "update white list"))
 }
 
-fn query_pool(
-    deps: &DepsMut,
-    pool_id: u64,
-) -> StdResult<osmosis_std::types::osmosis::gamm::v1beta1::Pool> {
-    let res = GammQuerier::new(&deps.querier).pool(pool_id)?;
-    res.pool
-        .ok_or_else(|| StdError::NotFound {
-            kind: "pool".to_string(),
-        })?
-        .try_into() // convert `Any` to `osmosis_std::types::osmosis::gamm::v1beta1::Pool`
-        .map_err(|e: DecodeError| StdError::ParseErr {
-            target_type: "osmosis_std::types::osmosis::gamm::v1beta1::Pool".to_string(),
-            msg: e.to_string(),
-        })
+/// Stores the swap route `denom` rewards take to end up in one of the pool assets.
+pub fn execute_set_route(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    route: Vec<SwapAmountInRoute>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+
+    let pool_info = POOL_INFO.load(deps.storage)?;
+    let is_pool_asset = |d: &str| d == pool_info.denom_1 || d == pool_info.denom_2;
+    match route.last() {
+        Some(hop) if is_pool_asset(&hop.token_out_denom) && !is_pool_asset(&denom) => {}
+        _ => return Err(ContractError::InvalidRoute {}),
+    }
+    ROUTES.save(deps.storage, &denom, &route)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_route")
+        .add_attribute("denom", denom)
+        .add_attribute("hops", route.len().to_string()))
+}
+
+pub fn execute_remove_route(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    ROUTES.remove(deps.storage, &denom);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_route")
+        .add_attribute("denom", denom))
+}
+
+pub fn execute_update_max_slippage(
+    deps: DepsMut,
+    info: MessageInfo,
+    max_slippage_bps: u64,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    let max_slippage_bps = validate_slippage(max_slippage_bps)?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.max_slippage_bps = max_slippage_bps;
+        Ok(state)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_max_slippage")
+        .add_attribute("max_slippage_bps", max_slippage_bps.to_string()))
+}
+
+pub fn execute_update_lock_duration(
+    deps: DepsMut,
+    info: MessageInfo,
+    seconds: u64,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.lock_duration_seconds = seconds;
+        Ok(state)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_lock_duration")
+        .add_attribute("seconds", seconds.to_string()))
 }
 
 pub fn execute_convert_rewards(
     env: Env,
     deps: DepsMut,
-    info: MessageInfo
+    info: MessageInfo,
+    max_slippage_bps: Option<u64>,
 ) -> Result<Response, ContractError>  {
+    assert_owner_or_operator(deps.as_ref(), &info.sender)?;
     let state = STATE.load(deps.storage)?;
-    if info.sender != state.owner {
-        return Err(ContractError::Unauthorized {});
-    }
-
-    let _contract_address = env.contract.address;
-    let balances = deps.querier.query_all_balances(&_contract_address);
-    if let Err(_e) = balances {
-        return Err(ContractError::NoValidAddress {});
+    // the operator may only tighten the slippage the owner allows
+    let max_slippage_bps = match max_slippage_bps {
+        Some(bps) if bps > state.max_slippage_bps => {
+            return Err(ContractError::SlippageAboveMax {
+                max_slippage_bps: state.max_slippage_bps,
+            })
+        }
+        Some(bps) => bps,
+        None => state.max_slippage_bps,
+    };
+    let harvest = harvest(deps.as_ref(), &env, &state, max_slippage_bps, Decimal::zero())?;
+    if let Some(osmo_left) = harvest.osmo_left {
+        SUPERFLUID_OSMO_BASE.save(deps.storage, &osmo_left)?;
     }
 
-    let balances_unwrapped = balances.unwrap();
-    if balances_unwrapped.len() == 0 {
-        return Err(ContractError::NoBalancesFound {});
+    let mut res = Response::new()
+        .add_attribute("method", "Convert rewards")
+        .add_attributes(harvest.attributes());
+    if let Some(fee_msg) = harvest.fee_msg(&state) {
+        res = res.add_message(fee_msg);
     }
 
-    let info = POOL_INFO.load(deps.storage)?;
-    let mut messages = Vec::new();
-
-    let pools_id = Vec::from([1]);
-
-    for coin in &balances_unwrapped{
-        if ((&coin.denom != &info.denom_1) || (&coin.denom != &info.denom_2 )) && (info.white_list_denoms.contains(&coin.denom)){ 
-            let mut swappable_denom = "".to_string();
-            let mut pool_id = 0;
-            for &id in &pools_id{
-                pool_id = id;
-                let pool = query_pool(&deps, id).unwrap();
-                let pool_asset = pool.pool_assets;
-                let denom1 = &pool_asset[0].token.as_ref().unwrap().denom;
-                let denom2 = &pool_asset[1].token.as_ref().unwrap().denom;
-                if &coin.denom == denom1{
-                    swappable_denom = denom2.to_string();
-                    break;
-                }
-                if &coin.denom == denom2{
-                    swappable_denom = denom1.to_string();
-                    break;
-                }
-            }
-            if (pool_id != 0 ) && (swappable_denom != ""){
-                let msg_: CosmosMsg = MsgSwapExactAmountIn {
-                    sender: _contract_address.to_string(),
-                    token_out_min_amount: "1".to_string(),
-                    token_in: Some(Coin{
-                        denom: coin.denom.to_string(),
-                        amount: coin.amount.to_string()
-                    }),
-                    routes: Vec::from([SwapAmountInRoute{
-                        pool_id: pool_id,
-                        token_out_denom: swappable_denom
-                    }])
-                }.into();
-                messages.push(msg_);
-            }
-           
-        }
+    let record = CompoundState {
+        pending_swaps: harvest.swap_out_denoms(),
+        pending_joins: 0,
+        shares_out: Uint128::zero(),
+        harvested: harvest.harvested,
+        fees: harvest.fees,
+        bounty: vec![],
+        swap_outputs: vec![],
     };
-    //TODO: Make fee collector as a function 
-
-    if state.fee != 0 {
-        messages.push(
-            BankMsg::Send {
-                to_address: state.fee_collector_address,
-                amount: Vec::from([
-                    cosmwasm_std::Coin{
-                        // TODO: Change denom
-                        denom: "uosmo".to_string(),
-                        // TODO: set calculated fee
-                        amount: Uint128::new(123213),
-                    }
-                ]),
-            }.into()
-        );
+    if harvest.swaps.is_empty() {
+        let share_price = current_share_price(deps.as_ref(), &env)?;
+        record_harvest(deps.storage, &env, record, share_price)?;
+        return Ok(res);
     }
-
-
-    Ok(Response::new().add_messages(messages,)
-        .add_attribute("method", "Convert rewards")
-)}
+    // the harvest is recorded with the swap outputs once the last swap replied
+    COMPOUND.save(deps.storage, &record)?;
+    Ok(res.add_submessages(
+        harvest
+            .swaps
+            .into_iter()
+            .map(|swap| SubMsg::reply_on_success(swap, CONVERT_SWAP_REPLY_ID)),
+    ))
+}
 
 pub fn execute_withdraw_tokens(
+    deps: DepsMut,
+    info: MessageInfo,
     to_address: String,
     tokens: Vec<cosmwasm_std::Coin>
 )  -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
 
     let messages = tokens.into_iter().map(|coin| BankMsg::Send {
         to_address: to_address.to_string(),
@@ -411,58 +455,112 @@ pub fn execute_withdraw_tokens(
         .add_attribute("method", "Send tokens"))
 }
 
+/// Messages starting the unlock of every lock of the vault.
+pub fn unlock_all_msgs(deps: Deps, env: &Env) -> Result<Vec<CosmosMsg>, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    // superfluid locks can't be unlocked in bulk, each has to be undelegated first
+    if state.superfluid_validator.is_some() {
+        let mut msgs = vec![];
+        for id in query_locks(deps)?.locks.into_iter().map(|lock| lock.id) {
+            msgs.extend(unlock_msgs(env, &state, id, vec![])?);
+        }
+        Ok(msgs)
+    } else {
+        Ok(vec![MsgBeginUnlockingAll {owner: env.contract.address.to_string()}.into()])
+    }
+}
+
 pub fn execute_unbond_all(
+    deps: DepsMut,
     env: Env,
+    info: MessageInfo,
 ) -> Result<Response, ContractError> {
-    let sender = env.contract.address.into();
-
-    let msg_unbond: CosmosMsg = MsgBeginUnlockingAll {owner: sender}.into();
+    assert_owner_or_operator(deps.as_ref(), &info.sender)?;
+    let msgs = unlock_all_msgs(deps.as_ref(), &env)?;
+    record_unlock_all(deps.storage)?;
 
     Ok(Response::new()
-        .add_message(msg_unbond,)
+        .add_messages(msgs)
         .add_attribute("method", "Undond all locks"))
 }
 
 pub fn execute_unbond(
+    deps: DepsMut,
     env: Env,
-    id: u64
+    info: MessageInfo,
+    id: u64,
+    coins: Vec<cosmwasm_std::Coin>,
 ) -> Result<Response, ContractError> {
-    
-    let sender = env.contract.address.into();
-
-    let msg_unbond: CosmosMsg = MsgBeginUnlocking {owner: sender, id: id, coins: Vec::new()}.into();
+    assert_owner_or_operator(deps.as_ref(), &info.sender)?;
+    let state = STATE.load(deps.storage)?;
+    record_unlock(deps.storage, id, &coins)?;
 
     Ok(Response::new()
-        .add_message(msg_unbond,)
+        .add_messages(unlock_msgs(&env, &state, id, coins)?)
         .add_attribute("method", "Undond certain lock"))
 }
 
 pub fn execute_join_pool(
+    deps: DepsMut,
     env: Env,
+    info: MessageInfo,
     pool_id: u64,
-    share_out_amount: String,
     token_in_maxs: Vec<Coin>,
 ) -> Result<Response, ContractError> {
+    assert_owner_or_operator(deps.as_ref(), &info.sender)?;
+    // the LP of any other pool would sit in the vault uncounted
+    if pool_id != POOL_INFO.load(deps.storage)?.id {
+        return Err(ContractError::NotVaultPool { pool_id });
+    }
 
-    let sender = env.contract.address.into();
+    // what the vault holds of every pool asset, capped by `token_in_maxs`
+    let pool = query_pool(deps.as_ref(), pool_id)?;
+    let held = pool
+        .assets()?
+        .into_iter()
+        .map(|asset| {
+            let mut held = deps.querier.query_balance(&env.contract.address, &asset.denom)?;
+            if let Some(max) = token_in_maxs.iter().find(|max| max.denom == asset.denom) {
+                held.amount = held.amount.min(parse_amount(Some(&max.amount))?);
+            }
+            Ok(held)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let (share_out_amount, _) = pool.simulate_join(&held)?;
+    if share_out_amount.is_zero() {
+        return Err(ContractError::NoBalancesFound {});
+    }
+
+    let msg_join_pool: CosmosMsg = MsgJoinPool {
+        sender: env.contract.address.into(),
+        pool_id,
+        share_out_amount: share_out_amount.to_string(),
+        token_in_maxs: held
+            .into_iter()
+            .map(|coin| Coin { denom: coin.denom, amount: coin.amount.to_string() })
+            .collect(),
+    }
+    .into();
 
-    let msg_create_denom: CosmosMsg = MsgJoinPool { sender, pool_id, share_out_amount, token_in_maxs }.into();
-    
     Ok(Response::new()
-        .add_message(msg_create_denom)
-        .add_attribute("method", "Join Pool"))
+        .add_message(msg_join_pool)
+        .add_attribute("method", "Join Pool")
+        .add_attribute("share_out_amount", share_out_amount))
 }
 
 pub fn execute_bond(
+    deps: DepsMut,
+    info: MessageInfo,
     owner: String,
     duration: Duration,
     coins: Vec<Coin>,
 ) -> Result<Response, ContractError> {
+    assert_owner_or_operator(deps.as_ref(), &info.sender)?;
 
     let msg_bond: CosmosMsg = MsgLockTokens { owner, duration: Some(duration), coins }.into();
     
     Ok(Response::new()
-        .add_message(msg_bond)
+        .add_submessage(SubMsg::reply_on_success(msg_bond, LOCK_REPLY_ID))
         .add_attribute("method", "Bond tokens"))
 }
 
@@ -532,64 +630,6 @@ pub fn execute_burn(
     Ok(res)
 }
 
-pub fn execute_mint(
-    deps: DepsMut,
-    _env: Env,
-    info: MessageInfo,
-    recipient: String,
-    amount: Uint128,
-) -> Result<Response, ContractError> {
-
-    if amount == Uint128::zero() {
-        return Err(ContractError::InvalidZeroAmount {});
-    }
-
-    let config = TOKEN_INFO
-        .may_load(deps.storage)?
-        .ok_or(ContractError::Unauthorized {})?;
-    if config
-        .mint
-        .as_ref()
-        .ok_or(ContractError::Unauthorized {})?
-        .minter
-        != info.sender
-    {
-        return Err(ContractError::Unauthorized {});
-    }
-
-    return mint(deps, recipient, amount, config);
-}
-
-fn mint(
-    deps: DepsMut,
-    recipient: String,
-    amount: Uint128,
-    mut config: TokenInfo,
-)  -> Result<Response, ContractError> {
-    // update supply and enforce cap
-    config.total_supply += amount;
-    if let Some(limit) = config.get_cap() {
-        if config.total_supply > limit {
-            return Err(ContractError::CannotExceedCap {});
-        }
-    }
-    TOKEN_INFO.save(deps.storage, &config)?;
-
-    // add amount to recipient balance
-    let rcpt_addr = deps.api.addr_validate(&recipient)?;
-    BALANCES.update(
-        deps.storage,
-        &rcpt_addr,
-        |balance: Option<Uint128>| -> StdResult<_> { Ok(balance.unwrap_or_default() + amount) },
-    )?;
-
-    let res = Response::new()
-        .add_attribute("action", "mint")
-        .add_attribute("to", recipient)
-        .add_attribute("amount", amount);
-    Ok(res)
-}
-
 pub fn execute_add_liquidity(
     _env: Env,
     msg_type: String,
@@ -688,44 +728,6 @@ pub fn execute_send(
     Ok(res)
 }
 
-pub fn execute_update_minter(
-    deps: DepsMut,
-    _env: Env,
-    info: MessageInfo,
-    new_minter: Option<String>,
-) -> Result<Response, ContractError> {
-    let mut config = TOKEN_INFO
-        .may_load(deps.storage)?
-        .ok_or(ContractError::Unauthorized {})?;
-
-    let mint = config.mint.as_ref().ok_or(ContractError::Unauthorized {})?;
-    if mint.minter != info.sender {
-        return Err(ContractError::Unauthorized {});
-    }
-
-    let minter_data = new_minter
-        .map(|new_minter| deps.api.addr_validate(&new_minter))
-        .transpose()?
-        .map(|minter| MinterData {
-            minter,
-            cap: mint.cap,
-        });
-
-    config.mint = minter_data;
-
-    TOKEN_INFO.save(deps.storage, &config)?;
-
-    Ok(Response::default()
-        .add_attribute("action", "update_minter")
-        .add_attribute(
-            "new_minter",
-            config
-                .mint
-                .map(|m| m.minter.into_string())
-                .unwrap_or_else(|| "None".to_string()),
-        ))
-}
-
 pub fn execute_update_marketing(
     deps: DepsMut,
     _env: Env,
@@ -816,11 +818,40 @@ pub fn execute_upload_logo(
 
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        EXIT_POOL_REPLY_ID | EXIT_SWAP_SHARE_REPLY_ID => reply_exit_pool(deps, msg),
+        EMERGENCY_EXIT_REPLY_ID => reply_emergency_exit(deps, msg),
+        LOCK_REPLY_ID | SUPERFLUID_LOCK_REPLY_ID => reply_lock(deps, env, msg),
+        DEPOSIT_SINGLE_REPLY_ID | ZAP_SWAP_REPLY_ID | ZAP_JOIN_REPLY_ID => {
+            crate::zap::reply(deps, env, msg)
+        }
+        _ => crate::compound::reply(deps, env, msg),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Balance { address } => to_binary(&query_balance(deps, address)?),
         QueryMsg::TokenInfo {} => to_binary(&query_token_info(deps)?),
         QueryMsg::PoolInfo {} => to_binary(&query_pool_info(deps)?),
+        QueryMsg::Roles {} => to_binary(&query_roles(deps)?),
+        QueryMsg::Routes {} => to_binary(&query_routes(deps)?),
+        QueryMsg::Claims { address } => to_binary(&query_claims(deps, address)?),
+        QueryMsg::PendingUnbonds {} => to_binary(&query_pending_unbonds(deps)?),
+        QueryMsg::Locks {} => to_binary(&query_locks(deps)?),
+        QueryMsg::Lock { id } => to_binary(&query_lock(deps, id)?),
+        QueryMsg::SimulateJoin { coins } => to_binary(&query_simulate_join(deps, coins)?),
+        QueryMsg::Tvl { quote_denom } => to_binary(&query_tvl(deps, env, quote_denom)?),
+        QueryMsg::SharePrice {} => to_binary(&query_share_price(deps, env)?),
+        QueryMsg::Apy { window_seconds } => to_binary(&query_apy(deps, env, window_seconds)?),
+        QueryMsg::Keeper {} => to_binary(&query_keeper(deps, env)?),
+        QueryMsg::NextCompoundEpoch {} => to_binary(&query_next_compound_epoch(deps)?),
+        QueryMsg::VaultStatus {} => to_binary(&query_vault_status(deps)?),
+        QueryMsg::HarvestHistory { start_after, limit } => {
+            to_binary(&query_harvest_history(deps, start_after, limit)?)
+        }
         QueryMsg::Minter {} => to_binary(&query_minter(deps)?),
         QueryMsg::Allowance { owner, spender } => {
             to_binary(&query_allowance(deps, owner, spender)?)
@@ -850,9 +881,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
 
 pub fn query_balance(deps: Deps, address: String) -> StdResult<BalanceResponse> {
     let address = deps.api.addr_validate(&address)?;
-    let balance = BALANCES
-        .may_load(deps.storage, &address)?
-        .unwrap_or_default();
+    let balance = share_balance(deps, &address)?;
     Ok(BalanceResponse { balance })
 }
 
@@ -874,10 +903,19 @@ pub fn query_pool_info(deps: Deps) -> StdResult<PoolInfoResponse> {
         denom_1: info.denom_1,
         denom_2: info.denom_2,
         white_list_denoms: info.white_list_denoms,
+        lp_denom: info.lp_denom,
     };
     Ok(res)
 }
 
+pub fn query_routes(deps: Deps) -> StdResult<RoutesResponse> {
+    let routes = ROUTES
+        .range(deps.storage, None, None, Ascending)
+        .map(|item| item.map(|(denom, route)| RouteInfo { denom, route }))
+        .collect::<StdResult<_>>()?;
+    Ok(RoutesResponse { routes })
+}
+
 pub fn query_minter(deps: Deps) -> StdResult<Option<MinterResponse>> {
     let meta = TOKEN_INFO.load(deps.storage)?;
     let minter = match meta.mint {
@@ -910,7 +948,7 @@ pub fn query_download_logo(deps: Deps) -> StdResult<DownloadLogoResponse> {
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(mut deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
     let original_version =
         ensure_from_older_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
@@ -923,76 +961,78 @@ pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, C
             ALLOWANCES_SPENDER.save(deps.storage, (&spender, &owner), &allowance)?;
         }
     }
+
+    // Vaults instantiated before the role system kept the owner inside `State`
+    if OWNER.get(deps.as_ref()).is_err() {
+        #[cw_serde]
+        struct LegacyState {
+            owner: Addr,
+            fee: u64,
+            fee_collector_address: Addr,
+        }
+        let legacy: LegacyState = Item::new("state").load(deps.storage)?;
+        STATE.save(
+            deps.storage,
+            &State {
+                // the legacy fee was never applied, read it as basis points
+                fee: Decimal::from_ratio(legacy.fee, 10_000u64),
+                fee_collector_address: legacy.fee_collector_address,
+                max_slippage_bps: DEFAULT_MAX_SLIPPAGE_BPS,
+                lock_duration_seconds: DEFAULT_LOCK_DURATION_SECONDS,
+                superfluid_validator: None,
+            },
+        )?;
+        OWNER.set(deps.branch(), Some(legacy.owner))?;
+        OPERATOR.set(deps.branch(), None)?;
+        DEPOSITOR.set(deps.branch(), None)?;
+    }
+
+    // Vaults instantiated before the guardian role have none
+    if GUARDIAN.get(deps.as_ref()).is_err() {
+        GUARDIAN.set(deps.branch(), None)?;
+    }
+
+    // Vaults instantiated before the pool was validated don't know their LP denom
+    let mut pool_info = POOL_INFO.load(deps.storage)?;
+    if pool_info.lp_denom.is_empty() {
+        pool_info.lp_denom = query_pool(deps.as_ref(), pool_info.id)?.total_shares()?.denom;
+        POOL_INFO.save(deps.storage, &pool_info)?;
+    }
     Ok(Response::default())
 }
 
 
 #[cfg(test)]
 mod tests {
-    use cosmwasm_std::testing::{
-        mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
-    };
-    use cosmwasm_std::{coins, from_binary, Addr, CosmosMsg, StdError, SubMsg, WasmMsg};
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, coin, coins, from_binary, Addr, CosmosMsg, StdError, SubMsg, WasmMsg};
+    use cw20::Cw20Coin;
 
     use super::*;
     use crate::msg::InstantiateMarketingInfo;
+    use crate::testing::{
+        mock_dependencies, mock_dependencies_with_balance, mock_instantiate_msg, mock_shares,
+    };
 
     fn get_balance<T: Into<String>>(deps: Deps, address: T) -> Uint128 {
         query_balance(deps, address.into()).unwrap().balance
     }
 
     // this will set up the instantiation for other tests
-    fn do_instantiate_with_minter(
-        deps: DepsMut,
-        addr: &str,
-        amount: Uint128,
-        minter: &str,
-        cap: Option<Uint128>,
-    ) -> TokenInfoResponse {
-        _do_instantiate(
-            deps,
-            addr,
-            amount,
-            Some(MinterResponse {
-                minter: minter.to_string(),
-                cap,
-            }),
-        )
-    }
-
-    // this will set up the instantiation for other tests
-    fn do_instantiate(deps: DepsMut, addr: &str, amount: Uint128) -> TokenInfoResponse {
-        _do_instantiate(deps, addr, amount, None)
-    }
-
-    // this will set up the instantiation for other tests
-    fn _do_instantiate(
-        mut deps: DepsMut,
-        addr: &str,
-        amount: Uint128,
-        mint: Option<MinterResponse>,
-    ) -> TokenInfoResponse {
+    fn do_instantiate(mut deps: DepsMut, addr: &str, amount: Uint128) -> TokenInfoResponse {
         let instantiate_msg = InstantiateMsg {
-            name: "Auto Gen".to_string(),
-            symbol: "AUTO".to_string(),
             decimals: 3,
-            id: 1, 
-            denom_1: "uosmo".to_string(),
-            denom_2: "".to_string(),
+            denom_1: Some("uosmo".to_string()),
+            denom_2: None,
             white_list_denoms: Vec::from(["uosmo".to_string()]),
-            fee: 0,
             fee_collector_address: addr.to_string(),
-            initial_balances: vec![Cw20Coin {
-                address: addr.to_string(),
-                amount,
-            }],
-            mint: mint.clone(),
-            marketing: None,
+            ..mock_instantiate_msg()
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
         let res = instantiate(deps.branch(), env, info, instantiate_msg).unwrap();
         assert_eq!(0, res.messages.len());
+        mock_shares(deps.branch(), addr, amount);
 
         let meta = query_token_info(deps.as_ref()).unwrap();
         assert_eq!(
@@ -1005,18 +1045,16 @@ mod tests {
             }
         );
         assert_eq!(get_balance(deps.as_ref(), addr), amount);
-        assert_eq!(query_minter(deps.as_ref()).unwrap(), mint,);
+        assert_eq!(query_minter(deps.as_ref()).unwrap(), None);
         meta
     }
 
     const PNG_HEADER: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
 
     mod instantiate {
-
-        use cosmwasm_std::Uint64;
-
         use super::*;
-        #[test]
+
+        // #[test]
         // fn test_osmosis() {
         //     let app = OsmosisTestApp::new();
         //     let accs = app
@@ -1066,7 +1104,7 @@ mod tests {
         //                 denom_2: "atom".to_string(),
         //                 fee_collector_address: "osmo1cyyzpxplxdzkeea7kwsydadg87357qnahakaks".to_string(),
         //                 white_list_denoms: Vec::from(["uosmo".to_string()]),
-        //                 fee: 0,
+        //                 fee: Decimal::zero(),
         //                 initial_balances: vec![Cw20Coin {
         //                     address: String::from("osmo1cyyzpxplxdzkeea7kwsydadg87357qnahakaks"),
         //                     amount: Uint128::new(12232)
@@ -1109,22 +1147,20 @@ mod tests {
         #[test]
         fn basic() {
             let mut deps = mock_dependencies();
-            let amount = Uint128::from(11223344u128);
             let instantiate_msg = InstantiateMsg {
                 
                 name: "Cash Token".to_string(),
                 symbol: "CASH".to_string(),
                 decimals: 9,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "".to_string(),
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
-                fee: 0,
-                initial_balances: vec![Cw20Coin {
-                    address: String::from("addr0000"),
-                    amount,
-                }],
+                fee: Decimal::zero(),
+                initial_balances: vec![],
                 mint: None,
                 marketing: None,
             };
@@ -1139,101 +1175,49 @@ mod tests {
                     name: "Cash Token".to_string(),
                     symbol: "CASH".to_string(),
                     decimals: 9,
-                    total_supply: amount,
+                    total_supply: Uint128::zero(),
                 }
             );
-            assert_eq!(
-                get_balance(deps.as_ref(), "addr0000"),
-                Uint128::new(11223344)
-            );
+            assert_eq!(query_minter(deps.as_ref()).unwrap(), None);
         }
 
         #[test]
-        fn mintable() {
+        fn no_supply_without_deposits() {
             let mut deps = mock_dependencies();
-            let amount = Uint128::new(11223344);
-            let minter = String::from("asmodat");
-            let limit = Uint128::new(511223344);
+            let info = mock_info("creator", &[]);
+
+            // shares without LP behind them would take from later depositors
             let instantiate_msg = InstantiateMsg {
-                id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "".to_string(),
-                white_list_denoms: Vec::from(["uosmo".to_string()]),
-                name: "Cash Token".to_string(),
-                symbol: "CASH".to_string(),
-                fee_collector_address: "someCollectorAddresss".to_string(),
-                decimals: 9,
-                fee: 0,
                 initial_balances: vec![Cw20Coin {
-                    address: "addr0000".into(),
-                    amount,
+                    address: String::from("addr0000"),
+                    amount: Uint128::new(11223344),
                 }],
-                mint: Some(MinterResponse {
-                    minter: minter.clone(),
-                    cap: Some(limit),
-                }),
-                marketing: None,
+                ..mock_instantiate_msg()
             };
-            let info = mock_info("creator", &[]);
-            let env = mock_env();
-            let res = instantiate(deps.as_mut(), env, info, instantiate_msg).unwrap();
-            assert_eq!(0, res.messages.len());
-
-            assert_eq!(
-                query_token_info(deps.as_ref()).unwrap(),
-                TokenInfoResponse {
-                    name: "Cash Token".to_string(),
-                    symbol: "CASH".to_string(),
-                    decimals: 9,
-                    total_supply: amount,
-                }
-            );
+            let err = instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg)
+                .unwrap_err();
             assert_eq!(
-                get_balance(deps.as_ref(), "addr0000"),
-                Uint128::new(11223344)
-            );
-            assert_eq!(
-                query_minter(deps.as_ref()).unwrap(),
-                Some(MinterResponse {
-                    minter,
-                    cap: Some(limit),
-                }),
+                err,
+                StdError::generic_err(
+                    "Initial balances are not supported, vault shares are only minted for deposits"
+                )
+                .into()
             );
-        }
 
-        #[test]
-        fn mintable_over_cap() {
-            let mut deps = mock_dependencies();
-            let amount = Uint128::new(11223344);
-            let minter = String::from("asmodat");
-            let limit = Uint128::new(11223300);
             let instantiate_msg = InstantiateMsg {
-                name: "Cash Token".to_string(),
-                symbol: "CASH".to_string(),
-                id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "".to_string(),
-                white_list_denoms: Vec::from(["uosmo".to_string()]),
-                fee_collector_address: "someCollectorAddresss".to_string(),
-
-                fee: 0,
-                decimals: 9,
-                initial_balances: vec![Cw20Coin {
-                    address: String::from("addr0000"),
-                    amount,
-                }],
                 mint: Some(MinterResponse {
-                    minter,
-                    cap: Some(limit),
+                    minter: String::from("asmodat"),
+                    cap: Some(Uint128::new(511223344)),
                 }),
-                marketing: None,
+                ..mock_instantiate_msg()
             };
-            let info = mock_info("creator", &[]);
-            let env = mock_env();
-            let err = instantiate(deps.as_mut(), env, info, instantiate_msg).unwrap_err();
+            let err = instantiate(deps.as_mut(), mock_env(), info, instantiate_msg).unwrap_err();
             assert_eq!(
                 err,
-                StdError::generic_err("Initial supply greater than cap").into()
+                StdError::generic_err(
+                    "Minting is not supported, vault shares are only minted for deposits"
+                )
+                .into()
             );
         }
 
@@ -1247,11 +1231,13 @@ mod tests {
                     name: "Cash Token".to_string(),
                     symbol: "CASH".to_string(),
                     id: 1, 
-                    denom_1: "uosmo".to_string(),
-                    denom_2: "".to_string(),
+                    denom_1: Some("uosmo".to_string()),
+                    denom_2: None,
                     white_list_denoms: Vec::from(["uosmo".to_string()]),
-                    fee: 0,
-                    fee_collector_address: "someCollectorAddresss".to_string(),
+                    fee: Decimal::zero(),
+                    fee_collector_address: "fee_collector".to_string(),
+                    superfluid_validator: None,
+                    share_subdenom: None,
                     decimals: 9,
                     initial_balances: vec![],
                     mint: None,
@@ -1293,10 +1279,12 @@ mod tests {
                     name: "Cash Token".to_string(),
                     symbol: "CASH".to_string(),
                     id: 1, 
-                    denom_1: "uosmo".to_string(),
-                    denom_2: "".to_string(),
-                    fee: 0,
-                    fee_collector_address: "someCollectorAddresss".to_string(),
+                    denom_1: Some("uosmo".to_string()),
+                    denom_2: None,
+                    fee: Decimal::zero(),
+                    fee_collector_address: "fee_collector".to_string(),
+                    superfluid_validator: None,
+                    share_subdenom: None,
                     white_list_denoms: Vec::from(["uosmo".to_string()]),
                     decimals: 9,
                     initial_balances: vec![],
@@ -1324,165 +1312,7 @@ mod tests {
     }
 
     #[test]
-    fn can_mint_by_minter() {
-        let mut deps = mock_dependencies();
-
-        let genesis = String::from("genesis");
-        let amount = Uint128::new(11223344);
-        let minter = String::from("asmodat");
-        let limit = Uint128::new(511223344);
-        do_instantiate_with_minter(deps.as_mut(), &genesis, amount, &minter, Some(limit));
-
-        // minter can mint coins to some winner
-        let winner = String::from("lucky");
-        let prize = Uint128::new(222_222_222);
-        let msg = ExecuteMsg::Mint {
-            recipient: winner.clone(),
-            amount: prize,
-        };
-
-        let info = mock_info(minter.as_ref(), &[]);
-        let env = mock_env();
-        let res = execute(deps.as_mut(), env, info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
-        assert_eq!(get_balance(deps.as_ref(), genesis), amount);
-        assert_eq!(get_balance(deps.as_ref(), winner.clone()), prize);
-
-        // but cannot mint nothing
-        let msg = ExecuteMsg::Mint {
-            recipient: winner.clone(),
-            amount: Uint128::zero(),
-        };
-        let info = mock_info(minter.as_ref(), &[]);
-        let env = mock_env();
-        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(err, ContractError::InvalidZeroAmount {});
-
-        // but if it exceeds cap (even over multiple rounds), it fails
-        // cap is enforced
-        let msg = ExecuteMsg::Mint {
-            recipient: winner,
-            amount: Uint128::new(333_222_222),
-        };
-        let info = mock_info(minter.as_ref(), &[]);
-        let env = mock_env();
-        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(err, ContractError::CannotExceedCap {});
-    }
-
-    #[test]
-    fn others_cannot_mint() {
-        let mut deps = mock_dependencies();
-        do_instantiate_with_minter(
-            deps.as_mut(),
-            &String::from("genesis"),
-            Uint128::new(1234),
-            &String::from("minter"),
-            None,
-        );
-
-        let msg = ExecuteMsg::Mint {
-            recipient: String::from("lucky"),
-            amount: Uint128::new(222),
-        };
-        let info = mock_info("anyone else", &[]);
-        let env = mock_env();
-        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(err, ContractError::Unauthorized {});
-    }
-
-    #[test]
-    fn minter_can_update_minter_but_not_cap() {
-        let mut deps = mock_dependencies();
-        let minter = String::from("minter");
-        let cap = Some(Uint128::from(3000000u128));
-        do_instantiate_with_minter(
-            deps.as_mut(),
-            &String::from("genesis"),
-            Uint128::new(1234),
-            &minter,
-            cap,
-        );
-
-        let new_minter = "new_minter";
-        let msg = ExecuteMsg::UpdateMinter {
-            new_minter: Some(new_minter.to_string()),
-        };
-
-        let info = mock_info(&minter, &[]);
-        let env = mock_env();
-        let res = execute(deps.as_mut(), env.clone(), info, msg);
-        assert!(res.is_ok());
-        let query_minter_msg = QueryMsg::Minter {};
-        let res = query(deps.as_ref(), env, query_minter_msg);
-        let mint: MinterResponse = from_binary(&res.unwrap()).unwrap();
-
-        // Minter cannot update cap.
-        assert!(mint.cap == cap);
-        assert!(mint.minter == new_minter)
-    }
-
-    #[test]
-    fn others_cannot_update_minter() {
-        let mut deps = mock_dependencies();
-        let minter = String::from("minter");
-        do_instantiate_with_minter(
-            deps.as_mut(),
-            &String::from("genesis"),
-            Uint128::new(1234),
-            &minter,
-            None,
-        );
-
-        let msg = ExecuteMsg::UpdateMinter {
-            new_minter: Some("new_minter".to_string()),
-        };
-
-        let info = mock_info("not the minter", &[]);
-        let env = mock_env();
-        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(err, ContractError::Unauthorized {});
-    }
-
-    #[test]
-    fn unset_minter() {
-        let mut deps = mock_dependencies();
-        let minter = String::from("minter");
-        let cap = None;
-        do_instantiate_with_minter(
-            deps.as_mut(),
-            &String::from("genesis"),
-            Uint128::new(1234),
-            &minter,
-            cap,
-        );
-
-        let msg = ExecuteMsg::UpdateMinter { new_minter: None };
-
-        let info = mock_info(&minter, &[]);
-        let env = mock_env();
-        let res = execute(deps.as_mut(), env.clone(), info, msg);
-        assert!(res.is_ok());
-        let query_minter_msg = QueryMsg::Minter {};
-        let res = query(deps.as_ref(), env, query_minter_msg);
-        let mint: Option<MinterResponse> = from_binary(&res.unwrap()).unwrap();
-
-        // Check that mint information was removed.
-        assert_eq!(mint, None);
-
-        // Check that old minter can no longer mint.
-        let msg = ExecuteMsg::Mint {
-            recipient: String::from("lucky"),
-            amount: Uint128::new(222),
-        };
-        let info = mock_info("minter", &[]);
-        let env = mock_env();
-        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(err, ContractError::Unauthorized {});
-    }
-
-    #[test]
-    fn no_one_mints_if_minter_unset() {
+    fn nobody_mints_shares() {
         let mut deps = mock_dependencies();
         do_instantiate(deps.as_mut(), &String::from("genesis"), Uint128::new(1234));
 
@@ -1490,87 +1320,19 @@ mod tests {
             recipient: String::from("lucky"),
             amount: Uint128::new(222),
         };
-        let info = mock_info("genesis", &[]);
-        let env = mock_env();
-        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(err, ContractError::Unauthorized {});
-    }
-
-    #[test]
-    fn instantiate_multiple_accounts() {
-        let mut deps = mock_dependencies();
-        let amount1 = Uint128::from(11223344u128);
-        let addr1 = String::from("addr0001");
-        let amount2 = Uint128::from(7890987u128);
-        let addr2 = String::from("addr0002");
-        let info = mock_info("creator", &[]);
-        let env = mock_env();
-
-        // Fails with duplicate addresses
-        let instantiate_msg = InstantiateMsg {
-            name: "Bash Shell".to_string(),
-            symbol: "BASH".to_string(),
-            decimals: 6,
-            id: 1, 
-            denom_1: "uosmo".to_string(),
-            denom_2: "stake".to_string(),
-            fee: 0,
-            fee_collector_address: "someCollectorAddresss".to_string(),
-            white_list_denoms: Vec::from(["uosmo".to_string()]),
-            initial_balances: vec![
-                Cw20Coin {
-                    address: addr1.clone(),
-                    amount: amount1,
-                },
-                Cw20Coin {
-                    address: addr1.clone(),
-                    amount: amount2,
-                },
-            ],
-            mint: None,
-            marketing: None,
-        };
-        let err =
-            instantiate(deps.as_mut(), env.clone(), info.clone(), instantiate_msg).unwrap_err();
-        assert_eq!(err, ContractError::DuplicateInitialBalanceAddresses {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::SharesOnlyForDeposits {});
 
-        // Works with unique addresses
-        let instantiate_msg = InstantiateMsg {
-            name: "Bash Shell".to_string(),
-            symbol: "BASH".to_string(),
-            decimals: 6,
-            id: 1, 
-            denom_1: "uosmo".to_string(),
-            denom_2: "stake".to_string(),
-            fee: 0,
-            fee_collector_address: "someCollectorAddresss".to_string(),
-            white_list_denoms: Vec::from(["uosmo".to_string()]),
-            initial_balances: vec![
-                Cw20Coin {
-                    address: addr1.clone(),
-                    amount: amount1,
-                },
-                Cw20Coin {
-                    address: addr2.clone(),
-                    amount: amount2,
-                },
-            ],
-            mint: None,
-            marketing: None,
+        let msg = ExecuteMsg::UpdateMinter {
+            new_minter: Some("creator".to_string()),
         };
-        let res = instantiate(deps.as_mut(), env, info, instantiate_msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::SharesOnlyForDeposits {});
+        assert_eq!(get_balance(deps.as_ref(), "lucky"), Uint128::zero());
         assert_eq!(
-            query_token_info(deps.as_ref()).unwrap(),
-            TokenInfoResponse {
-                name: "Bash Shell".to_string(),
-                symbol: "BASH".to_string(),
-                decimals: 6,
-                total_supply: amount1 + amount2,
-            }
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            Uint128::new(1234)
         );
-        assert_eq!(get_balance(deps.as_ref(), addr1), amount1);
-        assert_eq!(get_balance(deps.as_ref(), addr2), amount2);
     }
 
     #[test]
@@ -1798,118 +1560,94 @@ mod tests {
     mod migration {
         use super::*;
 
-        use cosmwasm_std::Empty;
         use cw20::{AllAllowancesResponse, AllSpenderAllowancesResponse, SpenderAllowanceInfo};
-        use cw_multi_test::{App, Contract, ContractWrapper, Executor};
         use cw_utils::Expiration;
 
-        fn cw20_contract() -> Box<dyn Contract<Empty>> {
-            let contract = ContractWrapper::new(
-                crate::contract::execute,
-                crate::contract::instantiate,
-                crate::contract::query,
-            )
-            .with_migrate(crate::contract::migrate);
-            Box::new(contract)
-        }
-
         #[test]
         fn test_migrate() {
-            let mut app = App::default();
-
-            let cw20_id = app.store_code(cw20_contract());
-            let cw20_addr = app
-                .instantiate_contract(
-                    cw20_id,
-                    Addr::unchecked("sender"),
-                    &InstantiateMsg {
-                        name: "Token".to_string(),
-                        symbol: "TOKEN".to_string(),
-                        decimals: 6,
-                        id: 1, 
-                        denom_1: "uosmo".to_string(),
-                        denom_2: "stake".to_string(),
-                        fee: 0,
-                        fee_collector_address: "someCollectorAddresss".to_string(),
-                        white_list_denoms: Vec::from(["uosmo".to_string()]),
-                        initial_balances: vec![Cw20Coin {
-                            address: "sender".to_string(),
-                            amount: Uint128::new(100),
-                        }],
-                        mint: None,
-                        marketing: None,
-                    },
-                    &[],
-                    "TOKEN",
-                    Some("sender".to_string()),
-                )
-                .unwrap();
+            let mut deps = mock_dependencies();
+            instantiate(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("sender", &[]),
+                InstantiateMsg {
+                    name: "Token".to_string(),
+                    symbol: "TOKEN".to_string(),
+                    decimals: 6,
+                    id: 1, 
+                    denom_1: Some("uosmo".to_string()),
+                    denom_2: None,
+                    fee: Decimal::zero(),
+                    fee_collector_address: "fee_collector".to_string(),
+                    superfluid_validator: None,
+                    share_subdenom: None,
+                    white_list_denoms: Vec::from(["uosmo".to_string()]),
+                    initial_balances: vec![],
+                    mint: None,
+                    marketing: None,
+                },
+            )
+            .unwrap();
+            mock_shares(deps.as_mut(), "sender", Uint128::new(100));
 
             // no allowance to start
-            let allowance: AllAllowancesResponse = app
-                .wrap()
-                .query_wasm_smart(
-                    cw20_addr.to_string(),
-                    &QueryMsg::AllAllowances {
+            let allowance: AllAllowancesResponse = from_binary(
+                &query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::AllAllowances {
                         owner: "sender".to_string(),
                         start_after: None,
                         limit: None,
                     },
                 )
-                .unwrap();
+                .unwrap(),
+            )
+            .unwrap();
             assert_eq!(allowance, AllAllowancesResponse::default());
 
             // Set allowance
             let allow1 = Uint128::new(7777);
             let expires = Expiration::AtHeight(123_456);
-            let msg = CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: cw20_addr.to_string(),
-                msg: to_binary(&ExecuteMsg::IncreaseAllowance {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("sender", &[]),
+                ExecuteMsg::IncreaseAllowance {
                     spender: "spender".into(),
                     amount: allow1,
                     expires: Some(expires),
-                })
-                .unwrap(),
-                funds: vec![],
-            });
-            app.execute(Addr::unchecked("sender"), msg).unwrap();
-
-            // Now migrate
-            app.execute(
-                Addr::unchecked("sender"),
-                CosmosMsg::Wasm(WasmMsg::Migrate {
-                    contract_addr: cw20_addr.to_string(),
-                    new_code_id: cw20_id,
-                    msg: to_binary(&MigrateMsg {}).unwrap(),
-                }),
+                },
             )
             .unwrap();
 
-            // Smoke check that the contract still works.
-            let balance: cw20::BalanceResponse = app
-                .wrap()
-                .query_wasm_smart(
-                    cw20_addr.clone(),
-                    &QueryMsg::Balance {
-                        address: "sender".to_string(),
-                    },
-                )
-                .unwrap();
+            // Vaults from before the pool check don't know their LP denom
+            let mut pool_info = POOL_INFO.load(&deps.storage).unwrap();
+            assert_eq!(pool_info.lp_denom, "gamm/pool/1");
+            pool_info.lp_denom = String::new();
+            POOL_INFO.save(&mut deps.storage, &pool_info).unwrap();
 
-            assert_eq!(balance.balance, Uint128::new(100));
+            // Now migrate
+            migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+            assert_eq!(query_pool_info(deps.as_ref()).unwrap().lp_denom, "gamm/pool/1");
+
+            // Smoke check that the contract still works.
+            assert_eq!(get_balance(deps.as_ref(), "sender"), Uint128::new(100));
 
             // Confirm that the allowance per spender is there
-            let allowance: AllSpenderAllowancesResponse = app
-                .wrap()
-                .query_wasm_smart(
-                    cw20_addr,
-                    &QueryMsg::AllSpenderAllowances {
+            let allowance: AllSpenderAllowancesResponse = from_binary(
+                &query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::AllSpenderAllowances {
                         spender: "spender".to_string(),
                         start_after: None,
                         limit: None,
                     },
                 )
-                .unwrap();
+                .unwrap(),
+            )
+            .unwrap();
             assert_eq!(
                 allowance.allowances,
                 &[SpenderAllowanceInfo {
@@ -1921,6 +1659,334 @@ mod tests {
         }
     }
 
+    mod routes {
+        use super::*;
+
+        use std::convert::TryFrom;
+
+        use cosmwasm_std::{SubMsgResponse, SubMsgResult};
+        use osmosis_std::types::osmosis::gamm::v1beta1::{
+            MsgSwapExactAmountIn, MsgSwapExactAmountInResponse,
+        };
+
+        use crate::testing::{mock_instantiate_msg, mock_osmosis_dependencies, mock_route_pool};
+
+        fn instantiate_msg(fee: Decimal) -> InstantiateMsg {
+            InstantiateMsg {
+                white_list_denoms: vec!["uion".to_string(), "ustrd".to_string()],
+                fee,
+                ..mock_instantiate_msg()
+            }
+        }
+
+        fn do_instantiate(deps: DepsMut) {
+            let msg = instantiate_msg(Decimal::zero());
+            instantiate(deps, mock_env(), mock_info("creator", &[]), msg).unwrap();
+        }
+
+        fn hop(pool_id: u64, token_out_denom: &str) -> SwapAmountInRoute {
+            SwapAmountInRoute {
+                pool_id,
+                token_out_denom: token_out_denom.to_string(),
+            }
+        }
+
+        #[test]
+        fn set_and_remove_routes() {
+            let mut deps = mock_dependencies();
+            do_instantiate(deps.as_mut());
+
+            let msg = ExecuteMsg::SetRoute {
+                denom: "uion".to_string(),
+                route: vec![hop(2, "uosmo")],
+            };
+            let err = execute(deps.as_mut(), mock_env(), mock_info("rando", &[]), msg.clone())
+                .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            let msg = ExecuteMsg::SetRoute {
+                denom: "ustrd".to_string(),
+                route: vec![hop(5, "uion"), hop(2, "uosmo"), hop(1, "uatom")],
+            };
+            execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            assert_eq!(
+                query_routes(deps.as_ref()).unwrap(),
+                RoutesResponse {
+                    routes: vec![
+                        RouteInfo {
+                            denom: "uion".to_string(),
+                            route: vec![hop(2, "uosmo")],
+                        },
+                        RouteInfo {
+                            denom: "ustrd".to_string(),
+                            route: vec![hop(5, "uion"), hop(2, "uosmo"), hop(1, "uatom")],
+                        },
+                    ]
+                }
+            );
+
+            let msg = ExecuteMsg::RemoveRoute {
+                denom: "uion".to_string(),
+            };
+            let err = execute(deps.as_mut(), mock_env(), mock_info("rando", &[]), msg.clone())
+                .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+            let routes = query_routes(deps.as_ref()).unwrap().routes;
+            assert_eq!(routes.len(), 1);
+            assert_eq!(routes[0].denom, "ustrd");
+        }
+
+        #[test]
+        fn routes_must_end_in_pool_asset() {
+            let mut deps = mock_dependencies();
+            do_instantiate(deps.as_mut());
+
+            for (denom, route) in [
+                ("uion", vec![]),
+                ("uion", vec![hop(2, "ustrd")]),
+                ("uosmo", vec![hop(1, "uatom")]),
+            ] {
+                let msg = ExecuteMsg::SetRoute {
+                    denom: denom.to_string(),
+                    route,
+                };
+                let err =
+                    execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+                assert_eq!(err, ContractError::InvalidRoute {});
+            }
+        }
+
+        #[test]
+        fn convert_rewards_follows_routes() {
+            let mut deps = mock_osmosis_dependencies(&[
+                coin(100, "uatom"),
+                coin(200, "uion"),
+                coin(300, "ustrd"),
+                coin(400, "ujuno"),
+            ]);
+            deps.querier.set_twap(5, "ustrd", "uion", "2");
+            deps.querier.set_twap(2, "uion", "uosmo", "0.5");
+            deps.querier.set_pool(mock_route_pool(5, "ustrd", "uion"));
+            deps.querier.set_pool(mock_route_pool(2, "uion", "uosmo"));
+            do_instantiate(deps.as_mut());
+
+            let msg = ExecuteMsg::SetRoute {
+                denom: "ustrd".to_string(),
+                route: vec![hop(5, "uion"), hop(2, "uosmo")],
+            };
+            execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // uion has no route yet and ujuno isn't whitelisted
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("creator", &[]),
+                ExecuteMsg::ConvertRewards {
+                    max_slippage_bps: None,
+                },
+            )
+            .unwrap();
+            // TWAP value of 300ustrd is 300uosmo, minus the default 1% slippage
+            let swap: CosmosMsg = MsgSwapExactAmountIn {
+                sender: MOCK_CONTRACT_ADDR.to_string(),
+                token_out_min_amount: "297".to_string(),
+                token_in: Some(Coin {
+                    denom: "ustrd".to_string(),
+                    amount: "300".to_string(),
+                }),
+                routes: vec![hop(5, "uion"), hop(2, "uosmo")],
+            }
+            .into();
+            assert_eq!(
+                res.messages,
+                vec![SubMsg::reply_on_success(swap, CONVERT_SWAP_REPLY_ID)]
+            );
+            let history = query_harvest_history(deps.as_ref(), None, None).unwrap();
+            assert_eq!(history.harvests, vec![]);
+
+            // the harvest is recorded with the output of its swaps
+            let data = MsgSwapExactAmountInResponse {
+                token_out_amount: "299".to_string(),
+            };
+            let reply_msg = Reply {
+                id: CONVERT_SWAP_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(data.into()),
+                }),
+            };
+            let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+            assert_eq!(res.attributes[1], ("swapped", "299uosmo"));
+            let history = query_harvest_history(deps.as_ref(), None, None).unwrap();
+            assert_eq!(
+                history.harvests,
+                vec![cw20::HarvestInfo {
+                    id: 1,
+                    height: mock_env().block.height,
+                    time: mock_env().block.time,
+                    rewards: vec![coin(300, "ustrd")],
+                    fees: vec![],
+                    bounty: vec![],
+                    swap_outputs: vec![coin(299, "uosmo")],
+                    lp_added: Uint128::zero(),
+                    share_price: None,
+                }]
+            );
+        }
+
+        #[test]
+        fn max_slippage_setting_and_override() {
+            let mut deps = mock_osmosis_dependencies(&[coin(1_000, "uion")]);
+            deps.querier.set_twap(2, "uion", "uosmo", "1");
+            deps.querier.set_pool(mock_route_pool(2, "uion", "uosmo"));
+            do_instantiate(deps.as_mut());
+            let msg = ExecuteMsg::SetRoute {
+                denom: "uion".to_string(),
+                route: vec![hop(2, "uosmo")],
+            };
+            execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            let min_out = |res: Response| match &res.messages[0].msg {
+                CosmosMsg::Stargate { value, .. } => {
+                    MsgSwapExactAmountIn::try_from(value.clone())
+                        .unwrap()
+                        .token_out_min_amount
+                }
+                msg => panic!("unexpected message {:?}", msg),
+            };
+
+            let msg = ExecuteMsg::UpdateMaxSlippage {
+                max_slippage_bps: 500,
+            };
+            let err = execute(deps.as_mut(), mock_env(), mock_info("rando", &[]), msg.clone())
+                .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            let msg = ExecuteMsg::ConvertRewards {
+                max_slippage_bps: None,
+            };
+            let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+            assert_eq!(min_out(res), "950");
+
+            let msg = ExecuteMsg::ConvertRewards {
+                max_slippage_bps: Some(20),
+            };
+            let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+            assert_eq!(min_out(res), "998");
+
+            // overrides can't loosen the vault setting
+            for max_slippage_bps in [501, 10_001] {
+                let msg = ExecuteMsg::ConvertRewards {
+                    max_slippage_bps: Some(max_slippage_bps),
+                };
+                let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+                    .unwrap_err();
+                assert_eq!(
+                    err,
+                    ContractError::SlippageAboveMax {
+                        max_slippage_bps: 500
+                    }
+                );
+            }
+            let msg = ExecuteMsg::UpdateMaxSlippage {
+                max_slippage_bps: 10_001,
+            };
+            let err =
+                execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+            assert_eq!(err, ContractError::InvalidSlippage {});
+        }
+
+        #[test]
+        fn performance_fee_is_taken_per_denom() {
+            let mut deps = mock_osmosis_dependencies(&[
+                coin(1_000, "uion"),
+                coin(333, "ustrd"),
+                coin(500, "uatom"),
+            ]);
+            deps.querier.set_twap(2, "uion", "uosmo", "1");
+            deps.querier.set_twap(3, "ustrd", "uatom", "1");
+            deps.querier.set_pool(mock_route_pool(2, "uion", "uosmo"));
+            deps.querier.set_pool(mock_route_pool(3, "ustrd", "uatom"));
+            let msg = instantiate_msg(Decimal::percent(10));
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+            for (denom, route) in [("uion", hop(2, "uosmo")), ("ustrd", hop(3, "uatom"))] {
+                let msg = ExecuteMsg::SetRoute {
+                    denom: denom.to_string(),
+                    route: vec![route],
+                };
+                execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+            }
+
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("creator", &[]),
+                ExecuteMsg::ConvertRewards {
+                    max_slippage_bps: Some(0),
+                },
+            )
+            .unwrap();
+
+            // pool assets are not harvested, fees round down
+            assert_eq!(
+                res.messages[0].msg,
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "collector".to_string(),
+                    amount: vec![coin(100, "uion"), coin(33, "ustrd")],
+                })
+            );
+            let swapped: Vec<_> = res.messages[1..]
+                .iter()
+                .map(|msg| match &msg.msg {
+                    CosmosMsg::Stargate { value, .. } => {
+                        let swap = MsgSwapExactAmountIn::try_from(value.clone()).unwrap();
+                        (swap.token_in.unwrap().amount, swap.token_out_min_amount)
+                    }
+                    msg => panic!("unexpected message {:?}", msg),
+                })
+                .collect();
+            assert_eq!(
+                swapped,
+                vec![
+                    ("900".to_string(), "900".to_string()),
+                    ("300".to_string(), "300".to_string()),
+                ]
+            );
+            assert_eq!(
+                res.attributes[1..],
+                [
+                    attr("harvested", "1000uion,333ustrd"),
+                    attr("fee", "100uion,33ustrd"),
+                ]
+            );
+        }
+
+        #[test]
+        fn performance_fee_is_capped() {
+            let mut deps = mock_dependencies();
+            let msg = instantiate_msg(Decimal::percent(51));
+            let err =
+                instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+            assert_eq!(
+                err,
+                ContractError::Std(StdError::generic_err(
+                    "Performance fee must not exceed 50%"
+                ))
+            );
+
+            let msg = instantiate_msg(Decimal::percent(50));
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+            assert_eq!(
+                STATE.load(deps.as_ref().storage).unwrap().fee,
+                Decimal::percent(50)
+            );
+        }
+    }
+
     mod marketing {
         use super::*;
 
@@ -1932,10 +1998,12 @@ mod tests {
                 symbol: "CASH".to_string(),
                 decimals: 9,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 initial_balances: vec![],
                 mint: None,
@@ -1992,10 +2060,12 @@ mod tests {
                 symbol: "CASH".to_string(),
                 decimals: 9,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 initial_balances: vec![],
                 mint: None,
@@ -2053,10 +2123,12 @@ mod tests {
                 initial_balances: vec![],
                 mint: None,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 marketing: Some(InstantiateMarketingInfo {
                     project: Some("Project".to_owned()),
@@ -2110,10 +2182,12 @@ mod tests {
                 symbol: "CASH".to_string(),
                 decimals: 9,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 initial_balances: vec![],
                 mint: None,
@@ -2169,10 +2243,12 @@ mod tests {
                 symbol: "CASH".to_string(),
                 decimals: 9,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 initial_balances: vec![],
                 mint: None,
@@ -2230,10 +2306,12 @@ mod tests {
                 initial_balances: vec![],
                 mint: None,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 marketing: Some(InstantiateMarketingInfo {
                     project: Some("Project".to_owned()),
@@ -2289,10 +2367,12 @@ mod tests {
                 initial_balances: vec![],
                 mint: None,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 marketing: Some(InstantiateMarketingInfo {
                     project: Some("Project".to_owned()),
@@ -2350,10 +2430,12 @@ mod tests {
                 symbol: "CASH".to_string(),
                 decimals: 9,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 initial_balances: vec![],
                 mint: None,
@@ -2411,10 +2493,12 @@ mod tests {
                 initial_balances: vec![],
                 mint: None,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 marketing: Some(InstantiateMarketingInfo {
                     project: Some("Project".to_owned()),
@@ -2464,10 +2548,12 @@ mod tests {
                 symbol: "CASH".to_string(),
                 decimals: 9,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 initial_balances: vec![],
                 mint: None,
@@ -2520,10 +2606,12 @@ mod tests {
                 symbol: "CASH".to_string(),
                 decimals: 9,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 initial_balances: vec![],
                 mint: None,
@@ -2577,10 +2665,12 @@ mod tests {
                 symbol: "CASH".to_string(),
                 decimals: 9,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 initial_balances: vec![],
                 mint: None,
@@ -2635,10 +2725,12 @@ mod tests {
                 initial_balances: vec![],
                 mint: None,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 marketing: Some(InstantiateMarketingInfo {
                     project: Some("Project".to_owned()),
@@ -2696,10 +2788,12 @@ mod tests {
                 symbol: "CASH".to_string(),
                 decimals: 9,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 initial_balances: vec![],
                 mint: None,
@@ -2754,10 +2848,12 @@ mod tests {
                 initial_balances: vec![],
                 mint: None,
                 id: 1, 
-                denom_1: "uosmo".to_string(),
-                denom_2: "stake".to_string(),
-                fee: 0,
-                fee_collector_address: "someCollectorAddresss".to_string(),
+                denom_1: Some("uosmo".to_string()),
+                denom_2: None,
+                fee: Decimal::zero(),
+                fee_collector_address: "fee_collector".to_string(),
+                superfluid_validator: None,
+                share_subdenom: None,
                 white_list_denoms: Vec::from(["uosmo".to_string()]),
                 marketing: Some(InstantiateMarketingInfo {
                     project: Some("Project".to_owned()),
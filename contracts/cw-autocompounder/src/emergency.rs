@@ -0,0 +1,475 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use cosmwasm_std::{
+    coin, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+    Storage, SubMsg, Uint128,
+};
+use cw20::{EmergencyExitInfo, VaultStatusResponse};
+use osmosis_std::types::osmosis::gamm::v1beta1::{MsgExitPool, MsgExitPoolResponse};
+
+use crate::contract::unlock_all_msgs;
+use crate::error::ContractError;
+use crate::locks::record_unlock_all;
+use crate::msg::ExecuteMsg;
+use crate::roles::{assert_owner, assert_owner_or_guardian};
+use crate::shares::burn_shares;
+use crate::state::{EMERGENCY_EXIT, PAUSED, POOL_INFO, TOKEN_INFO};
+use crate::vault::{lp_for_shares, query_vault_lp};
+
+pub const EMERGENCY_EXIT_REPLY_ID: u64 = 11;
+
+/// Rejects the messages held by a pause, or by the withdraw-only mode of an emergency
+/// exit. Transfers of native vault shares go through the bank module and can't be held.
+pub fn assert_not_halted(deps: Deps, msg: &ExecuteMsg) -> Result<(), ContractError> {
+    let held_by_pause = matches!(
+        msg,
+        ExecuteMsg::Deposit {}
+            | ExecuteMsg::DepositSingle {}
+            | ExecuteMsg::Zap {}
+            | ExecuteMsg::Compound {}
+            | ExecuteMsg::ConvertRewards { .. }
+            | ExecuteMsg::Transfer { .. }
+            | ExecuteMsg::Send { .. }
+            | ExecuteMsg::TransferFrom { .. }
+            | ExecuteMsg::SendFrom { .. }
+    );
+    if held_by_pause && PAUSED.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(ContractError::Paused {});
+    }
+
+    // anything that would grow the position the vault is winding down
+    let held_by_exit = matches!(
+        msg,
+        ExecuteMsg::Deposit {}
+            | ExecuteMsg::DepositSingle {}
+            | ExecuteMsg::Zap {}
+            | ExecuteMsg::Compound {}
+            | ExecuteMsg::ConvertRewards { .. }
+            | ExecuteMsg::JoinPool { .. }
+            | ExecuteMsg::AddBond { .. }
+            | ExecuteMsg::RequestUnbond { .. }
+            | ExecuteMsg::Mint { .. }
+    );
+    if held_by_exit && EMERGENCY_EXIT.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::WithdrawOnly {});
+    }
+    Ok(())
+}
+
+pub fn execute_pause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    assert_owner_or_guardian(deps.as_ref(), &info.sender)?;
+    PAUSED.save(deps.storage, &true)?;
+    Ok(Response::new()
+        .add_attribute("action", "pause")
+        .add_attribute("from", info.sender))
+}
+
+pub fn execute_unpause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    PAUSED.remove(deps.storage);
+    Ok(Response::new().add_attribute("action", "unpause"))
+}
+
+/// The first call starts unlocking every lock, the next one exits the pool with all
+/// the LP not owed to claims once nothing is locked anymore.
+///
+/// Only the owner can start the exit. The second stage is left to anyone: the owner
+/// already committed the vault to leaving the pool, the proportional exit has no
+/// parameters to abuse, and holders shouldn't depend on an owner that may be gone
+/// or compromised to get their assets out.
+pub fn execute_emergency_exit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    match EMERGENCY_EXIT.may_load(deps.storage)? {
+        None => start_emergency_exit(deps, env, info),
+        Some(EmergencyExitInfo { assets: None, .. }) => exit_pool(deps, env),
+        Some(_) => Err(ContractError::EmergencyExitDone {}),
+    }
+}
+
+fn start_emergency_exit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    let msgs = unlock_all_msgs(deps.as_ref(), &env)?;
+    record_unlock_all(deps.storage)?;
+    EMERGENCY_EXIT.save(
+        deps.storage,
+        &EmergencyExitInfo {
+            started_at: env.block.time,
+            assets: None,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("action", "emergency_exit")
+        .add_attribute("stage", "unlock"))
+}
+
+fn exit_pool(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let vault_lp = query_vault_lp(deps.as_ref(), &env)?;
+    if !vault_lp.locked.is_zero() {
+        return Err(ContractError::EmergencyExitUnlocking {});
+    }
+    let amount = vault_lp.available_liquid();
+    let res = Response::new()
+        .add_attribute("action", "emergency_exit")
+        .add_attribute("stage", "exit_pool")
+        .add_attribute("amount", amount);
+    if amount.is_zero() {
+        save_exited_assets(deps.storage, vec![])?;
+        return Ok(res);
+    }
+
+    // a proportional exit doesn't swap, there is no slippage to guard against
+    let exit: CosmosMsg = MsgExitPool {
+        sender: env.contract.address.to_string(),
+        pool_id: POOL_INFO.load(deps.storage)?.id,
+        share_in_amount: amount.to_string(),
+        token_out_mins: vec![],
+    }
+    .into();
+    Ok(res.add_submessage(SubMsg::reply_on_success(exit, EMERGENCY_EXIT_REPLY_ID)))
+}
+
+fn save_exited_assets(storage: &mut dyn Storage, assets: Vec<Coin>) -> StdResult<()> {
+    EMERGENCY_EXIT.update(storage, |mut exit| -> StdResult<_> {
+        exit.assets = Some(assets);
+        Ok(exit)
+    })?;
+    Ok(())
+}
+
+/// Keeps the pool assets the emergency exit got for the vault token holders.
+pub fn reply_emergency_exit(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let assets = MsgExitPoolResponse::try_from(msg.result)?
+        .token_out
+        .into_iter()
+        .map(|coin| {
+            Ok(Coin::new(
+                Uint128::from_str(&coin.amount)?.u128(),
+                coin.denom,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    save_exited_assets(deps.storage, assets)?;
+    Ok(Response::new().add_attribute("action", "emergency_exit_payout"))
+}
+
+/// Pool assets vault token holders redeem from, set once the emergency exit left the pool.
+pub fn exited_assets(storage: &dyn Storage) -> StdResult<Option<Vec<Coin>>> {
+    Ok(EMERGENCY_EXIT
+        .may_load(storage)?
+        .and_then(|exit| exit.assets))
+}
+
+/// Burns `shares` vault tokens of the sender for their share of every exited pool asset.
+pub fn withdraw_exited_assets(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    shares: Uint128,
+    mut assets: Vec<Coin>,
+) -> Result<Response, ContractError> {
+    let mut config = TOKEN_INFO.load(deps.storage)?;
+    let payout = assets
+        .iter()
+        .map(|asset| {
+            let amount = lp_for_shares(shares, asset.amount, config.total_supply);
+            coin(amount.u128(), &asset.denom)
+        })
+        .filter(|coin| !coin.amount.is_zero())
+        .collect::<Vec<_>>();
+    if payout.is_empty() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+
+    let messages = burn_shares(deps.storage, &env, &info, &mut config, shares)?;
+    TOKEN_INFO.save(deps.storage, &config)?;
+    for paid in &payout {
+        if let Some(asset) = assets.iter_mut().find(|asset| asset.denom == paid.denom) {
+            asset.amount -= paid.amount;
+        }
+    }
+    save_exited_assets(deps.storage, assets)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: payout,
+        })
+        .add_attribute("action", "withdraw")
+        .add_attribute("from", info.sender)
+        .add_attribute("shares", shares))
+}
+
+pub fn query_vault_status(deps: Deps) -> StdResult<VaultStatusResponse> {
+    Ok(VaultStatusResponse {
+        paused: PAUSED.may_load(deps.storage)?.unwrap_or_default(),
+        emergency_exit: EMERGENCY_EXIT.may_load(deps.storage)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coins, SubMsgResponse, SubMsgResult};
+    use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+    use osmosis_std::types::osmosis::lockup::MsgBeginUnlockingAll;
+
+    use crate::contract::{execute, instantiate, query_balance, reply};
+    use crate::msg::InstantiateMsg;
+    use crate::testing::{
+        mock_instantiate_msg, mock_lock, mock_osmosis_dependencies, mock_shares,
+    };
+
+    const LP: &str = "gamm/pool/1";
+
+    fn instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            denom_1: None,
+            denom_2: None,
+            ..mock_instantiate_msg()
+        }
+    }
+
+    #[test]
+    fn pause_holds_deposits_compounding_and_transfers() {
+        let mut deps = mock_osmosis_dependencies(&[]);
+        let msg = instantiate_msg();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        mock_shares(deps.as_mut(), "alice", Uint128::new(1_000));
+        let msg = ExecuteMsg::UpdateGuardian {
+            guardian: Some("guardian".to_string()),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("rando", &[]),
+            ExecuteMsg::Pause {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let info = mock_info("guardian", &[]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::Pause {},
+        )
+        .unwrap();
+        assert!(query_vault_status(deps.as_ref()).unwrap().paused);
+
+        let transfer = ExecuteMsg::Transfer {
+            recipient: "bob".to_string(),
+            amount: Uint128::new(100),
+        };
+        for (sender, funds, msg) in [
+            ("alice", coins(1_000, LP), ExecuteMsg::Deposit {}),
+            ("owner", vec![], ExecuteMsg::Compound {}),
+            (
+                "owner",
+                vec![],
+                ExecuteMsg::ConvertRewards {
+                    max_slippage_bps: None,
+                },
+            ),
+            ("alice", vec![], transfer.clone()),
+        ] {
+            let err =
+                execute(deps.as_mut(), mock_env(), mock_info(sender, &funds), msg).unwrap_err();
+            assert_eq!(err, ContractError::Paused {});
+        }
+
+        // only the owner lifts it
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Unpause {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let info = mock_info("owner", &[]);
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Unpause {}).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), transfer).unwrap();
+        assert_eq!(
+            query_balance(deps.as_ref(), "bob".to_string())
+                .unwrap()
+                .balance,
+            Uint128::new(100)
+        );
+    }
+
+    #[test]
+    fn anyone_finishes_an_exit_the_owner_started() {
+        // nothing is locked, the pool could be exited right away
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, LP)]);
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            instantiate_msg(),
+        )
+        .unwrap();
+        let info = mock_info("alice", &coins(10_000, LP));
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+        let exit = |deps: DepsMut, sender: &str| {
+            execute(
+                deps,
+                mock_env(),
+                mock_info(sender, &[]),
+                ExecuteMsg::EmergencyExit {},
+            )
+        };
+
+        // still only the owner can start it
+        for sender in ["rando", "alice"] {
+            let err = exit(deps.as_mut(), sender).unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+        }
+        assert!(EMERGENCY_EXIT.may_load(&deps.storage).unwrap().is_none());
+
+        let res = exit(deps.as_mut(), "owner").unwrap();
+        assert_eq!(res.attributes[1], ("stage", "unlock"));
+        let res = exit(deps.as_mut(), "rando").unwrap();
+        assert_eq!(res.attributes[1], ("stage", "exit_pool"));
+        assert_eq!(res.messages[0].id, EMERGENCY_EXIT_REPLY_ID);
+    }
+
+    #[test]
+    fn emergency_exit_redeems_pool_assets() {
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, LP)]);
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            instantiate_msg(),
+        )
+        .unwrap();
+        let info = mock_info("alice", &coins(10_000, LP));
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+        deps.querier.set_locks(vec![mock_lock(1, LP, 5_000)]);
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("rando", &[]),
+            ExecuteMsg::EmergencyExit {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::EmergencyExit {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(MsgBeginUnlockingAll {
+                owner: MOCK_CONTRACT_ADDR.to_string(),
+            })]
+        );
+
+        // withdraw-only from here on
+        for msg in [
+            ExecuteMsg::Deposit {},
+            ExecuteMsg::Compound {},
+            ExecuteMsg::RequestUnbond {
+                shares: Uint128::new(100),
+            },
+        ] {
+            let info = mock_info("alice", &coins(1_000, LP));
+            let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+            assert_eq!(err, ContractError::WithdrawOnly {});
+        }
+
+        // anyone can exit the pool once the owner started the exit and the unlock is over
+        let exit = |deps: DepsMut| {
+            execute(
+                deps,
+                mock_env(),
+                mock_info("rando", &[]),
+                ExecuteMsg::EmergencyExit {},
+            )
+        };
+        let err = exit(deps.as_mut()).unwrap_err();
+        assert_eq!(err, ContractError::EmergencyExitUnlocking {});
+        deps.querier.set_locks(vec![]);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(15_000, LP)]);
+        let res = exit(deps.as_mut()).unwrap();
+        let exit_msg: CosmosMsg = MsgExitPool {
+            sender: MOCK_CONTRACT_ADDR.to_string(),
+            pool_id: 1,
+            share_in_amount: "15000".to_string(),
+            token_out_mins: vec![],
+        }
+        .into();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(exit_msg, EMERGENCY_EXIT_REPLY_ID)]
+        );
+
+        let exited = MsgExitPoolResponse {
+            token_out: vec![
+                ProtoCoin {
+                    denom: "uatom".to_string(),
+                    amount: "150000".to_string(),
+                },
+                ProtoCoin {
+                    denom: "uosmo".to_string(),
+                    amount: "150000".to_string(),
+                },
+            ],
+        };
+        let msg = Reply {
+            id: EMERGENCY_EXIT_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(exited.into()),
+            }),
+        };
+        reply(deps.as_mut(), mock_env(), msg).unwrap();
+        deps.querier.update_balance(
+            MOCK_CONTRACT_ADDR,
+            vec![coin(150_000, "uatom"), coin(150_000, "uosmo")],
+        );
+        let err = exit(deps.as_mut()).unwrap_err();
+        assert_eq!(err, ContractError::EmergencyExitDone {});
+
+        // holders get their share of what is left on every withdraw
+        let withdraw = |deps: DepsMut| {
+            let msg = ExecuteMsg::Withdraw {
+                shares: Uint128::new(4_500),
+            };
+            execute(deps, mock_env(), mock_info("alice", &[]), msg).unwrap()
+        };
+        for _ in 0..2 {
+            let res = withdraw(deps.as_mut());
+            assert_eq!(
+                res.messages,
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "alice".to_string(),
+                    amount: vec![coin(67_500, "uatom"), coin(67_500, "uosmo")],
+                })]
+            );
+        }
+        let status = query_vault_status(deps.as_ref()).unwrap();
+        assert_eq!(
+            status.emergency_exit.unwrap().assets,
+            Some(vec![coin(15_000, "uatom"), coin(15_000, "uosmo")])
+        );
+        assert_eq!(
+            TOKEN_INFO.load(&deps.storage).unwrap().total_supply,
+            Uint128::new(1_000)
+        );
+    }
+}
@@ -82,35 +82,28 @@ pub fn query_all_accounts(
 mod tests {
     use super::*;
 
-    use cosmwasm_std::testing::{mock_dependencies_with_balance, mock_env, mock_info};
+    use cosmwasm_std::testing::{mock_env, mock_info};
     use cosmwasm_std::{coins, from_binary, DepsMut, Uint128};
-    use cw20::{Cw20Coin, Expiration, TokenInfoResponse};
+    use cw20::{Expiration, TokenInfoResponse};
 
     use crate::contract::{execute, instantiate, query, query_token_info};
     use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+    use crate::testing::{mock_dependencies_with_balance, mock_instantiate_msg, mock_shares};
 
     // this will set up the instantiation for other tests
     fn do_instantiate(mut deps: DepsMut, addr: &str, amount: Uint128) -> TokenInfoResponse {
         let instantiate_msg = InstantiateMsg {
-            name: "Auto Gen".to_string(),
-            symbol: "AUTO".to_string(),
-            id: 1, 
-            denom_1: "uosmo".to_string(),
-            denom_2: "stake".to_string(),
-            fee: 0,
+            denom_1: Some("uosmo".to_string()),
+            denom_2: None,
             fee_collector_address: addr.to_string(),
             white_list_denoms: Vec::from(["uosmo".to_string()]),
             decimals: 3,
-            initial_balances: vec![Cw20Coin {
-                address: addr.into(),
-                amount,
-            }],
-            mint: None,
-            marketing: None,
+            ..mock_instantiate_msg()
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
         instantiate(deps.branch(), env, info, instantiate_msg).unwrap();
+        mock_shares(deps.branch(), addr, amount);
         query_token_info(deps.as_ref()).unwrap()
     }
 
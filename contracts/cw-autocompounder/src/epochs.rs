@@ -0,0 +1,114 @@
+use cosmwasm_std::{Deps, StdError, StdResult, Timestamp};
+use cw20::NextCompoundEpochResponse;
+use osmosis_std::shim::Timestamp as ProtoTimestamp;
+use osmosis_std::types::osmosis::epochs::v1beta1::{EpochInfo, EpochsQuerier};
+
+use crate::error::ContractError;
+use crate::state::LAST_COMPOUND_EPOCH;
+
+/// Epoch at the end of which osmosis distributes LP incentives.
+pub const COMPOUND_EPOCH_IDENTIFIER: &str = "day";
+
+fn to_timestamp(time: Option<ProtoTimestamp>) -> Timestamp {
+    let time = time.unwrap_or_default();
+    Timestamp::from_seconds(time.seconds as u64).plus_nanos(time.nanos as u64)
+}
+
+fn compound_epoch(deps: Deps) -> StdResult<EpochInfo> {
+    EpochsQuerier::new(&deps.querier)
+        .epoch_infos()?
+        .epochs
+        .into_iter()
+        .find(|epoch| epoch.identifier == COMPOUND_EPOCH_IDENTIFIER)
+        .ok_or_else(|| StdError::not_found(format!("epoch {}", COMPOUND_EPOCH_IDENTIFIER)))
+}
+
+/// Current epoch, as long as the vault didn't compound in it yet: the incentives of
+/// an epoch are only paid once.
+pub fn assert_new_compound_epoch(deps: Deps) -> Result<u64, ContractError> {
+    let epoch = compound_epoch(deps)?.current_epoch as u64;
+    if LAST_COMPOUND_EPOCH.may_load(deps.storage)? == Some(epoch) {
+        return Err(ContractError::AlreadyCompoundedInEpoch { epoch });
+    }
+    Ok(epoch)
+}
+
+pub fn query_next_compound_epoch(deps: Deps) -> StdResult<NextCompoundEpochResponse> {
+    let epoch = compound_epoch(deps)?;
+    let current_epoch = epoch.current_epoch as u64;
+    let last_compound_epoch = LAST_COMPOUND_EPOCH.may_load(deps.storage)?;
+
+    let current_start = to_timestamp(epoch.current_epoch_start_time);
+    let (next_epoch, starts_at) = if last_compound_epoch == Some(current_epoch) {
+        let duration = epoch.duration.unwrap_or_default();
+        let next_start = current_start
+            .plus_seconds(duration.seconds as u64)
+            .plus_nanos(duration.nanos as u64);
+        (current_epoch + 1, next_start)
+    } else {
+        (current_epoch, current_start)
+    };
+    Ok(NextCompoundEpochResponse {
+        identifier: epoch.identifier,
+        current_epoch,
+        last_compound_epoch,
+        next_epoch,
+        starts_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_env, mock_info};
+    use cosmwasm_std::{coin, DepsMut};
+
+    use crate::contract::{execute, instantiate};
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::testing::{mock_instantiate_msg, mock_osmosis_dependencies};
+
+    const DAY_START: u64 = 1_571_797_000;
+
+    #[test]
+    fn compounds_once_per_epoch() {
+        let mut deps = mock_osmosis_dependencies(&[coin(1_000, "uatom"), coin(1_000, "uosmo")]);
+        let msg = InstantiateMsg {
+            denom_1: None,
+            denom_2: None,
+            ..mock_instantiate_msg()
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(
+            query_next_compound_epoch(deps.as_ref()).unwrap(),
+            NextCompoundEpochResponse {
+                identifier: COMPOUND_EPOCH_IDENTIFIER.to_string(),
+                current_epoch: 1,
+                last_compound_epoch: None,
+                next_epoch: 1,
+                starts_at: Timestamp::from_seconds(DAY_START),
+            }
+        );
+
+        let compound = |deps: DepsMut| {
+            execute(
+                deps,
+                mock_env(),
+                mock_info("owner", &[]),
+                ExecuteMsg::Compound {},
+            )
+        };
+        compound(deps.as_mut()).unwrap();
+        let res = query_next_compound_epoch(deps.as_ref()).unwrap();
+        assert_eq!(res.last_compound_epoch, Some(1));
+        assert_eq!(res.next_epoch, 2);
+        assert_eq!(res.starts_at, Timestamp::from_seconds(DAY_START + 86_400));
+
+        let err = compound(deps.as_mut()).unwrap_err();
+        assert_eq!(err, ContractError::AlreadyCompoundedInEpoch { epoch: 1 });
+
+        deps.querier.set_day_epoch(2, (DAY_START + 86_400) as i64);
+        compound(deps.as_mut()).unwrap();
+        assert_eq!(LAST_COMPOUND_EPOCH.load(&deps.storage).unwrap(), 2);
+    }
+}
@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Decimal, StdError, Uint128};
+use cw_utils::{Expiration, PaymentError};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,9 +7,15 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("No ownership transfer in progress")]
+    NoPendingOwner {},
+
     #[error("Cannot set to own account")]
     CannotSetOwnAccount {},
 
@@ -36,13 +43,87 @@ pub enum ContractError {
     #[error("Invalid expiration value")]
     InvalidExpiration {},
 
-    #[error("Duplicate initial balance addresses")]
-    DuplicateInitialBalanceAddresses {},
-
     #[error("Invalid address. Can't find balance")]
     NoValidAddress {},
 
     #[error("This smartcontract doesn't hold any tokens")]
     NoBalancesFound {},
 
+    #[error("Swap route must be non-empty and end in one of the pool assets")]
+    InvalidRoute {},
+
+    #[error("Max slippage can't exceed 10000 bps")]
+    InvalidSlippage {},
+
+    #[error("Max slippage can't exceed the vault setting of {max_slippage_bps} bps")]
+    SlippageAboveMax { max_slippage_bps: u64 },
+
+    #[error("Deposit is too small to mint any vault shares")]
+    DepositTooSmall {},
+
+    #[error("{denom} is not one of the pool assets")]
+    NotPoolAsset { denom: String },
+
+    #[error("Pool {id} not found")]
+    PoolNotFound { id: u64 },
+
+    #[error("Pool {pool_id} is not the vault pool")]
+    NotVaultPool { pool_id: u64 },
+
+    #[error("Pool {pool_id} has {count} assets, the vault needs a two asset pool")]
+    UnsupportedPoolAssets { pool_id: u64, count: u64 },
+
+    #[error("{denom} is not an asset of pool {pool_id}")]
+    DenomNotInPool { denom: String, pool_id: u64 },
+
+    #[error("{denom} can't be a whitelisted reward denom")]
+    InvalidWhiteListDenom { denom: String },
+
+    #[error("Not enough liquid LP shares in the vault, unbond first")]
+    InsufficientLiquidity {},
+
+    #[error("No matured claims to pay out")]
+    NothingToClaim {},
+
+    #[error("Superfluid locks can only be unlocked as a whole")]
+    SuperfluidPartialUnlock {},
+
+    #[error("Sent vault shares ({sent}) do not match the requested amount ({requested})")]
+    SharesMismatch { sent: Uint128, requested: Uint128 },
+
+    #[error("Vault shares are native tokens, they move through the bank module")]
+    NativeShares {},
+
+    #[error("Vault shares are only minted for deposited LP")]
+    SharesOnlyForDeposits {},
+
+    #[error("Keepers can compound again at {next}")]
+    CompoundTooSoon { next: Expiration },
+
+    #[error("Already compounded in epoch {epoch}")]
+    AlreadyCompoundedInEpoch { epoch: u64 },
+
+    #[error("Rewards are below the keeper minimum")]
+    RewardsBelowMinimum {},
+
+    #[error("Keeper bounty can't be more than {max}")]
+    InvalidKeeperBounty { max: Decimal },
+
+    #[error("The vault is paused")]
+    Paused {},
+
+    #[error("The vault is withdraw-only after an emergency exit")]
+    WithdrawOnly {},
+
+    #[error("An emergency exit already started")]
+    EmergencyExitStarted {},
+
+    #[error("Vault LP is still unlocking, the pool can be exited once the unlock is over")]
+    EmergencyExitUnlocking {},
+
+    #[error("The vault already exited the pool")]
+    EmergencyExitDone {},
+
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
 }
@@ -0,0 +1,120 @@
+use std::convert::TryFrom;
+
+use cosmwasm_std::{Decimal, Deps, Env, StdResult, Storage};
+use cw20::{HarvestHistoryResponse, HarvestInfo};
+
+use crate::state::{CompoundState, HARVESTS};
+
+/// Harvests kept in the history, the oldest one is dropped past that.
+pub const MAX_HARVEST_RECORDS: u32 = 100;
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Appends a finished harvest to the history, with the share price it left the vault at.
+pub fn record_harvest(
+    storage: &mut dyn Storage,
+    env: &Env,
+    harvest: CompoundState,
+    share_price: Option<Decimal>,
+) -> StdResult<()> {
+    let id = HARVESTS.back(storage)?.map(|last| last.id + 1).unwrap_or(1);
+    HARVESTS.push_back(
+        storage,
+        &HarvestInfo {
+            id,
+            height: env.block.height,
+            time: env.block.time,
+            rewards: harvest.harvested,
+            fees: harvest.fees,
+            bounty: harvest.bounty,
+            swap_outputs: harvest.swap_outputs,
+            lp_added: harvest.shares_out,
+            share_price,
+        },
+    )?;
+    if HARVESTS.len(storage)? > MAX_HARVEST_RECORDS {
+        HARVESTS.pop_front(storage)?;
+    }
+    Ok(())
+}
+
+pub fn query_harvest_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<HarvestHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let first_id = match HARVESTS.front(deps.storage)? {
+        Some(first) => first.id,
+        None => return Ok(HarvestHistoryResponse { harvests: vec![] }),
+    };
+    // ids are consecutive, so the position of a harvest follows from its id
+    let start = start_after
+        .map(|id| (id + 1).saturating_sub(first_id))
+        .unwrap_or_default();
+
+    let mut harvests = vec![];
+    for pos in start..start + limit as u64 {
+        let pos = match u32::try_from(pos) {
+            Ok(pos) => pos,
+            Err(_) => break,
+        };
+        match HARVESTS.get(deps.storage, pos)? {
+            Some(harvest) => harvests.push(harvest),
+            None => break,
+        }
+    }
+    Ok(HarvestHistoryResponse { harvests })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{coin, Uint128};
+
+    fn harvest(lp_added: u128) -> CompoundState {
+        CompoundState {
+            pending_swaps: vec![],
+            pending_joins: 0,
+            shares_out: Uint128::new(lp_added),
+            harvested: vec![coin(100, "uion")],
+            fees: vec![],
+            bounty: vec![],
+            swap_outputs: vec![coin(50, "uosmo")],
+        }
+    }
+
+    #[test]
+    fn history_is_capped_and_paginated() {
+        let mut deps = mock_dependencies();
+        let history = query_harvest_history(deps.as_ref(), None, None).unwrap();
+        assert!(history.harvests.is_empty());
+
+        for i in 0..MAX_HARVEST_RECORDS as u128 + 5 {
+            record_harvest(&mut deps.storage, &mock_env(), harvest(i), None).unwrap();
+        }
+        assert_eq!(HARVESTS.len(&deps.storage).unwrap(), MAX_HARVEST_RECORDS);
+
+        let ids = |start_after: Option<u64>, limit: Option<u32>| -> Vec<u64> {
+            query_harvest_history(deps.as_ref(), start_after, limit)
+                .unwrap()
+                .harvests
+                .into_iter()
+                .map(|harvest| harvest.id)
+                .collect()
+        };
+        // the 5 oldest were dropped
+        assert_eq!(ids(None, None), (6..16).collect::<Vec<_>>());
+        assert_eq!(ids(Some(2), Some(3)), vec![6, 7, 8]);
+        assert_eq!(ids(Some(20), Some(2)), vec![21, 22]);
+        assert_eq!(ids(Some(100), Some(100)), (101..106).collect::<Vec<_>>());
+        assert!(ids(Some(105), None).is_empty());
+
+        let last = HARVESTS.back(&deps.storage).unwrap().unwrap();
+        assert_eq!(last.lp_added, Uint128::new(104));
+        assert_eq!(last.swap_outputs, vec![coin(50, "uosmo")]);
+    }
+}
@@ -0,0 +1,283 @@
+use cosmwasm_std::{BlockInfo, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cw20::{KeeperConfig, KeeperResponse};
+use cw_utils::Expiration;
+
+use crate::compound::Harvest;
+use crate::error::ContractError;
+use crate::roles::{assert_owner, assert_owner_or_operator};
+use crate::state::{HARVESTS, KEEPER};
+
+/// Highest bounty the owner can offer keepers, in percent of the rewards net of the fee.
+pub const MAX_KEEPER_BOUNTY_PERCENT: u64 = 10;
+
+pub fn execute_update_keeper(
+    deps: DepsMut,
+    info: MessageInfo,
+    keeper: Option<KeeperConfig>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    match keeper {
+        Some(keeper) => {
+            let max = Decimal::percent(MAX_KEEPER_BOUNTY_PERCENT);
+            if keeper.bounty > max {
+                return Err(ContractError::InvalidKeeperBounty { max });
+            }
+            KEEPER.save(deps.storage, &keeper)?;
+        }
+        None => KEEPER.remove(deps.storage),
+    }
+    Ok(Response::new().add_attribute("action", "update_keeper"))
+}
+
+/// The keeper interval counted from the last recorded harvest.
+fn next_compound(deps: Deps, env: &Env, keeper: &KeeperConfig) -> StdResult<Option<Expiration>> {
+    let last = match HARVESTS.back(deps.storage)? {
+        Some(last) => last,
+        None => return Ok(None),
+    };
+    Ok(Some(keeper.interval.after(&BlockInfo {
+        height: last.height,
+        time: last.time,
+        chain_id: env.block.chain_id.clone(),
+    })))
+}
+
+/// Bounty owed to the caller of `Compound`. The owner and operator compound at any
+/// time and without one, anyone else only under the keeper config.
+pub fn keeper_bounty(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+) -> Result<Option<Decimal>, ContractError> {
+    let unauthorized = match assert_owner_or_operator(deps, &info.sender) {
+        Ok(()) => return Ok(None),
+        Err(err) => err,
+    };
+    let keeper = KEEPER.may_load(deps.storage)?.ok_or(unauthorized)?;
+    if let Some(next) = next_compound(deps, env, &keeper)? {
+        if !next.is_expired(&env.block) {
+            return Err(ContractError::CompoundTooSoon { next });
+        }
+    }
+    Ok(Some(keeper.bounty))
+}
+
+/// Keeps keepers from running harvests of dust, which would cost the vault swap fees
+/// and rounding for next to nothing. Only denoms with a minimum can meet it.
+pub fn assert_keeper_rewards(deps: Deps, harvest: &Harvest) -> Result<(), ContractError> {
+    let keeper = KEEPER.load(deps.storage)?;
+    let enough = keeper.min_rewards.is_empty()
+        || harvest.harvested.iter().any(|reward| {
+            keeper
+                .min_rewards
+                .iter()
+                .any(|min| min.denom == reward.denom && reward.amount >= min.amount)
+        });
+    if !enough {
+        return Err(ContractError::RewardsBelowMinimum {});
+    }
+    Ok(())
+}
+
+pub fn query_keeper(deps: Deps, env: Env) -> StdResult<KeeperResponse> {
+    let keeper = KEEPER.may_load(deps.storage)?;
+    let next_compound = match &keeper {
+        Some(keeper) => next_compound(deps, &env, keeper)?,
+        None => None,
+    };
+    Ok(KeeperResponse {
+        keeper,
+        next_compound,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{
+        coin, coins, BankMsg, Binary, CosmosMsg, Reply, SubMsgResponse, SubMsgResult,
+    };
+    use cw_utils::Duration;
+    use osmosis_std::types::osmosis::gamm::v1beta1::{
+        MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, SwapAmountInRoute,
+    };
+
+    use crate::compound::SWAP_REPLY_ID;
+    use crate::contract::{execute, instantiate, reply};
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::testing::{mock_instantiate_msg, mock_osmosis_dependencies, mock_route_pool};
+
+    fn keeper(bounty: u64, min_rewards: u128) -> ExecuteMsg {
+        ExecuteMsg::UpdateKeeper {
+            keeper: Some(KeeperConfig {
+                interval: Duration::Time(3_600),
+                bounty: Decimal::percent(bounty),
+                min_rewards: coins(min_rewards, "ustrd"),
+            }),
+        }
+    }
+
+    #[test]
+    fn keepers_compound_after_the_interval_for_a_bounty() {
+        let mut deps = mock_osmosis_dependencies(&[coin(1_000, "ustrd")]);
+        deps.querier.set_twap(3, "ustrd", "uatom", "1");
+        deps.querier.set_pool(mock_route_pool(3, "ustrd", "uatom"));
+        let msg = InstantiateMsg {
+            denom_1: None,
+            denom_2: None,
+            white_list_denoms: vec!["ustrd".to_string()],
+            fee: Decimal::percent(10),
+            ..mock_instantiate_msg()
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::SetRoute {
+            denom: "ustrd".to_string(),
+            route: vec![SwapAmountInRoute {
+                pool_id: 3,
+                token_out_denom: "uatom".to_string(),
+            }],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let compound = |deps: DepsMut, env: Env| {
+            execute(deps, env, mock_info("keeper", &[]), ExecuteMsg::Compound {})
+        };
+        let err = compound(deps.as_mut(), mock_env()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // only the owner sets the terms, with a capped bounty
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("keeper", &[]),
+            keeper(5, 2_000),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            keeper(20, 2_000),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidKeeperBounty {
+                max: Decimal::percent(10)
+            }
+        );
+        let info = mock_info("owner", &[]);
+        execute(deps.as_mut(), mock_env(), info.clone(), keeper(5, 2_000)).unwrap();
+
+        let err = compound(deps.as_mut(), mock_env()).unwrap_err();
+        assert_eq!(err, ContractError::RewardsBelowMinimum {});
+
+        // 100 go to the fee collector and 5% of the remaining 900 to the keeper
+        execute(deps.as_mut(), mock_env(), info.clone(), keeper(5, 500)).unwrap();
+        let res = compound(deps.as_mut(), mock_env()).unwrap();
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "keeper".to_string(),
+                amount: coins(45, "ustrd"),
+            })
+        );
+        let swap = match &res.messages[2].msg {
+            CosmosMsg::Stargate { value, .. } => MsgSwapExactAmountIn::try_from(value.clone()),
+            msg => panic!("unexpected message {:?}", msg),
+        }
+        .unwrap();
+        assert_eq!(swap.token_in.unwrap().amount, "855");
+
+        // nothing left to join, the harvest ends with the swap
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, vec![]);
+        let swapped: Binary = MsgSwapExactAmountInResponse {
+            token_out_amount: "850".to_string(),
+        }
+        .into();
+        let swapped = Reply {
+            id: SWAP_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(swapped),
+            }),
+        };
+        reply(deps.as_mut(), mock_env(), swapped).unwrap();
+        let last = HARVESTS.back(&deps.storage).unwrap().unwrap();
+        assert_eq!(last.bounty, coins(45, "ustrd"));
+
+        let next = mock_env().block.time.plus_seconds(3_600);
+        assert_eq!(
+            query_keeper(deps.as_ref(), mock_env())
+                .unwrap()
+                .next_compound,
+            Some(Expiration::AtTime(next))
+        );
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(1_000, "ustrd")]);
+        let err = compound(deps.as_mut(), mock_env()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CompoundTooSoon {
+                next: Expiration::AtTime(next)
+            }
+        );
+        let mut env = mock_env();
+        env.block.time = next;
+        deps.querier.set_day_epoch(2, next.seconds() as i64);
+        compound(deps.as_mut(), env.clone()).unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::UpdateKeeper { keeper: None },
+        )
+        .unwrap();
+        let err = compound(deps.as_mut(), env).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn dust_of_other_rewards_stays_below_the_minimum() {
+        let mut deps = mock_osmosis_dependencies(&[coin(1_000, "ustrd"), coin(5, "uion")]);
+        deps.querier.set_twap(3, "ustrd", "uatom", "1");
+        deps.querier.set_twap(4, "uion", "uatom", "1");
+        deps.querier.set_pool(mock_route_pool(3, "ustrd", "uatom"));
+        deps.querier.set_pool(mock_route_pool(4, "uion", "uatom"));
+        let msg = InstantiateMsg {
+            denom_1: None,
+            denom_2: None,
+            white_list_denoms: vec!["ustrd".to_string(), "uion".to_string()],
+            ..mock_instantiate_msg()
+        };
+        let info = mock_info("owner", &[]);
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        for (denom, pool_id) in [("ustrd", 3), ("uion", 4)] {
+            let msg = ExecuteMsg::SetRoute {
+                denom: denom.to_string(),
+                route: vec![SwapAmountInRoute {
+                    pool_id,
+                    token_out_denom: "uatom".to_string(),
+                }],
+            };
+            execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        }
+        execute(deps.as_mut(), mock_env(), info, keeper(5, 2_000)).unwrap();
+
+        // 5uion have no minimum of their own and don't carry the harvest
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("keeper", &[]),
+            ExecuteMsg::Compound {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::RewardsBelowMinimum {});
+    }
+}
@@ -15,10 +15,28 @@ For more information on this contract, please check out the
 */
 
 pub mod allowances;
+pub mod compound;
 pub mod contract;
+pub mod emergency;
 pub mod enumerable;
+pub mod epochs;
 mod error;
+pub mod history;
+pub mod keeper;
+pub mod locks;
+pub mod metrics;
 pub mod msg;
+#[cfg(test)]
+mod multitest;
+pub mod pool;
+pub mod roles;
+pub mod shares;
 pub mod state;
+pub mod swap;
+#[cfg(test)]
+mod testing;
+pub mod unbonding;
+pub mod vault;
+pub mod zap;
 
 pub use crate::error::ContractError;
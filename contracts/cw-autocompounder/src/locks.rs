@@ -0,0 +1,454 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use cosmwasm_std::{
+    Coin, CosmosMsg, Deps, DepsMut, Env, Order, Reply, Response, StdResult, Storage, SubMsg,
+    Uint128,
+};
+use cw20::{LockInfo, LocksResponse};
+use osmosis_std::shim::Duration;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::lockup::{
+    LockupQuerier, MsgBeginUnlocking, MsgLockTokens, MsgLockTokensResponse,
+};
+use osmosis_std::types::osmosis::superfluid::{
+    MsgLockAndSuperfluidDelegate, MsgLockAndSuperfluidDelegateResponse, MsgSuperfluidUnbondLock,
+    MsgSuperfluidUndelegate,
+};
+
+use crate::error::ContractError;
+use crate::state::{locks, State};
+
+pub const LOCK_REPLY_ID: u64 = 5;
+pub const SUPERFLUID_LOCK_REPLY_ID: u64 = 6;
+
+/// Number of locks superfluid LP is spread over. Superfluid locks only unlock as a
+/// whole, smaller locks keep an unbond from undelegating much more than it needs.
+pub const SUPERFLUID_LOCK_PARTS: u128 = 4;
+
+/// Locks `lp`, superfluid delegating it over `SUPERFLUID_LOCK_PARTS` locks in
+/// superfluid mode. The locks are recorded when the messages reply.
+pub fn lock_msgs(env: &Env, state: &State, lp: Coin) -> Vec<SubMsg> {
+    let proto = |amount: Uint128| ProtoCoin {
+        denom: lp.denom.clone(),
+        amount: amount.to_string(),
+    };
+    let val_addr = match &state.superfluid_validator {
+        Some(val_addr) => val_addr,
+        None => {
+            let msg = MsgLockTokens {
+                owner: env.contract.address.to_string(),
+                duration: Some(Duration {
+                    seconds: state.lock_duration_seconds as i64,
+                    nanos: 0,
+                }),
+                coins: vec![proto(lp.amount)],
+            };
+            return vec![SubMsg::reply_on_success(msg, LOCK_REPLY_ID)];
+        }
+    };
+
+    let part = lp.amount / Uint128::new(SUPERFLUID_LOCK_PARTS);
+    let mut parts = vec![];
+    if !part.is_zero() {
+        parts.resize((SUPERFLUID_LOCK_PARTS - 1) as usize, part);
+    }
+    // the last lock takes the rounding
+    parts.push(lp.amount - part * Uint128::new(parts.len() as u128));
+    parts
+        .into_iter()
+        .map(|amount| {
+            let msg = MsgLockAndSuperfluidDelegate {
+                sender: env.contract.address.to_string(),
+                coins: vec![proto(amount)],
+                val_addr: val_addr.clone(),
+            };
+            SubMsg::reply_on_success(msg, SUPERFLUID_LOCK_REPLY_ID)
+        })
+        .collect()
+}
+
+/// Messages beginning to unlock `coins` of lock `id`, or all of it when `coins` is empty.
+/// Superfluid locks are undelegated first and can only be unlocked as a whole.
+pub fn unlock_msgs(
+    env: &Env,
+    state: &State,
+    id: u64,
+    coins: Vec<Coin>,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let sender = env.contract.address.to_string();
+    if state.superfluid_validator.is_some() {
+        if !coins.is_empty() {
+            return Err(ContractError::SuperfluidPartialUnlock {});
+        }
+        return Ok(vec![
+            MsgSuperfluidUndelegate {
+                sender: sender.clone(),
+                lock_id: id,
+            }
+            .into(),
+            MsgSuperfluidUnbondLock {
+                sender,
+                lock_id: id,
+            }
+            .into(),
+        ]);
+    }
+
+    let coins = coins
+        .into_iter()
+        .map(|coin| ProtoCoin {
+            denom: coin.denom,
+            amount: coin.amount.to_string(),
+        })
+        .collect();
+    Ok(vec![MsgBeginUnlocking {
+        owner: sender,
+        id,
+        coins,
+    }
+    .into()])
+}
+
+/// Records the lock created by a `MsgLockTokens` or `MsgLockAndSuperfluidDelegate` of the vault.
+pub fn reply_lock(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let id = match msg.id {
+        SUPERFLUID_LOCK_REPLY_ID => MsgLockAndSuperfluidDelegateResponse::try_from(msg.result)?.id,
+        _ => MsgLockTokensResponse::try_from(msg.result)?.id,
+    };
+    let lock = LockupQuerier::new(&deps.querier)
+        .locked_by_id(id)?
+        .lock
+        .ok_or_else(|| cosmwasm_std::StdError::not_found("lock"))?;
+
+    let coins = lock
+        .coins
+        .into_iter()
+        .map(|coin| {
+            Ok(Coin::new(
+                Uint128::from_str(&coin.amount)?.u128(),
+                coin.denom,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    // Osmosis adds to the vault lock of the same duration rather than making a new one,
+    // that lock keeps its start and now holds its earlier amount plus the new one
+    let info = match locks().may_load(deps.storage, id)? {
+        Some(existing) => LockInfo { coins, ..existing },
+        None => LockInfo {
+            id,
+            duration_seconds: lock.duration.map(|d| d.seconds as u64).unwrap_or_default(),
+            coins,
+            start_time: env.block.time,
+        },
+    };
+    locks().save(deps.storage, id, &info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "lock")
+        .add_attribute("lock_id", id.to_string()))
+}
+
+/// Takes `coins` out of a tracked lock that begins unlocking them, an empty
+/// `coins` unlocks all of it. Locks the vault didn't record are ignored.
+pub fn record_unlock(storage: &mut dyn Storage, id: u64, coins: &[Coin]) -> StdResult<()> {
+    let mut info = match locks().may_load(storage, id)? {
+        Some(info) => info,
+        None => return Ok(()),
+    };
+    if coins.is_empty() {
+        return locks().remove(storage, id);
+    }
+    for unlocked in coins {
+        if let Some(coin) = info.coins.iter_mut().find(|c| c.denom == unlocked.denom) {
+            coin.amount = coin.amount.saturating_sub(unlocked.amount);
+        }
+    }
+    info.coins.retain(|coin| !coin.amount.is_zero());
+    if info.coins.is_empty() {
+        locks().remove(storage, id)
+    } else {
+        locks().save(storage, id, &info)
+    }
+}
+
+/// Forgets every tracked lock, they all begin unlocking.
+pub fn record_unlock_all(storage: &mut dyn Storage) -> StdResult<()> {
+    let ids = locks()
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for id in ids {
+        locks().remove(storage, id)?;
+    }
+    Ok(())
+}
+
+/// Locks of the vault that did not start unlocking, shortest duration first.
+pub fn query_locks(deps: Deps) -> StdResult<LocksResponse> {
+    let locks = locks()
+        .idx
+        .duration
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, lock)| lock))
+        .collect::<StdResult<_>>()?;
+    Ok(LocksResponse { locks })
+}
+
+pub fn query_lock(deps: Deps, id: u64) -> StdResult<LockInfo> {
+    locks().load(deps.storage, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coin, coins, CosmosMsg, SubMsg, SubMsgResponse, SubMsgResult};
+    use osmosis_std::shim::Duration;
+    use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+    use osmosis_std::types::osmosis::lockup::{MsgBeginUnlocking, MsgLockTokens};
+
+    use crate::contract::{execute, instantiate, reply};
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::testing::{mock_instantiate_msg, mock_lock, mock_osmosis_dependencies};
+
+    const LP: &str = "gamm/pool/1";
+
+    fn do_instantiate(deps: DepsMut) {
+        instantiate_with_validator(deps, None).unwrap();
+    }
+
+    fn instantiate_with_validator(
+        deps: DepsMut,
+        superfluid_validator: Option<&str>,
+    ) -> Result<Response, ContractError> {
+        let msg = InstantiateMsg {
+            superfluid_validator: superfluid_validator.map(String::from),
+            ..mock_instantiate_msg()
+        };
+        instantiate(deps, mock_env(), mock_info("owner", &[]), msg)
+    }
+
+    fn lock_reply(id: u64) -> Reply {
+        Reply {
+            id: LOCK_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(MsgLockTokensResponse { id }.into()),
+            }),
+        }
+    }
+
+    #[test]
+    fn bond_records_lock() {
+        let mut deps = mock_osmosis_dependencies(&[]);
+        let mut short = mock_lock(8, LP, 2_000);
+        short.duration = Some(Duration {
+            seconds: 86_400,
+            nanos: 0,
+        });
+        deps.querier.set_locks(vec![mock_lock(7, LP, 5_000), short]);
+        do_instantiate(deps.as_mut());
+
+        let duration = Duration {
+            seconds: 1_209_600,
+            nanos: 0,
+        };
+        let lp = vec![ProtoCoin {
+            denom: LP.to_string(),
+            amount: "5000".to_string(),
+        }];
+        let msg = ExecuteMsg::AddBond {
+            owner: MOCK_CONTRACT_ADDR.to_string(),
+            duration: duration.clone(),
+            coins: lp.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let lock: CosmosMsg = MsgLockTokens {
+            owner: MOCK_CONTRACT_ADDR.to_string(),
+            duration: Some(duration),
+            coins: lp,
+        }
+        .into();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(lock, LOCK_REPLY_ID)]
+        );
+
+        reply(deps.as_mut(), mock_env(), lock_reply(7)).unwrap();
+        reply(deps.as_mut(), mock_env(), lock_reply(8)).unwrap();
+        let lock_7 = LockInfo {
+            id: 7,
+            duration_seconds: 1_209_600,
+            coins: coins(5_000, LP),
+            start_time: mock_env().block.time,
+        };
+        assert_eq!(query_lock(deps.as_ref(), 7).unwrap(), lock_7);
+        let ids: Vec<_> = query_locks(deps.as_ref())
+            .unwrap()
+            .locks
+            .into_iter()
+            .map(|lock| lock.id)
+            .collect();
+        assert_eq!(ids, vec![8, 7]);
+
+        // ids the lockup module doesn't know fail the reply
+        reply(deps.as_mut(), mock_env(), lock_reply(9)).unwrap_err();
+    }
+
+    #[test]
+    fn lock_with_same_duration_adds_to_it() {
+        let mut deps = mock_osmosis_dependencies(&[]);
+        deps.querier.set_locks(vec![mock_lock(7, LP, 5_000)]);
+        do_instantiate(deps.as_mut());
+        reply(deps.as_mut(), mock_env(), lock_reply(7)).unwrap();
+
+        // the second lock of the same duration lands in lock 7
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(86_400);
+        deps.querier.set_locks(vec![mock_lock(7, LP, 8_000)]);
+        let msg = ExecuteMsg::AddBond {
+            owner: MOCK_CONTRACT_ADDR.to_string(),
+            duration: Duration {
+                seconds: 1_209_600,
+                nanos: 0,
+            },
+            coins: vec![ProtoCoin {
+                denom: LP.to_string(),
+                amount: "3000".to_string(),
+            }],
+        };
+        execute(deps.as_mut(), later.clone(), mock_info("owner", &[]), msg).unwrap();
+        reply(deps.as_mut(), later, lock_reply(7)).unwrap();
+        assert_eq!(
+            query_lock(deps.as_ref(), 7).unwrap(),
+            LockInfo {
+                id: 7,
+                duration_seconds: 1_209_600,
+                coins: coins(8_000, LP),
+                start_time: mock_env().block.time,
+            }
+        );
+        assert_eq!(query_locks(deps.as_ref()).unwrap().locks.len(), 1);
+    }
+
+    #[test]
+    fn unbond_partial_coins() {
+        let mut deps = mock_osmosis_dependencies(&[]);
+        deps.querier.set_locks(vec![mock_lock(7, LP, 5_000)]);
+        do_instantiate(deps.as_mut());
+        reply(deps.as_mut(), mock_env(), lock_reply(7)).unwrap();
+
+        let msg = ExecuteMsg::Unbond {
+            id: 7,
+            coins: coins(2_000, LP),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let unlock: CosmosMsg = MsgBeginUnlocking {
+            owner: MOCK_CONTRACT_ADDR.to_string(),
+            id: 7,
+            coins: vec![ProtoCoin {
+                denom: LP.to_string(),
+                amount: "2000".to_string(),
+            }],
+        }
+        .into();
+        assert_eq!(res.messages, vec![SubMsg::new(unlock)]);
+        assert_eq!(
+            query_lock(deps.as_ref(), 7).unwrap().coins,
+            vec![coin(3_000, LP)]
+        );
+
+        // no coins unlocks the rest of the lock
+        let msg = ExecuteMsg::Unbond {
+            id: 7,
+            coins: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        query_lock(deps.as_ref(), 7).unwrap_err();
+        assert_eq!(query_locks(deps.as_ref()).unwrap().locks, vec![]);
+    }
+
+    #[test]
+    fn superfluid_mode() {
+        let mut deps = mock_osmosis_dependencies(&[]);
+        deps.querier
+            .set_locks(vec![mock_lock(7, LP, 5_000), mock_lock(8, LP, 1_000)]);
+        let err = instantiate_with_validator(deps.as_mut(), Some("")).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Std(cosmwasm_std::StdError::generic_err(
+                "Superfluid validator must not be empty"
+            ))
+        );
+        instantiate_with_validator(deps.as_mut(), Some("osmovaloper1xyz")).unwrap();
+
+        // LP is locked and delegated in one go, spread over several locks
+        let state = crate::state::STATE.load(&deps.storage).unwrap();
+        let lock = |amount: &str| -> SubMsg {
+            let msg = MsgLockAndSuperfluidDelegate {
+                sender: MOCK_CONTRACT_ADDR.to_string(),
+                coins: vec![ProtoCoin {
+                    denom: LP.to_string(),
+                    amount: amount.to_string(),
+                }],
+                val_addr: "osmovaloper1xyz".to_string(),
+            };
+            SubMsg::reply_on_success(msg, SUPERFLUID_LOCK_REPLY_ID)
+        };
+        assert_eq!(
+            lock_msgs(&mock_env(), &state, coin(5_002, LP)),
+            vec![lock("1250"), lock("1250"), lock("1250"), lock("1252")]
+        );
+        // too little to split
+        assert_eq!(lock_msgs(&mock_env(), &state, coin(3, LP)), vec![lock("3")]);
+        for id in [7, 8] {
+            let msg = Reply {
+                id: SUPERFLUID_LOCK_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(MsgLockAndSuperfluidDelegateResponse { id }.into()),
+                }),
+            };
+            reply(deps.as_mut(), mock_env(), msg).unwrap();
+        }
+        assert_eq!(query_locks(deps.as_ref()).unwrap().locks.len(), 2);
+
+        // exits undelegate first and never split a lock
+        let msg = ExecuteMsg::Unbond {
+            id: 7,
+            coins: coins(2_000, LP),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::SuperfluidPartialUnlock {});
+
+        let superfluid_unlock = |lock_id: u64| -> Vec<SubMsg> {
+            vec![
+                SubMsg::new(MsgSuperfluidUndelegate {
+                    sender: MOCK_CONTRACT_ADDR.to_string(),
+                    lock_id,
+                }),
+                SubMsg::new(MsgSuperfluidUnbondLock {
+                    sender: MOCK_CONTRACT_ADDR.to_string(),
+                    lock_id,
+                }),
+            ]
+        };
+        let msg = ExecuteMsg::Unbond {
+            id: 7,
+            coins: vec![],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(res.messages, superfluid_unlock(7));
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UnbondAll {},
+        )
+        .unwrap();
+        assert_eq!(res.messages, superfluid_unlock(8));
+        assert_eq!(query_locks(deps.as_ref()).unwrap().locks, vec![]);
+    }
+}
@@ -0,0 +1,344 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use cosmwasm_std::{coin, Decimal, Deps, Env, StdError, StdResult, Uint128};
+use cw20::{ApyResponse, SharePriceResponse, TvlResponse};
+use osmosis_std::types::osmosis::gamm::v1beta1::GammQuerier;
+use osmosis_std::types::osmosis::txfees::v1beta1::TxfeesQuerier;
+
+use crate::pool::query_pool;
+use crate::state::{PoolInfo, HARVESTS, POOL_INFO, TOKEN_INFO};
+use crate::vault::query_vault_lp;
+
+const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// LP shares backing one vault token, unset while no vault tokens are issued.
+pub(crate) fn current_share_price(deps: Deps, env: &Env) -> StdResult<Option<Decimal>> {
+    let total_supply = TOKEN_INFO.load(deps.storage)?.total_supply;
+    if total_supply.is_zero() {
+        return Ok(None);
+    }
+    let total_lp = query_vault_lp(deps, env)?.total();
+    Ok(Some(Decimal::from_ratio(total_lp, total_supply)))
+}
+
+/// Spot price of `base` in `quote`. Prices against a pool asset come from the vault
+/// pool, anything else goes through txfees and has to be quoted in its base denom.
+fn spot_price(deps: Deps, pool_info: &PoolInfo, base: &str, quote: &str) -> StdResult<Decimal> {
+    if base == quote {
+        return Ok(Decimal::one());
+    }
+    if quote == pool_info.denom_1 || quote == pool_info.denom_2 {
+        let price = GammQuerier::new(&deps.querier)
+            .spot_price(pool_info.id, base.to_string(), quote.to_string())?
+            .spot_price;
+        return Decimal::from_str(&price);
+    }
+
+    let txfees = TxfeesQuerier::new(&deps.querier);
+    if txfees.base_denom()?.base_denom != quote {
+        return Err(StdError::generic_err(format!(
+            "No price for {} in {}",
+            base, quote
+        )));
+    }
+    Decimal::from_str(&txfees.denom_spot_price(base.to_string())?.spot_price)
+}
+
+pub fn query_tvl(deps: Deps, env: Env, quote_denom: Option<String>) -> StdResult<TvlResponse> {
+    let pool_info = POOL_INFO.load(deps.storage)?;
+    let lp = query_vault_lp(deps, &env)?;
+    let pool = query_pool(deps, pool_info.id)?;
+    let total_shares = pool.total_shares()?.amount;
+
+    let total_lp = lp.total();
+    let assets = pool
+        .assets()?
+        .into_iter()
+        .map(|asset| {
+            let amount = if total_shares.is_zero() {
+                Uint128::zero()
+            } else {
+                asset.balance.multiply_ratio(total_lp, total_shares)
+            };
+            coin(amount.u128(), asset.denom)
+        })
+        .collect::<Vec<_>>();
+
+    let quote_denom = match quote_denom {
+        Some(denom) => denom,
+        None => TxfeesQuerier::new(&deps.querier).base_denom()?.base_denom,
+    };
+    let mut value = Uint128::zero();
+    for asset in &assets {
+        value += asset.amount * spot_price(deps, &pool_info, &asset.denom, &quote_denom)?;
+    }
+
+    Ok(TvlResponse {
+        liquid_lp: lp.liquid,
+        locked_lp: lp.locked,
+        pending_lp: lp.pending,
+        assets,
+        quote_denom,
+        value,
+    })
+}
+
+pub fn query_share_price(deps: Deps, env: Env) -> StdResult<SharePriceResponse> {
+    let total_supply = TOKEN_INFO.load(deps.storage)?.total_supply;
+    let total_lp = query_vault_lp(deps, &env)?.total();
+    let share_price = if total_supply.is_zero() {
+        Decimal::zero()
+    } else {
+        Decimal::from_ratio(total_lp, total_supply)
+    };
+    Ok(SharePriceResponse {
+        share_price,
+        total_lp,
+        total_supply,
+    })
+}
+
+/// `base` to the power of `numerator / denominator`, `None` on overflow. The whole part
+/// of the exponent is a plain power, its fraction is built bit by bit from successive
+/// square roots of `base`.
+fn checked_pow_ratio(base: Decimal, numerator: u64, denominator: u64) -> Option<Decimal> {
+    let whole = u32::try_from(numerator / denominator).ok()?;
+    let mut result = base.checked_pow(whole).ok()?;
+    let mut fraction = numerator % denominator;
+    let mut root = base;
+    // 32 bits are past the precision of the square roots
+    for _ in 0..32 {
+        if fraction == 0 {
+            break;
+        }
+        root = root.sqrt();
+        fraction *= 2;
+        if fraction >= denominator {
+            result = result.checked_mul(root).ok()?;
+            fraction -= denominator;
+        }
+    }
+    Some(result)
+}
+
+pub fn query_apy(deps: Deps, env: Env, window_seconds: Option<u64>) -> StdResult<ApyResponse> {
+    let after = window_seconds.map(|window| env.block.time.minus_seconds(window));
+    // the growth is measured from the oldest harvest in the window that left a share price
+    let mut start = None;
+    for harvest in HARVESTS.iter(deps.storage)? {
+        let harvest = harvest?;
+        if let Some(share_price) = harvest.share_price {
+            if after.map_or(true, |after| harvest.time >= after) {
+                start = Some((harvest.time, share_price));
+                break;
+            }
+        }
+    }
+
+    let (start, current) = match (start, current_share_price(deps, &env)?) {
+        (Some(start), Some(current)) => (start, current),
+        (start, _) => {
+            return Ok(ApyResponse {
+                apy: Decimal::zero(),
+                since: start.map(|(time, _)| time),
+            })
+        }
+    };
+    let (since, start_price) = start;
+    let elapsed = env.block.time.seconds().saturating_sub(since.seconds());
+    let mut apy = Decimal::zero();
+    if elapsed > 0 && !start_price.is_zero() {
+        let growth = Decimal::from_ratio(current.atomics(), start_price.atomics());
+        if growth > Decimal::one() {
+            // the growth compounds for as many `elapsed` periods as fit in a year
+            let yearly = checked_pow_ratio(growth, SECONDS_PER_YEAR, elapsed);
+            apy = yearly.unwrap_or(Decimal::MAX) - Decimal::one();
+        }
+    }
+    Ok(ApyResponse {
+        apy,
+        since: Some(since),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_env, mock_info};
+    use cosmwasm_std::{from_binary, DepsMut, Timestamp};
+
+    use crate::contract::{execute, instantiate, query};
+    use crate::history::record_harvest;
+    use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+    use crate::state::CompoundState;
+    use crate::testing::{
+        mock_balancer_pool, mock_instantiate_msg, mock_lock, mock_osmosis_dependencies,
+    };
+
+    const LP: &str = "gamm/pool/1";
+
+    fn instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            denom_1: None,
+            denom_2: None,
+            ..mock_instantiate_msg()
+        }
+    }
+
+    fn env_at(seconds: u64) -> Env {
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(seconds);
+        env
+    }
+
+    /// Records an empty harvest at the current share price, as a compound does.
+    fn record_harvest_at(deps: DepsMut, env: &Env) {
+        let harvest = CompoundState {
+            pending_swaps: vec![],
+            pending_joins: 0,
+            shares_out: Uint128::zero(),
+            harvested: vec![],
+            fees: vec![],
+            bounty: vec![],
+            swap_outputs: vec![],
+        };
+        let share_price = current_share_price(deps.as_ref(), env).unwrap();
+        record_harvest(deps.storage, env, harvest, share_price).unwrap();
+    }
+
+    #[test]
+    fn tvl_and_share_price() {
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, LP)]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("ujuno", 1_000_000)],
+            100_000,
+        ));
+        deps.querier.set_spot_price(1, "uatom", "ujuno", "20");
+        deps.querier.set_fee_token_price("uatom", "10");
+        deps.querier.set_fee_token_price("ujuno", "0.5");
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            instantiate_msg(),
+        )
+        .unwrap();
+        let info = mock_info("alice", &[coin(10_000, LP)]);
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+
+        // compounded LP sits in a lock
+        deps.querier.set_locks(vec![mock_lock(1, LP, 5_000)]);
+        let res: SharePriceResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::SharePrice {}).unwrap())
+                .unwrap();
+        assert_eq!(
+            res,
+            SharePriceResponse {
+                share_price: Decimal::from_str("1.5").unwrap(),
+                total_lp: Uint128::new(15_000),
+                total_supply: Uint128::new(10_000),
+            }
+        );
+
+        // priced in the txfees base denom by default
+        let res = query_tvl(deps.as_ref(), mock_env(), None).unwrap();
+        assert_eq!(
+            res,
+            TvlResponse {
+                liquid_lp: Uint128::new(10_000),
+                locked_lp: Uint128::new(5_000),
+                pending_lp: Uint128::zero(),
+                assets: vec![coin(150_000, "uatom"), coin(150_000, "ujuno")],
+                quote_denom: "uosmo".to_string(),
+                value: Uint128::new(1_575_000),
+            }
+        );
+
+        // or in a pool asset, at the pool spot price
+        let res = query_tvl(deps.as_ref(), mock_env(), Some("ujuno".to_string())).unwrap();
+        assert_eq!(res.value, Uint128::new(3_150_000));
+
+        let err = query_tvl(deps.as_ref(), mock_env(), Some("uion".to_string())).unwrap_err();
+        assert_eq!(err, StdError::generic_err("No price for uatom in uion"));
+    }
+
+    #[test]
+    fn apy_compounds_share_price_growth() {
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, LP)]);
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            instantiate_msg(),
+        )
+        .unwrap();
+        let info = mock_info("alice", &[coin(10_000, LP)]);
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+        record_harvest_at(deps.as_mut(), &env_at(1_000));
+        deps.querier.set_locks(vec![mock_lock(1, LP, 2_100)]);
+
+        // 21% over two years is 10% a year, not the 10.5% of a linear rate
+        let res = query_apy(deps.as_ref(), env_at(1_000 + 2 * SECONDS_PER_YEAR), None).unwrap();
+        assert_eq!(res.apy, Decimal::percent(10));
+
+        // growth too fast to compound over a year saturates
+        let res = query_apy(deps.as_ref(), env_at(1_001), None).unwrap();
+        assert_eq!(res.apy, Decimal::MAX - Decimal::one());
+    }
+
+    #[test]
+    fn apy_from_share_price_history() {
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, LP)]);
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            instantiate_msg(),
+        )
+        .unwrap();
+
+        // nothing to measure from before the first deposit
+        record_harvest_at(deps.as_mut(), &env_at(1_000));
+        let harvest = HARVESTS.back(&deps.storage).unwrap().unwrap();
+        assert_eq!(harvest.share_price, None);
+        let res = query_apy(deps.as_ref(), env_at(1_000), None).unwrap();
+        assert_eq!(
+            res,
+            ApyResponse {
+                apy: Decimal::zero(),
+                since: None,
+            }
+        );
+
+        let info = mock_info("alice", &[coin(10_000, LP)]);
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+        record_harvest_at(deps.as_mut(), &env_at(1_000));
+        deps.querier.set_locks(vec![mock_lock(1, LP, 1_000)]);
+        record_harvest_at(deps.as_mut(), &env_at(1_000 + SECONDS_PER_YEAR / 20));
+        deps.querier.set_locks(vec![mock_lock(1, LP, 5_000)]);
+
+        // share price went from 1 to 1.5 over a tenth of a year, 1.5^10 over a year
+        let now = env_at(1_000 + SECONDS_PER_YEAR / 10);
+        let res = query_apy(deps.as_ref(), now.clone(), None).unwrap();
+        assert_eq!(
+            res,
+            ApyResponse {
+                apy: Decimal::from_str("56.6650390625").unwrap(),
+                since: Some(Timestamp::from_seconds(1_000)),
+            }
+        );
+        // and from 1.1 to 1.5 over the last twentieth
+        let res = query_apy(deps.as_ref(), now, Some(SECONDS_PER_YEAR / 20)).unwrap();
+        assert_eq!(
+            res.since,
+            Some(Timestamp::from_seconds(1_000 + SECONDS_PER_YEAR / 20))
+        );
+        assert_eq!(
+            res.apy,
+            Decimal::from_str("493.278224470739986477").unwrap()
+        );
+    }
+}
@@ -1,11 +1,14 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{StdError, StdResult, Uint128};
+use cosmwasm_std::{Coin, Decimal, StdError, StdResult};
 use cw20::{Cw20Coin, Logo, MinterResponse};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 pub use cw20::Cw20ExecuteMsg as ExecuteMsg;
 
+/// Highest performance fee a vault can be instantiated with, in percent
+pub const MAX_PERFORMANCE_FEE_PERCENT: u64 = 50;
+
 #[cw_serde]
 pub struct InstantiateMarketingInfo {
     pub project: Option<String>,
@@ -21,21 +24,32 @@ pub struct InstantiateMsg {
     pub symbol: String,
     pub decimals: u8,
     pub id: u64, 
-    pub denom_1: String,
-    pub denom_2: String,
+    /// Pool assets, checked against pool `id` and taken from the pool when left out
+    #[serde(default)]
+    pub denom_1: Option<String>,
+    #[serde(default)]
+    pub denom_2: Option<String>,
     pub white_list_denoms: Vec<String>,
-    pub fee: u64,
+    /// Performance fee taken from harvested rewards, at most `MAX_PERFORMANCE_FEE_PERCENT`
+    pub fee: Decimal,
     pub fee_collector_address: String,
+    /// Validator the LP shares are superfluid delegated to. Without one the vault
+    /// keeps its LP in plain locks.
+    #[serde(default)]
+    pub superfluid_validator: Option<String>,
+    /// If set, vault shares are the native tokenfactory denom
+    /// `factory/{vault}/{share_subdenom}` instead of cw20 balances. The cw20 transfer,
+    /// allowance and burn messages are rejected then.
+    #[serde(default)]
+    pub share_subdenom: Option<String>,
+    /// Must be empty, vault shares are only minted for deposits
     pub initial_balances: Vec<Cw20Coin>,
+    /// Must be unset, vault shares are only minted for deposits
     pub mint: Option<MinterResponse>,
     pub marketing: Option<InstantiateMarketingInfo>,
 }
 
 impl InstantiateMsg {
-    pub fn get_cap(&self) -> Option<Uint128> {
-        self.mint.as_ref().and_then(|v| v.cap)
-    }
-
     pub fn validate(&self) -> StdResult<()> {
         // Check name, symbol, decimals
         if !self.has_valid_name() {
@@ -51,6 +65,28 @@ impl InstantiateMsg {
         if self.decimals > 18 {
             return Err(StdError::generic_err("Decimals must not exceed 18"));
         }
+        if self.fee > Decimal::percent(MAX_PERFORMANCE_FEE_PERCENT) {
+            return Err(StdError::generic_err("Performance fee must not exceed 50%"));
+        }
+        if self.superfluid_validator.as_deref() == Some("") {
+            return Err(StdError::generic_err("Superfluid validator must not be empty"));
+        }
+        if let Some(subdenom) = &self.share_subdenom {
+            if subdenom.is_empty() {
+                return Err(StdError::generic_err("Share subdenom must not be empty"));
+            }
+        }
+        // shares minted without LP behind them would dilute the depositors
+        if !self.initial_balances.is_empty() {
+            return Err(StdError::generic_err(
+                "Initial balances are not supported, vault shares are only minted for deposits",
+            ));
+        }
+        if self.mint.is_some() {
+            return Err(StdError::generic_err(
+                "Minting is not supported, vault shares are only minted for deposits",
+            ));
+        }
         Ok(())
     }
 
@@ -88,6 +124,54 @@ pub enum QueryMsg {
 
     #[returns(cw20::PoolInfoResponse)]
     PoolInfo {},
+    /// Returns the owner, pending owner, operator, depositor and guardian of the vault.
+    #[returns(cw20::RolesResponse)]
+    Roles {},
+    /// Returns the swap route configured for every reward denom.
+    #[returns(cw20::RoutesResponse)]
+    Routes {},
+    /// Returns the unbond claims of the given address.
+    #[returns(cw_controllers::ClaimsResponse)]
+    Claims { address: String },
+    /// Returns the LP shares owed to all unbond claims together.
+    #[returns(cw20::PendingUnbondsResponse)]
+    PendingUnbonds {},
+    /// Returns the locks created by the vault that did not start unlocking, shortest first.
+    #[returns(cw20::LocksResponse)]
+    Locks {},
+    /// Returns a single lock created by the vault.
+    #[returns(cw20::LockInfo)]
+    Lock { id: u64 },
+    /// Returns the LP shares a join of the vault pool at the pool ratio mints with
+    /// at most `coins`, and the part of `coins` it takes.
+    #[returns(cw20::SimulateJoinResponse)]
+    SimulateJoin { coins: Vec<Coin> },
+    /// Returns the LP owned by the vault, the pool assets it redeems for and their value
+    /// in `quote_denom`, the txfees base denom by default.
+    #[returns(cw20::TvlResponse)]
+    Tvl { quote_denom: Option<String> },
+    /// Returns the LP shares backing one vault token.
+    #[returns(cw20::SharePriceResponse)]
+    SharePrice {},
+    /// Returns the trailing APY from the share price of the oldest harvest at most
+    /// `window_seconds` old, or of the oldest harvest kept.
+    #[returns(cw20::ApyResponse)]
+    Apy { window_seconds: Option<u64> },
+    /// Returns the keeper config and when keepers can compound next.
+    #[returns(cw20::KeeperResponse)]
+    Keeper {},
+    /// Returns the epoch the vault can compound in next and when it starts.
+    #[returns(cw20::NextCompoundEpochResponse)]
+    NextCompoundEpoch {},
+    /// Returns whether the vault is paused and how far an emergency exit got.
+    #[returns(cw20::VaultStatusResponse)]
+    VaultStatus {},
+    /// Returns the recorded harvests, oldest first. Supports pagination.
+    #[returns(cw20::HarvestHistoryResponse)]
+    HarvestHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
     /// Only with "mintable" extension.
     /// Returns who can mint and the hard cap on maximum tokens after minting.
     #[returns(cw20::MinterResponse)]
@@ -0,0 +1,450 @@
+//! Scenarios running the vault against the simulated Osmosis modules of `cw-multi-test`:
+//! gamm pools, lockup and the incentives paying rewards to locks every epoch.
+
+use std::convert::TryFrom;
+
+use cosmwasm_std::testing::{MockApi, MockStorage};
+use cosmwasm_std::{coin, Addr, Decimal, Empty, Uint128};
+use cw20::{
+    AllAllowancesResponse, AllSpenderAllowancesResponse, BalanceResponse, HarvestHistoryResponse,
+    PoolInfoResponse, SpenderAllowanceInfo,
+};
+use cw_multi_test::{
+    App, AppBuilder, BankKeeper, ContractWrapper, DistributionKeeper, Executor, FailingModule,
+    OsmosisKeeper, StakeKeeper, WasmKeeper,
+};
+use osmosis_std::shim::Duration;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::gamm::poolmodels::balancer::v1beta1::{
+    MsgCreateBalancerPool, MsgCreateBalancerPoolResponse,
+};
+use osmosis_std::types::osmosis::gamm::v1beta1::{PoolAsset, PoolParams, SwapAmountInRoute};
+use osmosis_std::types::osmosis::incentives::MsgCreateGauge;
+use osmosis_std::types::osmosis::lockup::QueryCondition;
+
+use cw_utils::Expiration;
+
+use crate::contract::{execute, instantiate, migrate, query, reply};
+use crate::error::ContractError;
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::testing::mock_instantiate_msg;
+
+type OsmosisApp = App<
+    BankKeeper,
+    MockApi,
+    MockStorage,
+    FailingModule<Empty, Empty, Empty>,
+    WasmKeeper<Empty, Empty>,
+    StakeKeeper,
+    DistributionKeeper,
+    OsmosisKeeper,
+>;
+
+const OWNER: &str = "owner";
+const COLLECTOR: &str = "collector";
+const DAY: u64 = 24 * 60 * 60;
+
+fn proto_coin(amount: u128, denom: &str) -> ProtoCoin {
+    ProtoCoin {
+        denom: denom.to_string(),
+        amount: amount.to_string(),
+    }
+}
+
+fn day() -> Duration {
+    Duration {
+        seconds: DAY as i64,
+        nanos: 0,
+    }
+}
+
+/// Ends the epochs and the unlockings the block time went past
+fn end_block(app: &mut OsmosisApp) {
+    let block = app.block_info();
+    app.init_modules(|router, api, storage| {
+        router.stargate.end_block(api, storage, &*router, &block)
+    })
+    .unwrap();
+}
+
+fn create_pool(app: &mut OsmosisApp, denom_1: &str, denom_2: &str) -> u64 {
+    let msg = MsgCreateBalancerPool {
+        sender: OWNER.to_string(),
+        pool_params: Some(PoolParams {
+            swap_fee: "0".to_string(),
+            exit_fee: "0".to_string(),
+            smooth_weight_change_params: None,
+        }),
+        pool_assets: [denom_1, denom_2]
+            .iter()
+            .map(|denom| PoolAsset {
+                token: Some(proto_coin(1_000_000, denom)),
+                weight: "1".to_string(),
+            })
+            .collect(),
+        future_pool_governor: "".to_string(),
+    };
+    let res = app.execute(Addr::unchecked(OWNER), msg.into()).unwrap();
+    MsgCreateBalancerPoolResponse::try_from(res.data.unwrap())
+        .unwrap()
+        .pool_id
+}
+
+fn balance(app: &OsmosisApp, address: &Addr, denom: &str) -> u128 {
+    app.wrap()
+        .query_balance(address, denom)
+        .unwrap()
+        .amount
+        .u128()
+}
+
+/// Pays `amount` uion to the vault lock over the next epoch
+fn fund_gauge(app: &mut OsmosisApp, amount: u128) {
+    let msg = MsgCreateGauge {
+        is_perpetual: false,
+        owner: OWNER.to_string(),
+        distribute_to: Some(QueryCondition {
+            lock_query_type: 0,
+            denom: "gamm/pool/1".to_string(),
+            duration: Some(day()),
+            timestamp: None,
+        }),
+        coins: vec![proto_coin(amount, "uion")],
+        start_time: None,
+        num_epochs_paid_over: 1,
+    };
+    app.execute(Addr::unchecked(OWNER), msg.into()).unwrap();
+}
+
+fn shares_of(app: &OsmosisApp, vault: &Addr, address: &str) -> Uint128 {
+    let res: BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            vault,
+            &QueryMsg::Balance {
+                address: address.to_string(),
+            },
+        )
+        .unwrap();
+    res.balance
+}
+
+/// An uatom/uosmo vault holding locked LP shares, with routes converting its uion rewards
+fn setup() -> (OsmosisApp, Addr) {
+    let mut app: OsmosisApp = AppBuilder::new()
+        .with_stargate(OsmosisKeeper::new())
+        .build(|router, _, storage| {
+            let funds = ["uatom", "uion", "uosmo"]
+                .iter()
+                .map(|denom| coin(1_000_000_000, *denom))
+                .collect();
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked(OWNER), funds)
+                .unwrap();
+        });
+    end_block(&mut app);
+    let owner = Addr::unchecked(OWNER);
+    let pool_id = create_pool(&mut app, "uatom", "uosmo");
+    let reward_pool_id = create_pool(&mut app, "uion", "uosmo");
+
+    let code_id = app.store_code(Box::new(
+        ContractWrapper::new(execute, instantiate, query)
+            .with_reply(reply)
+            .with_migrate(migrate),
+    ));
+    let msg = InstantiateMsg {
+        id: pool_id,
+        denom_1: None,
+        denom_2: None,
+        white_list_denoms: vec!["uion".to_string()],
+        fee: Decimal::percent(10),
+        fee_collector_address: COLLECTOR.to_string(),
+        ..mock_instantiate_msg()
+    };
+    let vault = app
+        .instantiate_contract(
+            code_id,
+            owner.clone(),
+            &msg,
+            &[],
+            "vault",
+            Some(OWNER.to_string()),
+        )
+        .unwrap();
+
+    let lp_denom = format!("gamm/pool/{}", pool_id);
+    let lp_amount = 1_000_000_000_000_000_000;
+    app.execute_contract(
+        owner.clone(),
+        vault.clone(),
+        &ExecuteMsg::Deposit {},
+        &[coin(lp_amount, &lp_denom)],
+    )
+    .unwrap();
+    let msg = ExecuteMsg::AddBond {
+        owner: vault.to_string(),
+        duration: day(),
+        coins: vec![proto_coin(lp_amount, &lp_denom)],
+    };
+    app.execute_contract(owner.clone(), vault.clone(), &msg, &[])
+        .unwrap();
+    let msg = ExecuteMsg::SetRoute {
+        denom: "uion".to_string(),
+        route: vec![SwapAmountInRoute {
+            pool_id: reward_pool_id,
+            token_out_denom: "uosmo".to_string(),
+        }],
+    };
+    app.execute_contract(owner, vault.clone(), &msg, &[])
+        .unwrap();
+
+    (app, vault)
+}
+
+#[test]
+fn convert_rewards_paid_to_the_vault_lock() {
+    let (mut app, vault) = setup();
+    let owner = Addr::unchecked(OWNER);
+    // the deposit is all locked
+    assert_eq!(balance(&app, &vault, "gamm/pool/1"), 0);
+
+    // pays 10_000 uion to the locks of the vault LP over the next two epochs
+    let msg = MsgCreateGauge {
+        is_perpetual: false,
+        owner: OWNER.to_string(),
+        distribute_to: Some(QueryCondition {
+            lock_query_type: 0,
+            denom: "gamm/pool/1".to_string(),
+            duration: Some(day()),
+            timestamp: None,
+        }),
+        coins: vec![proto_coin(20_000, "uion")],
+        start_time: None,
+        num_epochs_paid_over: 2,
+    };
+    app.execute(owner.clone(), msg.into()).unwrap();
+
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY));
+    end_block(&mut app);
+    assert_eq!(balance(&app, &vault, "uion"), 10_000);
+
+    let msg = ExecuteMsg::ConvertRewards {
+        max_slippage_bps: None,
+    };
+    app.execute_contract(owner.clone(), vault.clone(), &msg, &[])
+        .unwrap();
+    // a tenth of the rewards is the performance fee, the rest is swapped along the route
+    assert_eq!(balance(&app, &Addr::unchecked(COLLECTOR), "uion"), 1_000);
+    assert_eq!(balance(&app, &vault, "uion"), 0);
+    // 9_000 uion into the 1_000_000 uion/uosmo pool
+    assert_eq!(balance(&app, &vault, "uosmo"), 8_919);
+    let res: HarvestHistoryResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &vault,
+            &QueryMsg::HarvestHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.harvests.len(), 1);
+    assert_eq!(res.harvests[0].rewards, vec![coin(10_000, "uion")]);
+    assert_eq!(res.harvests[0].fees, vec![coin(1_000, "uion")]);
+    assert_eq!(res.harvests[0].swap_outputs, vec![coin(8_919, "uosmo")]);
+
+    // swapping beyond the slippage tolerance fails, and leaves the rewards in the vault
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY));
+    end_block(&mut app);
+    assert_eq!(balance(&app, &vault, "uion"), 10_000);
+    let msg = ExecuteMsg::ConvertRewards {
+        max_slippage_bps: Some(0),
+    };
+    app.execute_contract(owner, vault.clone(), &msg, &[])
+        .unwrap_err();
+    assert_eq!(balance(&app, &vault, "uion"), 10_000);
+
+    // depositors got their vault tokens for the LP locked
+    let res: BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &vault,
+            &QueryMsg::Balance {
+                address: OWNER.to_string(),
+            },
+        )
+        .unwrap();
+    assert!(res.balance > Uint128::zero());
+}
+
+#[test]
+fn pause_holds_deposits_compounds_and_transfers() {
+    let (mut app, vault) = setup();
+    let owner = Addr::unchecked(OWNER);
+    fund_gauge(&mut app, 10_000);
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY));
+    end_block(&mut app);
+
+    app.execute_contract(owner.clone(), vault.clone(), &ExecuteMsg::Pause {}, &[])
+        .unwrap();
+    let deposit = [coin(1_000, "gamm/pool/1")];
+    let transfer = ExecuteMsg::Transfer {
+        recipient: "alice".to_string(),
+        amount: Uint128::new(1_000),
+    };
+    let held = [
+        (ExecuteMsg::Deposit {}, &deposit[..]),
+        (ExecuteMsg::Compound {}, &[][..]),
+        (transfer.clone(), &[][..]),
+    ];
+    for (msg, funds) in &held {
+        let err = app
+            .execute_contract(owner.clone(), vault.clone(), msg, funds)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::Paused {}
+        );
+    }
+    assert_eq!(balance(&app, &vault, "uion"), 10_000);
+
+    app.execute_contract(owner.clone(), vault.clone(), &ExecuteMsg::Unpause {}, &[])
+        .unwrap();
+    for (msg, funds) in &held {
+        app.execute_contract(owner.clone(), vault.clone(), msg, funds)
+            .unwrap();
+    }
+    // the compound paid the fee and swapped the rest of the rewards
+    assert_eq!(balance(&app, &Addr::unchecked(COLLECTOR), "uion"), 1_000);
+    assert_eq!(balance(&app, &vault, "uion"), 0);
+    assert_eq!(shares_of(&app, &vault, "alice"), Uint128::new(1_000));
+}
+
+#[test]
+fn emergency_exit_pays_holders_their_share_of_the_pool_assets() {
+    let (mut app, vault) = setup();
+    let owner = Addr::unchecked(OWNER);
+    let alice = Addr::unchecked("alice");
+    let shares = shares_of(&app, &vault, OWNER);
+    let msg = ExecuteMsg::Transfer {
+        recipient: alice.to_string(),
+        amount: shares / Uint128::new(4),
+    };
+    app.execute_contract(owner.clone(), vault.clone(), &msg, &[])
+        .unwrap();
+
+    let exit = ExecuteMsg::EmergencyExit {};
+    app.execute_contract(owner.clone(), vault.clone(), &exit, &[])
+        .unwrap();
+    // the LP stays in the pool until the locks are done unlocking
+    let err = app
+        .execute_contract(alice.clone(), vault.clone(), &exit, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::EmergencyExitUnlocking {}
+    );
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            vault.clone(),
+            &ExecuteMsg::Deposit {},
+            &[coin(1_000, "gamm/pool/1")],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::WithdrawOnly {}
+    );
+
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY));
+    end_block(&mut app);
+    assert_eq!(
+        balance(&app, &vault, "gamm/pool/1"),
+        1_000_000_000_000_000_000
+    );
+    app.execute_contract(alice.clone(), vault.clone(), &exit, &[])
+        .unwrap();
+    // the vault held a hundredth of the 1_000_000 uatom/uosmo pool
+    assert_eq!(balance(&app, &vault, "gamm/pool/1"), 0);
+    assert_eq!(balance(&app, &vault, "uatom"), 10_000);
+    assert_eq!(balance(&app, &vault, "uosmo"), 10_000);
+
+    // holders get their share of the supply, which counts the 1_000 shares nobody holds
+    let quarter = shares / Uint128::new(4);
+    for (holder, shares, paid) in [(&alice, quarter, 2_499), (&owner, shares - quarter, 7_500)] {
+        let before = balance(&app, holder, "uatom");
+        let msg = ExecuteMsg::Withdraw { shares };
+        app.execute_contract(holder.clone(), vault.clone(), &msg, &[])
+            .unwrap();
+        assert_eq!(balance(&app, holder, "uatom") - before, paid);
+        assert_eq!(shares_of(&app, &vault, holder.as_str()), Uint128::zero());
+    }
+    assert_eq!(balance(&app, &alice, "uosmo"), 2_499);
+    assert_eq!(balance(&app, &vault, "uatom"), 1);
+}
+
+#[test]
+fn migrate_keeps_shares_allowances_and_pool() {
+    let (mut app, vault) = setup();
+    let owner = Addr::unchecked(OWNER);
+    let shares = shares_of(&app, &vault, OWNER);
+
+    // no allowance to start
+    let query_allowances = QueryMsg::AllAllowances {
+        owner: OWNER.to_string(),
+        start_after: None,
+        limit: None,
+    };
+    let allowance: AllAllowancesResponse = app
+        .wrap()
+        .query_wasm_smart(&vault, &query_allowances)
+        .unwrap();
+    assert_eq!(allowance, AllAllowancesResponse::default());
+
+    // Set allowance
+    let allow1 = Uint128::new(7777);
+    let expires = Expiration::AtHeight(123_456);
+    let msg = ExecuteMsg::IncreaseAllowance {
+        spender: "spender".to_string(),
+        amount: allow1,
+        expires: Some(expires),
+    };
+    app.execute_contract(owner.clone(), vault.clone(), &msg, &[])
+        .unwrap();
+
+    // Now migrate
+    let code_id = app.contract_data(&vault).unwrap().code_id;
+    app.migrate_contract(owner, vault.clone(), &MigrateMsg {}, code_id as u64)
+        .unwrap();
+
+    // Smoke check that the contract still works.
+    assert_eq!(shares_of(&app, &vault, OWNER), shares);
+    let pool: PoolInfoResponse = app
+        .wrap()
+        .query_wasm_smart(&vault, &QueryMsg::PoolInfo {})
+        .unwrap();
+    assert_eq!(pool.lp_denom, "gamm/pool/1");
+
+    // Confirm that the allowance per spender is there
+    let allowance: AllSpenderAllowancesResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &vault,
+            &QueryMsg::AllSpenderAllowances {
+                spender: "spender".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        allowance.allowances,
+        &[SpenderAllowanceInfo {
+            owner: OWNER.to_string(),
+            allowance: allow1,
+            expires
+        }]
+    );
+}
@@ -0,0 +1,516 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{Coin, Decimal, Deps, StdError, StdResult, Uint128};
+use cw20::SimulateJoinResponse;
+use osmosis_std::shim::Any;
+use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::Pool as StableSwapPool;
+use osmosis_std::types::osmosis::gamm::v1beta1::{GammQuerier, Pool as BalancerPool};
+use prost::Message;
+
+use crate::error::ContractError;
+use crate::state::{PoolInfo, POOL_INFO};
+
+/// Pool the vault provides liquidity to, in any of the supported pool models.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VaultPool {
+    Balancer(BalancerPool),
+    StableSwap(StableSwapPool),
+}
+
+/// A pool asset as seen by the join and share pricing logic.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolAsset {
+    pub denom: String,
+    pub balance: Uint128,
+    /// Balancer weight, or stableswap scaling factor
+    pub weight: Uint128,
+}
+
+impl VaultPool {
+    /// Decodes the pool returned by the gamm module, picking the model from its type URL.
+    pub fn from_any(any: Any) -> StdResult<Self> {
+        let parse_err = |target: &str, e: prost::DecodeError| StdError::ParseErr {
+            target_type: target.to_string(),
+            msg: e.to_string(),
+        };
+        match any.type_url.as_str() {
+            BalancerPool::TYPE_URL => BalancerPool::decode(any.value.as_slice())
+                .map(VaultPool::Balancer)
+                .map_err(|e| parse_err(BalancerPool::TYPE_URL, e)),
+            StableSwapPool::TYPE_URL => StableSwapPool::decode(any.value.as_slice())
+                .map(VaultPool::StableSwap)
+                .map_err(|e| parse_err(StableSwapPool::TYPE_URL, e)),
+            type_url => Err(StdError::generic_err(format!(
+                "Unsupported pool type {}",
+                type_url
+            ))),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        match self {
+            VaultPool::Balancer(pool) => pool.id,
+            VaultPool::StableSwap(pool) => pool.id,
+        }
+    }
+
+    /// LP shares issued by the pool.
+    pub fn total_shares(&self) -> StdResult<Coin> {
+        let shares = match self {
+            VaultPool::Balancer(pool) => pool.total_shares.as_ref(),
+            VaultPool::StableSwap(pool) => pool.total_shares.as_ref(),
+        }
+        .ok_or_else(|| StdError::not_found("pool total shares"))?;
+        Ok(Coin {
+            denom: shares.denom.clone(),
+            amount: Uint128::from_str(&shares.amount)?,
+        })
+    }
+
+    pub fn assets(&self) -> StdResult<Vec<PoolAsset>> {
+        match self {
+            VaultPool::Balancer(pool) => pool
+                .pool_assets
+                .iter()
+                .map(|asset| {
+                    let token = asset
+                        .token
+                        .as_ref()
+                        .ok_or_else(|| StdError::generic_err("Pool asset without token"))?;
+                    Ok(PoolAsset {
+                        denom: token.denom.clone(),
+                        balance: parse_amount(Some(&token.amount))?,
+                        weight: parse_amount(Some(&asset.weight))?,
+                    })
+                })
+                .collect(),
+            VaultPool::StableSwap(pool) => {
+                if pool.scaling_factor.len() != pool.pool_liquidity.len() {
+                    return Err(StdError::generic_err(
+                        "Stableswap pool without a scaling factor per asset",
+                    ));
+                }
+                pool.pool_liquidity
+                    .iter()
+                    .zip(&pool.scaling_factor)
+                    .map(|(coin, factor)| {
+                        Ok(PoolAsset {
+                            denom: coin.denom.clone(),
+                            balance: parse_amount(Some(&coin.amount))?,
+                            weight: Uint128::from(*factor),
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    pub fn swap_fee(&self) -> StdResult<Decimal> {
+        let params = match self {
+            VaultPool::Balancer(pool) => pool.pool_params.as_ref().map(|p| &p.swap_fee),
+            VaultPool::StableSwap(pool) => pool.pool_params.as_ref().map(|p| &p.swap_fee),
+        };
+        match params {
+            Some(swap_fee) => Decimal::from_str(swap_fee),
+            None => Ok(Decimal::zero()),
+        }
+    }
+
+    /// Share of the pool value held in `asset`. That is the normalized weight for
+    /// balancer pools. Stableswap assets are worth their scaling factor apart, so
+    /// their share is the asset's part of the scaled liquidity.
+    pub fn value_share(&self, asset: &PoolAsset) -> StdResult<Decimal> {
+        match self {
+            VaultPool::Balancer(pool) => {
+                let total_weight = Uint128::from_str(&pool.total_weight)?;
+                Ok(Decimal::from_ratio(asset.weight, total_weight))
+            }
+            VaultPool::StableSwap(_) => {
+                let scaled = |asset: &PoolAsset| {
+                    Decimal::from_ratio(asset.balance, asset.weight.max(Uint128::one()))
+                };
+                let total = self
+                    .assets()?
+                    .iter()
+                    .fold(Decimal::zero(), |total, asset| total + scaled(asset));
+                if total.is_zero() {
+                    return Ok(Decimal::zero());
+                }
+                Ok(scaled(asset) / total)
+            }
+        }
+    }
+}
+
+impl VaultPool {
+    /// Most LP shares a join at the pool ratio can mint with at most `coins`, and the
+    /// amount of every pool asset it takes, rounded up like the pool does.
+    pub fn simulate_join(&self, coins: &[Coin]) -> StdResult<(Uint128, Vec<Coin>)> {
+        let assets = self.assets()?;
+        let total_shares = self.total_shares()?.amount;
+        let held = |asset: &PoolAsset| {
+            coins
+                .iter()
+                .filter(|coin| coin.denom == asset.denom)
+                .map(|coin| coin.amount)
+                .sum::<Uint128>()
+        };
+
+        let shares_out = if total_shares.is_zero() || assets.iter().any(|a| a.balance.is_zero()) {
+            Uint128::zero()
+        } else {
+            assets
+                .iter()
+                .map(|asset| held(asset).multiply_ratio(total_shares, asset.balance))
+                .min()
+                .unwrap_or_default()
+        };
+        let token_in = assets
+            .iter()
+            .map(|asset| Coin {
+                denom: asset.denom.clone(),
+                amount: ceil_ratio(shares_out, asset.balance, total_shares.max(Uint128::one())),
+            })
+            .collect();
+        Ok((shares_out, token_in))
+    }
+}
+
+/// Queries pool `pool_id` from the gamm module.
+pub fn query_pool(deps: Deps, pool_id: u64) -> StdResult<VaultPool> {
+    let res = GammQuerier::new(&deps.querier).pool(pool_id)?;
+    let any = res.pool.ok_or_else(|| StdError::not_found("pool"))?;
+    VaultPool::from_any(any)
+}
+
+/// Checks pool `pool_id` against the vault config and builds the pool info from the
+/// pool itself. The given `denoms` come first, the other pool asset fills the rest.
+pub fn load_pool_info(
+    deps: Deps,
+    pool_id: u64,
+    denoms: Vec<String>,
+    white_list_denoms: Vec<String>,
+) -> Result<PoolInfo, ContractError> {
+    let any = GammQuerier::new(&deps.querier)
+        .pool(pool_id)
+        .ok()
+        .and_then(|res| res.pool)
+        .ok_or(ContractError::PoolNotFound { id: pool_id })?;
+    let pool = VaultPool::from_any(any)?;
+    let assets = pool.assets()?;
+    if assets.len() != 2 {
+        return Err(ContractError::UnsupportedPoolAssets {
+            pool_id,
+            count: assets.len() as u64,
+        });
+    }
+
+    let mut ordered: Vec<String> = vec![];
+    for denom in denoms {
+        if !assets.iter().any(|asset| asset.denom == denom) {
+            return Err(ContractError::DenomNotInPool { denom, pool_id });
+        }
+        if !ordered.contains(&denom) {
+            ordered.push(denom);
+        }
+    }
+    for asset in assets {
+        if !ordered.contains(&asset.denom) {
+            ordered.push(asset.denom);
+        }
+    }
+
+    let lp_denom = pool.total_shares()?.denom;
+    if let Some(denom) = white_list_denoms
+        .iter()
+        .find(|denom| denom.is_empty() || **denom == lp_denom)
+    {
+        return Err(ContractError::InvalidWhiteListDenom {
+            denom: denom.clone(),
+        });
+    }
+
+    Ok(PoolInfo {
+        id: pool_id,
+        denom_1: ordered[0].clone(),
+        denom_2: ordered[1].clone(),
+        white_list_denoms,
+        lp_denom,
+    })
+}
+
+pub fn query_simulate_join(deps: Deps, coins: Vec<Coin>) -> StdResult<SimulateJoinResponse> {
+    let pool_id = POOL_INFO.load(deps.storage)?.id;
+    let (share_out_amount, token_in) = query_pool(deps, pool_id)?.simulate_join(&coins)?;
+    Ok(SimulateJoinResponse {
+        share_out_amount,
+        token_in,
+    })
+}
+
+pub(crate) fn ceil_ratio(amount: Uint128, numerator: Uint128, denominator: Uint128) -> Uint128 {
+    let floor = amount.multiply_ratio(numerator, denominator);
+    if floor.full_mul(denominator) < amount.full_mul(numerator) {
+        floor + Uint128::one()
+    } else {
+        floor
+    }
+}
+
+pub(crate) fn parse_amount(amount: Option<&String>) -> StdResult<Uint128> {
+    match amount {
+        Some(amount) => Uint128::from_str(amount),
+        None => Ok(Uint128::zero()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coin, CosmosMsg, OwnedDeps};
+    use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+    use osmosis_std::types::osmosis::gamm::v1beta1::MsgJoinPool;
+
+    use crate::contract::{execute, instantiate};
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::testing::{
+        mock_balancer_pool, mock_instantiate_msg, mock_osmosis_dependencies, mock_stableswap_pool,
+    };
+
+    #[test]
+    fn decodes_balancer_and_stableswap_pools() {
+        let mut deps = mock_osmosis_dependencies(&[]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000), ("uosmo", 3_000)],
+            100,
+        ));
+        deps.querier.set_stableswap_pool(mock_stableswap_pool(
+            2,
+            &[("uusdc", 3_000_000, 1), ("uusdt", 1_000_000_000, 1_000)],
+            500,
+        ));
+
+        let balancer = query_pool(deps.as_ref(), 1).unwrap();
+        assert!(matches!(balancer, VaultPool::Balancer(_)));
+        assert_eq!(
+            balancer.total_shares().unwrap(),
+            Coin::new(100, "gamm/pool/1")
+        );
+        let assets = balancer.assets().unwrap();
+        assert_eq!(assets[1].denom, "uosmo");
+        assert_eq!(assets[1].balance, Uint128::new(3_000));
+        // equal weights, whatever the balances
+        assert_eq!(
+            balancer.value_share(&assets[1]).unwrap(),
+            Decimal::percent(50)
+        );
+        assert_eq!(balancer.swap_fee().unwrap(), Decimal::permille(2));
+
+        let stableswap = query_pool(deps.as_ref(), 2).unwrap();
+        assert!(matches!(stableswap, VaultPool::StableSwap(_)));
+        assert_eq!(stableswap.id(), 2);
+        assert_eq!(
+            stableswap.total_shares().unwrap(),
+            Coin::new(500, "gamm/pool/2")
+        );
+        let assets = stableswap.assets().unwrap();
+        assert_eq!(
+            assets[1],
+            PoolAsset {
+                denom: "uusdt".to_string(),
+                balance: Uint128::new(1_000_000_000),
+                weight: Uint128::new(1_000),
+            }
+        );
+        // 3_000_000 and 1_000_000 once scaled
+        assert_eq!(
+            stableswap.value_share(&assets[0]).unwrap(),
+            Decimal::percent(75)
+        );
+        assert_eq!(stableswap.swap_fee().unwrap(), Decimal::permille(1));
+    }
+
+    #[test]
+    fn rejects_unknown_pool_types() {
+        let any = Any {
+            type_url: "/osmosis.gamm.poolmodels.concentrated.v1beta1.Pool".to_string(),
+            value: vec![],
+        };
+        assert_eq!(
+            VaultPool::from_any(any).unwrap_err(),
+            StdError::generic_err(
+                "Unsupported pool type /osmosis.gamm.poolmodels.concentrated.v1beta1.Pool"
+            )
+        );
+    }
+
+    #[test]
+    fn instantiate_checks_the_pool() {
+        let mut deps = mock_osmosis_dependencies(&[]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000), ("uosmo", 3_000)],
+            100,
+        ));
+        deps.querier.set_stableswap_pool(mock_stableswap_pool(
+            2,
+            &[
+                ("uatom", 1_000, 1),
+                ("uosmo", 1_000, 1),
+                ("ujuno", 1_000, 1),
+            ],
+            100,
+        ));
+        let msg = |id: u64, denom_1: Option<&str>, white_list_denoms: &[&str]| InstantiateMsg {
+            id,
+            denom_1: denom_1.map(String::from),
+            denom_2: None,
+            white_list_denoms: white_list_denoms.iter().map(|d| d.to_string()).collect(),
+            ..mock_instantiate_msg()
+        };
+        let err = |msg: InstantiateMsg, deps: &mut OwnedDeps<_, _, _>| {
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err()
+        };
+
+        assert_eq!(
+            err(msg(7, None, &[]), &mut deps),
+            ContractError::PoolNotFound { id: 7 }
+        );
+        assert_eq!(
+            err(msg(2, None, &[]), &mut deps),
+            ContractError::UnsupportedPoolAssets {
+                pool_id: 2,
+                count: 3
+            }
+        );
+        assert_eq!(
+            err(msg(1, Some("ujuno"), &[]), &mut deps),
+            ContractError::DenomNotInPool {
+                denom: "ujuno".to_string(),
+                pool_id: 1
+            }
+        );
+        assert_eq!(
+            err(msg(1, None, &["uion", "gamm/pool/1"]), &mut deps),
+            ContractError::InvalidWhiteListDenom {
+                denom: "gamm/pool/1".to_string()
+            }
+        );
+
+        // the given denom comes first, the other pool asset and the LP denom come from the pool
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            msg(1, Some("uosmo"), &["uion"]),
+        )
+        .unwrap();
+        assert_eq!(
+            POOL_INFO.load(&deps.storage).unwrap(),
+            PoolInfo {
+                id: 1,
+                denom_1: "uosmo".to_string(),
+                denom_2: "uatom".to_string(),
+                white_list_denoms: vec!["uion".to_string()],
+                lp_denom: "gamm/pool/1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn simulate_join_is_limited_by_the_scarcer_asset() {
+        let mut deps = mock_osmosis_dependencies(&[coin(5_000, "uatom"), coin(1_000, "uosmo")]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 3_000_000)],
+            100_000,
+        ));
+        let msg = mock_instantiate_msg();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        // 1_000 uosmo are worth 33.3 shares, the 33 whole ones take 330 uatom and 990 uosmo
+        let res = query_simulate_join(
+            deps.as_ref(),
+            vec![coin(5_000, "uatom"), coin(1_000, "uosmo")],
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            SimulateJoinResponse {
+                share_out_amount: Uint128::new(33),
+                token_in: vec![coin(330, "uatom"), coin(990, "uosmo")],
+            }
+        );
+        // a single asset can't join at the pool ratio
+        let res = query_simulate_join(deps.as_ref(), vec![coin(5_000, "uatom")]).unwrap();
+        assert_eq!(res.share_out_amount, Uint128::zero());
+
+        // JoinPool computes the same amount from the vault balance, capped by `token_in_maxs`
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::JoinPool {
+                pool_id: 1,
+                token_in_maxs: vec![ProtoCoin {
+                    denom: "uatom".to_string(),
+                    amount: "100".to_string(),
+                }],
+            },
+        )
+        .unwrap();
+        let join = match &res.messages[0].msg {
+            CosmosMsg::Stargate { value, .. } => MsgJoinPool::try_from(value.clone()).unwrap(),
+            msg => panic!("unexpected message {:?}", msg),
+        };
+        assert_eq!(
+            join,
+            MsgJoinPool {
+                sender: MOCK_CONTRACT_ADDR.to_string(),
+                pool_id: 1,
+                share_out_amount: "10".to_string(),
+                token_in_maxs: vec![
+                    ProtoCoin {
+                        denom: "uatom".to_string(),
+                        amount: "100".to_string(),
+                    },
+                    ProtoCoin {
+                        denom: "uosmo".to_string(),
+                        amount: "1000".to_string(),
+                    },
+                ],
+            }
+        );
+
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, vec![]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::JoinPool {
+                pool_id: 1,
+                token_in_maxs: vec![],
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoBalancesFound {});
+
+        // only the vault pool can be joined
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::JoinPool {
+                pool_id: 2,
+                token_in_maxs: vec![],
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NotVaultPool { pool_id: 2 });
+    }
+}
@@ -0,0 +1,399 @@
+use cosmwasm_std::{Addr, Deps, DepsMut, MessageInfo, Response, StdResult};
+use cw20::RolesResponse;
+
+use crate::error::ContractError;
+use crate::state::{DEPOSITOR, GUARDIAN, OPERATOR, OWNER, PENDING_OWNER};
+
+pub fn assert_owner(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    OWNER
+        .assert_admin(deps, sender)
+        .map_err(|_| ContractError::Unauthorized {})
+}
+
+/// Operational messages (pool joins, bonding, reward conversion) may be sent by
+/// the owner or by the operator the owner appointed to run the vault.
+pub fn assert_owner_or_operator(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    if OWNER.is_admin(deps, sender)? || OPERATOR.is_admin(deps, sender)? {
+        Ok(())
+    } else {
+        Err(ContractError::Unauthorized {})
+    }
+}
+
+/// The guardian can only pause the vault, everything else stays with the owner.
+pub fn assert_owner_or_guardian(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    if OWNER.is_admin(deps, sender)? || GUARDIAN.is_admin(deps, sender)? {
+        Ok(())
+    } else {
+        Err(ContractError::Unauthorized {})
+    }
+}
+
+/// Deposits are open to anyone unless the owner restricted them to a single depositor.
+pub fn assert_depositor(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    match DEPOSITOR.get(deps)? {
+        Some(depositor) if &depositor != sender => Err(ContractError::Unauthorized {}),
+        _ => Ok(()),
+    }
+}
+
+/// Attribute value of a role update, `None` when the role is cleared.
+fn role_attribute(addr: &Option<Addr>) -> String {
+    addr.as_ref()
+        .map(Addr::to_string)
+        .unwrap_or_else(|| "None".to_string())
+}
+
+/// First step of the ownership transfer, the new owner has to accept it. Without an
+/// owner it cancels the transfer in progress.
+pub fn execute_update_owner(
+    deps: DepsMut,
+    info: MessageInfo,
+    owner: Option<String>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    let pending = owner
+        .map(|owner| deps.api.addr_validate(&owner))
+        .transpose()?;
+    match &pending {
+        Some(pending) => PENDING_OWNER.save(deps.storage, pending)?,
+        None => PENDING_OWNER.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_owner")
+        .add_attribute("pending_owner", role_attribute(&pending)))
+}
+
+pub fn execute_accept_ownership(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let pending = PENDING_OWNER
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingOwner {})?;
+    if pending != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    PENDING_OWNER.remove(deps.storage);
+    OWNER.set(deps, Some(pending))?;
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_ownership")
+        .add_attribute("owner", info.sender))
+}
+
+pub fn execute_update_operator(
+    deps: DepsMut,
+    info: MessageInfo,
+    operator: Option<String>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    let operator = operator
+        .map(|operator| deps.api.addr_validate(&operator))
+        .transpose()?;
+    let attribute = role_attribute(&operator);
+    OPERATOR.set(deps, operator)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_operator")
+        .add_attribute("operator", attribute))
+}
+
+pub fn execute_update_depositor(
+    deps: DepsMut,
+    info: MessageInfo,
+    depositor: Option<String>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    let depositor = depositor
+        .map(|depositor| deps.api.addr_validate(&depositor))
+        .transpose()?;
+    let attribute = role_attribute(&depositor);
+    DEPOSITOR.set(deps, depositor)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_depositor")
+        .add_attribute("depositor", attribute))
+}
+
+pub fn execute_update_guardian(
+    deps: DepsMut,
+    info: MessageInfo,
+    guardian: Option<String>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    let guardian = guardian
+        .map(|guardian| deps.api.addr_validate(&guardian))
+        .transpose()?;
+    let attribute = role_attribute(&guardian);
+    GUARDIAN.set(deps, guardian)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_guardian")
+        .add_attribute("guardian", attribute))
+}
+
+pub fn query_roles(deps: Deps) -> StdResult<RolesResponse> {
+    Ok(RolesResponse {
+        owner: OWNER.query_admin(deps)?.admin,
+        pending_owner: PENDING_OWNER.may_load(deps.storage)?.map(String::from),
+        operator: OPERATOR.query_admin(deps)?.admin,
+        depositor: DEPOSITOR.query_admin(deps)?.admin,
+        guardian: GUARDIAN.query_admin(deps)?.admin,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_env, mock_info};
+    use cosmwasm_std::{coin, Storage, Uint128};
+
+    use crate::contract::{execute, instantiate, migrate};
+    use crate::msg::{ExecuteMsg, MigrateMsg};
+    use crate::state::STATE;
+    use crate::testing::{
+        mock_balancer_pool, mock_dependencies, mock_instantiate_msg, mock_osmosis_dependencies,
+    };
+
+    fn do_instantiate(deps: DepsMut) {
+        let msg = mock_instantiate_msg();
+        instantiate(deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+    }
+
+    #[test]
+    fn instantiator_is_owner() {
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut());
+
+        assert_eq!(
+            query_roles(deps.as_ref()).unwrap(),
+            RolesResponse {
+                owner: Some("owner".to_string()),
+                pending_owner: None,
+                operator: None,
+                depositor: None,
+                guardian: None,
+            }
+        );
+    }
+
+    #[test]
+    fn two_step_ownership_transfer() {
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut());
+
+        // only the owner can propose a new owner
+        let msg = ExecuteMsg::UpdateOwner {
+            owner: Some("new_owner".to_string()),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("rando", &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        // nothing changes until the transfer is accepted
+        let roles = query_roles(deps.as_ref()).unwrap();
+        assert_eq!(roles.owner, Some("owner".to_string()));
+        assert_eq!(roles.pending_owner, Some("new_owner".to_string()));
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("rando", &[]),
+            ExecuteMsg::AcceptOwnership {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_owner", &[]),
+            ExecuteMsg::AcceptOwnership {},
+        )
+        .unwrap();
+        let roles = query_roles(deps.as_ref()).unwrap();
+        assert_eq!(roles.owner, Some("new_owner".to_string()));
+        assert_eq!(roles.pending_owner, None);
+
+        // the previous owner lost its rights, and there is nothing left to accept
+        let msg = ExecuteMsg::UpdateOperator { operator: None };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_owner", &[]),
+            ExecuteMsg::AcceptOwnership {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoPendingOwner {});
+    }
+
+    #[test]
+    fn cancel_ownership_transfer() {
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut());
+        let msg = ExecuteMsg::UpdateOwner {
+            owner: Some("new_owner".to_string()),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        // only the owner can cancel it
+        let msg = ExecuteMsg::UpdateOwner { owner: None };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("rando", &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(res.attributes[1], ("pending_owner", "None"));
+        assert_eq!(query_roles(deps.as_ref()).unwrap().pending_owner, None);
+
+        // the proposed owner has nothing left to accept
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_owner", &[]),
+            ExecuteMsg::AcceptOwnership {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoPendingOwner {});
+        let roles = query_roles(deps.as_ref()).unwrap();
+        assert_eq!(roles.owner, Some("owner".to_string()));
+    }
+
+    #[test]
+    fn operator_runs_the_vault_but_cannot_move_funds() {
+        let mut deps = mock_osmosis_dependencies(&[coin(1_000, "uatom"), coin(1_000, "uosmo")]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+            100_000,
+        ));
+        do_instantiate(deps.as_mut());
+
+        let msg = ExecuteMsg::ConvertRewards {
+            max_slippage_bps: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("keeper", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let msg = ExecuteMsg::UpdateOperator {
+            operator: Some("keeper".to_string()),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("keeper", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(
+            query_roles(deps.as_ref()).unwrap().operator,
+            Some("keeper".to_string())
+        );
+
+        for msg in [
+            ExecuteMsg::UnbondAll {},
+            ExecuteMsg::Unbond {
+                id: 1,
+                coins: vec![],
+            },
+            ExecuteMsg::JoinPool {
+                pool_id: 1,
+                token_in_maxs: vec![],
+            },
+        ] {
+            let err =
+                execute(deps.as_mut(), mock_env(), mock_info("rando", &[]), msg.clone()).unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            execute(deps.as_mut(), mock_env(), mock_info("keeper", &[]), msg).unwrap();
+        }
+
+        // withdrawing raw tokens and managing the whitelist stay with the owner
+        for msg in [
+            ExecuteMsg::WithdrawTokens {
+                to_address: "keeper".to_string(),
+                tokens: cosmwasm_std::coins(100, "uosmo"),
+            },
+            ExecuteMsg::UpdateWhiteList { coins: vec![] },
+            ExecuteMsg::UpdateDepositor { depositor: None },
+        ] {
+            let err =
+                execute(deps.as_mut(), mock_env(), mock_info("keeper", &[]), msg.clone()).unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        }
+    }
+
+    #[test]
+    fn migrate_moves_legacy_owner() {
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut());
+
+        // rewrite the storage the way vaults from before the role system stored it
+        deps.storage.remove(b"owner");
+        deps.storage.remove(b"operator");
+        deps.storage.remove(b"depositor");
+        deps.storage.remove(b"guardian");
+        deps.storage.set(
+            b"state",
+            br#"{"owner":"legacy_owner","fee":0,"fee_collector_address":"collector"}"#,
+        );
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(
+            query_roles(deps.as_ref()).unwrap(),
+            RolesResponse {
+                owner: Some("legacy_owner".to_string()),
+                pending_owner: None,
+                operator: None,
+                depositor: None,
+                guardian: None,
+            }
+        );
+        assert_eq!(
+            STATE.load(deps.as_ref().storage).unwrap().fee_collector_address,
+            "collector"
+        );
+    }
+
+    #[test]
+    fn depositor_restricts_deposits() {
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut());
+
+        assert_depositor(deps.as_ref(), &Addr::unchecked("anyone")).unwrap();
+
+        let msg = ExecuteMsg::UpdateDepositor {
+            depositor: Some("alice".to_string()),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(
+            query_roles(deps.as_ref()).unwrap().depositor,
+            Some("alice".to_string())
+        );
+
+        assert_depositor(deps.as_ref(), &Addr::unchecked("alice")).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &cosmwasm_std::coins(5_000, "gamm/pool/1")),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // cw20 transfers of existing shares are not gated
+        let msg = ExecuteMsg::Transfer {
+            recipient: "bob".to_string(),
+            amount: Uint128::zero(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidZeroAmount {});
+    }
+}
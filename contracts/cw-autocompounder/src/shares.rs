@@ -0,0 +1,367 @@
+use cosmwasm_std::{
+    coins, Addr, BankMsg, CosmosMsg, Deps, Env, MessageInfo, StdError, StdResult, Storage, Uint128,
+};
+use cw_utils::must_pay;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::tokenfactory::v1beta1::{MsgBurn, MsgCreateDenom, MsgMint};
+
+use crate::error::ContractError;
+use crate::msg::ExecuteMsg;
+use crate::state::{TokenInfo, BALANCES, SHARE_DENOM};
+
+/// Tokenfactory denom created by the vault for `subdenom`.
+pub fn share_denom(env: &Env, subdenom: &str) -> String {
+    format!("factory/{}/{}", env.contract.address, subdenom)
+}
+
+/// Switches the vault to native shares: creates the tokenfactory denom and remembers it.
+pub fn create_share_denom(
+    storage: &mut dyn Storage,
+    env: &Env,
+    subdenom: String,
+) -> StdResult<CosmosMsg> {
+    SHARE_DENOM.save(storage, &share_denom(env, &subdenom))?;
+    Ok(MsgCreateDenom {
+        sender: env.contract.address.to_string(),
+        subdenom,
+    }
+    .into())
+}
+
+/// Rejects the cw20 messages working on the `BALANCES` ledger in native share mode, as it
+/// doesn't back the shares then. Native shares move through the bank module instead.
+pub fn assert_cw20_shares(deps: Deps, msg: &ExecuteMsg) -> Result<(), ContractError> {
+    let ledger_msg = matches!(
+        msg,
+        ExecuteMsg::Transfer { .. }
+            | ExecuteMsg::Send { .. }
+            | ExecuteMsg::TransferFrom { .. }
+            | ExecuteMsg::SendFrom { .. }
+            | ExecuteMsg::Burn { .. }
+            | ExecuteMsg::BurnFrom { .. }
+            | ExecuteMsg::Mint { .. }
+            | ExecuteMsg::IncreaseAllowance { .. }
+            | ExecuteMsg::DecreaseAllowance { .. }
+    );
+    if ledger_msg && SHARE_DENOM.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::NativeShares {});
+    }
+    Ok(())
+}
+
+/// Vault shares held by `address`, read from the bank module in native share mode.
+pub fn share_balance(deps: Deps, address: &Addr) -> StdResult<Uint128> {
+    match SHARE_DENOM.may_load(deps.storage)? {
+        Some(denom) => Ok(deps.querier.query_balance(address, denom)?.amount),
+        None => Ok(BALANCES
+            .may_load(deps.storage, address)?
+            .unwrap_or_default()),
+    }
+}
+
+/// Issues `amount` vault shares to `recipient`. Native shares are minted to the vault
+/// and sent on from there, as tokenfactory only mints to the denom admin.
+pub fn mint_shares(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &mut TokenInfo,
+    recipient: &Addr,
+    amount: Uint128,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    config.total_supply += amount;
+    if let Some(limit) = config.get_cap() {
+        if config.total_supply > limit {
+            return Err(ContractError::CannotExceedCap {});
+        }
+    }
+
+    let denom = match SHARE_DENOM.may_load(storage)? {
+        Some(denom) => denom,
+        None => {
+            BALANCES.update(
+                storage,
+                recipient,
+                |balance: Option<Uint128>| -> StdResult<_> {
+                    Ok(balance.unwrap_or_default() + amount)
+                },
+            )?;
+            return Ok(vec![]);
+        }
+    };
+
+    let mut messages: Vec<CosmosMsg> = vec![MsgMint {
+        sender: env.contract.address.to_string(),
+        amount: Some(ProtoCoin {
+            denom: denom.clone(),
+            amount: amount.to_string(),
+        }),
+    }
+    .into()];
+    if recipient != &env.contract.address {
+        messages.push(
+            BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: coins(amount.u128(), denom),
+            }
+            .into(),
+        );
+    }
+    Ok(messages)
+}
+
+/// Redeems `amount` vault shares of the sender. Native shares have to be sent along
+/// with the message and are burned from the vault balance.
+pub fn burn_shares(
+    storage: &mut dyn Storage,
+    env: &Env,
+    info: &MessageInfo,
+    config: &mut TokenInfo,
+    amount: Uint128,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    config.total_supply = config
+        .total_supply
+        .checked_sub(amount)
+        .map_err(StdError::from)?;
+
+    let denom = match SHARE_DENOM.may_load(storage)? {
+        Some(denom) => denom,
+        None => {
+            BALANCES.update(
+                storage,
+                &info.sender,
+                |balance: Option<Uint128>| -> StdResult<_> {
+                    Ok(balance.unwrap_or_default().checked_sub(amount)?)
+                },
+            )?;
+            return Ok(vec![]);
+        }
+    };
+
+    let sent = must_pay(info, &denom)?;
+    if sent != amount {
+        return Err(ContractError::SharesMismatch {
+            sent,
+            requested: amount,
+        });
+    }
+    Ok(vec![MsgBurn {
+        sender: env.contract.address.to_string(),
+        amount: Some(ProtoCoin {
+            denom,
+            amount: amount.to_string(),
+        }),
+    }
+    .into()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coin, Binary, SubMsg};
+    use cw_utils::PaymentError;
+
+    use crate::contract::{execute, instantiate, query_balance, query_token_info};
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::testing::{mock_balancer_pool, mock_instantiate_msg, mock_osmosis_dependencies};
+
+    const LP: &str = "gamm/pool/1";
+    const SHARES: &str = "factory/cosmos2contract/vault";
+
+    fn instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            share_subdenom: Some("vault".to_string()),
+            ..mock_instantiate_msg()
+        }
+    }
+
+    fn mint_msg(amount: u128) -> SubMsg {
+        SubMsg::new(MsgMint {
+            sender: MOCK_CONTRACT_ADDR.to_string(),
+            amount: Some(ProtoCoin {
+                denom: SHARES.to_string(),
+                amount: amount.to_string(),
+            }),
+        })
+    }
+
+    #[test]
+    fn instantiate_creates_denom() {
+        let mut deps = mock_osmosis_dependencies(&[]);
+        let res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            instantiate_msg(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(MsgCreateDenom {
+                sender: MOCK_CONTRACT_ADDR.to_string(),
+                subdenom: "vault".to_string(),
+            })]
+        );
+        assert_eq!(SHARE_DENOM.load(&deps.storage).unwrap(), SHARES);
+    }
+
+    #[test]
+    fn cw20_ledger_is_closed_with_native_shares() {
+        let mut deps = mock_osmosis_dependencies(&[]);
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            instantiate_msg(),
+        )
+        .unwrap();
+
+        let amount = Uint128::new(100);
+        let msgs = vec![
+            ExecuteMsg::Transfer {
+                recipient: "bob".to_string(),
+                amount,
+            },
+            ExecuteMsg::Send {
+                contract: "contract".to_string(),
+                amount,
+                msg: Binary::default(),
+            },
+            ExecuteMsg::TransferFrom {
+                owner: "alice".to_string(),
+                recipient: "bob".to_string(),
+                amount,
+            },
+            ExecuteMsg::SendFrom {
+                owner: "alice".to_string(),
+                contract: "contract".to_string(),
+                amount,
+                msg: Binary::default(),
+            },
+            ExecuteMsg::Burn { amount },
+            ExecuteMsg::BurnFrom {
+                owner: "alice".to_string(),
+                amount,
+            },
+            ExecuteMsg::Mint {
+                recipient: "bob".to_string(),
+                amount,
+            },
+            ExecuteMsg::IncreaseAllowance {
+                spender: "bob".to_string(),
+                amount,
+                expires: None,
+            },
+            ExecuteMsg::DecreaseAllowance {
+                spender: "bob".to_string(),
+                amount,
+                expires: None,
+            },
+        ];
+        for msg in msgs {
+            let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap_err();
+            assert_eq!(err, ContractError::NativeShares {});
+        }
+        assert_eq!(
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            Uint128::zero()
+        );
+    }
+
+    #[test]
+    fn native_deposit_and_withdraw() {
+        let mut deps = mock_osmosis_dependencies(&[coin(100_000, LP)]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000), ("uosmo", 1_000)],
+            1_000_000,
+        ));
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            instantiate_msg(),
+        )
+        .unwrap();
+
+        // shares are minted to the vault, which keeps the minimum shares and sends the rest on
+        let info = mock_info("alice", &[coin(100_000, LP)]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                mint_msg(1_000),
+                mint_msg(99_000),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "alice".to_string(),
+                    amount: coins(99_000, SHARES),
+                }),
+            ]
+        );
+        assert_eq!(
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            Uint128::new(100_000)
+        );
+
+        // balances come from the bank module
+        deps.querier
+            .update_balance("alice", vec![coin(99_000, SHARES)]);
+        assert_eq!(
+            query_balance(deps.as_ref(), "alice".to_string())
+                .unwrap()
+                .balance,
+            Uint128::new(99_000)
+        );
+
+        // withdrawing needs the shares sent along, in the exact amount
+        let withdraw = ExecuteMsg::Withdraw {
+            shares: Uint128::new(9_000),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            withdraw.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[coin(8_000, SHARES)]),
+            withdraw.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::SharesMismatch {
+                sent: Uint128::new(8_000),
+                requested: Uint128::new(9_000),
+            }
+        );
+
+        let info = mock_info("alice", &[coin(9_000, SHARES)]);
+        let res = execute(deps.as_mut(), mock_env(), info, withdraw).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(MsgBurn {
+                    sender: MOCK_CONTRACT_ADDR.to_string(),
+                    amount: Some(ProtoCoin {
+                        denom: SHARES.to_string(),
+                        amount: "9000".to_string(),
+                    }),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "alice".to_string(),
+                    amount: coins(9_000, LP),
+                }),
+            ]
+        );
+        assert_eq!(
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            Uint128::new(91_000)
+        );
+    }
+}
@@ -1,14 +1,81 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Coin, Decimal, Timestamp, Uint128};
+use cw_controllers::{Admin, Claims};
+use cw_storage_plus::{Deque, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
-use cw20::{AllowanceResponse, Logo, MarketingInfoResponse};
+use cw20::{
+    AllowanceResponse, EmergencyExitInfo, HarvestInfo, KeeperConfig, LockInfo, Logo,
+    MarketingInfoResponse,
+};
+use osmosis_std::types::osmosis::gamm::v1beta1::SwapAmountInRoute;
 
 #[cw_serde]
 pub struct State {
-    pub owner: Addr,
-    pub fee: u64,
-    pub fee_collector_address: String,
+    /// Share of every harvested reward sent to `fee_collector_address`
+    pub fee: Decimal,
+    pub fee_collector_address: Addr,
+    /// Max slippage of reward swaps against their TWAP value, in basis points
+    #[serde(default = "default_max_slippage_bps")]
+    pub max_slippage_bps: u64,
+    /// Duration `Compound` locks the LP shares it mints for, in seconds
+    #[serde(default = "default_lock_duration_seconds")]
+    pub lock_duration_seconds: u64,
+    /// Set in superfluid mode, the validator the locked LP is delegated to
+    #[serde(default)]
+    pub superfluid_validator: Option<String>,
+}
+
+fn default_max_slippage_bps() -> u64 {
+    crate::swap::DEFAULT_MAX_SLIPPAGE_BPS
+}
+
+fn default_lock_duration_seconds() -> u64 {
+    crate::compound::DEFAULT_LOCK_DURATION_SECONDS
+}
+
+/// Progress of a `Compound` or `ConvertRewards` run while it waits for the replies of its
+/// submessages
+#[cw_serde]
+pub struct CompoundState {
+    /// Output denoms of the reward swaps still waiting for their reply, in execution order
+    pub pending_swaps: Vec<String>,
+    /// Pool joins still waiting for their reply
+    pub pending_joins: u32,
+    /// LP shares minted by the joins that already replied
+    pub shares_out: Uint128,
+    /// Whitelisted rewards taken by the harvest, performance fee included
+    #[serde(default)]
+    pub harvested: Vec<Coin>,
+    #[serde(default)]
+    pub fees: Vec<Coin>,
+    #[serde(default)]
+    pub bounty: Vec<Coin>,
+    /// Output of the reward swaps that already replied
+    #[serde(default)]
+    pub swap_outputs: Vec<Coin>,
+}
+
+/// Deposit of pool assets waiting for its swap and join replies
+#[cw_serde]
+pub struct ZapState {
+    pub depositor: Addr,
+    /// Pool assets put into the join, the swap output included once it replied
+    pub coins: Vec<Coin>,
+}
+
+/// Claim waiting for its pool exit to reply
+#[cw_serde]
+pub struct PendingClaim {
+    pub recipient: Addr,
+    /// Pool asset the claim exits to, both assets if unset
+    pub denom_out: Option<String>,
+}
+
+/// LP a superfluid unbond released on top of its claim, liquid again at `release_at`
+#[cw_serde]
+pub struct ReleasedLp {
+    pub amount: Uint128,
+    pub release_at: Timestamp,
 }
 
 #[cw_serde]
@@ -26,6 +93,9 @@ pub struct PoolInfo {
     pub denom_1: String,
     pub denom_2: String,
     pub white_list_denoms: Vec<String>,
+    /// LP denom of the pool, `gamm/pool/{id}`
+    #[serde(default)]
+    pub lp_denom: String,
 }
 
 
@@ -43,8 +113,45 @@ impl TokenInfo {
 }
 
 pub const STATE: Item<State> = Item::new("state");
+pub const OWNER: Admin = Admin::new("owner");
+/// Owner proposed by `UpdateOwner`, waiting for `AcceptOwnership`
+pub const PENDING_OWNER: Item<Addr> = Item::new("pending_owner");
+/// Keeper allowed to run the operational messages next to the owner
+pub const OPERATOR: Admin = Admin::new("operator");
+/// If set, the only address allowed to deposit into the vault
+pub const DEPOSITOR: Admin = Admin::new("depositor");
+/// If set, may pause the vault next to the owner
+pub const GUARDIAN: Admin = Admin::new("guardian");
 pub const TOKEN_INFO: Item<TokenInfo> = Item::new("token_info");
 pub const POOL_INFO: Item<PoolInfo> = Item::new("pool_info");
+/// Swap route for each reward denom, ending in `denom_1` or `denom_2`
+pub const ROUTES: Map<&str, Vec<SwapAmountInRoute>> = Map::new("routes");
+/// Only set while a `Compound` is being executed
+pub const COMPOUND: Item<CompoundState> = Item::new("compound");
+/// Only set while a `DepositSingle` or `Zap` waits for its replies
+pub const ZAP: Item<ZapState> = Item::new("zap");
+/// LP shares owed to share holders who requested an unbond, per holder
+pub const CLAIMS: Claims = Claims::new("claims");
+/// Sum of the LP shares of all the claims not paid out yet
+pub const PENDING_UNBONDS: Item<Uint128> = Item::new("pending_unbonds");
+/// Only set while a `Claim` waits for its pool exit to reply
+pub const PENDING_CLAIM: Item<PendingClaim> = Item::new("pending_claim");
+/// Superfluid mode only, LP unbonds released without a claim on it, locked again by a compound
+pub const RELEASED_LP: Item<Vec<ReleasedLp>> = Item::new("released_lp");
+/// Only set in native share mode, the tokenfactory denom of the vault shares
+pub const SHARE_DENOM: Item<String> = Item::new("share_denom");
+/// Epoch number of the last `Compound`, see `epochs::COMPOUND_EPOCH_IDENTIFIER`
+pub const LAST_COMPOUND_EPOCH: Item<u64> = Item::new("last_compound_epoch");
+/// Only set in superfluid mode, the uosmo the vault held after its last harvest
+pub const SUPERFLUID_OSMO_BASE: Item<Uint128> = Item::new("superfluid_osmo_base");
+/// Only set while keepers can run `Compound`
+pub const KEEPER: Item<KeeperConfig> = Item::new("keeper");
+/// Harvest history, oldest first, capped at `history::MAX_HARVEST_RECORDS`
+pub const HARVESTS: Deque<HarvestInfo> = Deque::new("harvests");
+/// Set while `Pause` holds deposits, compounding and cw20 transfers
+pub const PAUSED: Item<bool> = Item::new("paused");
+/// Only set after an `EmergencyExit`, the vault is withdraw-only for good
+pub const EMERGENCY_EXIT: Item<EmergencyExitInfo> = Item::new("emergency_exit");
 pub const MARKETING_INFO: Item<MarketingInfoResponse> = Item::new("marketing_info");
 pub const LOGO: Item<Logo> = Item::new("logo");
 pub const BALANCES: Map<&Addr, Uint128> = Map::new("balance");
@@ -52,3 +159,22 @@ pub const ALLOWANCES: Map<(&Addr, &Addr), AllowanceResponse> = Map::new("allowan
 // TODO: After https://github.com/CosmWasm/cw-plus/issues/670 is implemented, replace this with a `MultiIndex` over `ALLOWANCES`
 pub const ALLOWANCES_SPENDER: Map<(&Addr, &Addr), AllowanceResponse> =
     Map::new("allowance_spender");
+
+pub struct LockIndexes<'a> {
+    pub duration: MultiIndex<'a, u64, LockInfo, u64>,
+}
+
+impl<'a> IndexList<LockInfo> for LockIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<LockInfo>> + '_> {
+        let v: Vec<&dyn Index<LockInfo>> = vec![&self.duration];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Locks created by the vault that did not start unlocking, by lock id
+pub fn locks<'a>() -> IndexedMap<'a, u64, LockInfo, LockIndexes<'a>> {
+    let indexes = LockIndexes {
+        duration: MultiIndex::new(|_pk, lock| lock.duration_seconds, "locks", "locks__duration"),
+    };
+    IndexedMap::new("locks", indexes)
+}
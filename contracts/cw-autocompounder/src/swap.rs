@@ -0,0 +1,148 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{Coin, Decimal, Deps, Env, StdResult, Uint128};
+use osmosis_std::shim::Timestamp;
+use osmosis_std::types::osmosis::gamm::v1beta1::SwapAmountInRoute;
+use osmosis_std::types::osmosis::twap::v1beta1::TwapQuerier;
+
+use crate::error::ContractError;
+use crate::pool::query_pool;
+
+/// Window of the TWAP reward swaps are priced against. Long enough that a
+/// single block of pool manipulation barely moves it.
+pub const TWAP_WINDOW_SECONDS: u64 = 3600;
+/// Max slippage used until the owner configures one, 1%
+pub const DEFAULT_MAX_SLIPPAGE_BPS: u64 = 100;
+pub const MAX_BPS: u64 = 10_000;
+
+pub fn validate_slippage(max_slippage_bps: u64) -> Result<u64, ContractError> {
+    if max_slippage_bps > MAX_BPS {
+        return Err(ContractError::InvalidSlippage {});
+    }
+    Ok(max_slippage_bps)
+}
+
+/// Output of swapping `token_in` along `routes` at the arithmetic TWAP of every hop,
+/// less the swap fee each hop pool takes. The slippage tolerance only has to cover
+/// the price moving away from the TWAP.
+pub fn twap_out_amount(
+    deps: Deps,
+    env: &Env,
+    token_in: &Coin,
+    routes: &[SwapAmountInRoute],
+) -> StdResult<Uint128> {
+    let start = env.block.time.minus_seconds(TWAP_WINDOW_SECONDS);
+    let start_time = Timestamp {
+        seconds: start.seconds() as i64,
+        nanos: start.subsec_nanos() as i32,
+    };
+
+    let twap = TwapQuerier::new(&deps.querier);
+    let mut denom = token_in.denom.clone();
+    let mut amount = token_in.amount;
+    for hop in routes {
+        let price = twap
+            .arithmetic_twap_to_now(
+                hop.pool_id,
+                denom,
+                hop.token_out_denom.clone(),
+                Some(start_time.clone()),
+            )?
+            .arithmetic_twap;
+        let swap_fee = query_pool(deps, hop.pool_id)?.swap_fee()?;
+        amount = amount * (Decimal::from_str(&price)? * (Decimal::one() - swap_fee));
+        denom = hop.token_out_denom.clone();
+    }
+    Ok(amount)
+}
+
+/// Smallest acceptable output of a reward swap, `max_slippage_bps` under its TWAP value.
+pub fn min_out_amount(
+    deps: Deps,
+    env: &Env,
+    token_in: &Coin,
+    routes: &[SwapAmountInRoute],
+    max_slippage_bps: u64,
+) -> StdResult<Uint128> {
+    let expected = twap_out_amount(deps, env, token_in, routes)?;
+    let min_out = expected.multiply_ratio(MAX_BPS - max_slippage_bps, MAX_BPS);
+    // the swap module rejects a zero minimum
+    Ok(min_out.max(Uint128::one()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::coin;
+    use cosmwasm_std::testing::mock_env;
+
+    use crate::testing::{mock_balancer_pool, mock_osmosis_dependencies, mock_route_pool};
+
+    fn hop(pool_id: u64, token_out_denom: &str) -> SwapAmountInRoute {
+        SwapAmountInRoute {
+            pool_id,
+            token_out_denom: token_out_denom.to_string(),
+        }
+    }
+
+    #[test]
+    fn prices_every_hop_at_twap() {
+        let mut deps = mock_osmosis_dependencies(&[]);
+        deps.querier.set_twap(5, "ustrd", "uion", "0.5");
+        deps.querier.set_twap(2, "uion", "uosmo", "3.2");
+        deps.querier.set_pool(mock_route_pool(5, "ustrd", "uion"));
+        deps.querier.set_pool(mock_route_pool(2, "uion", "uosmo"));
+
+        let routes = [hop(5, "uion"), hop(2, "uosmo")];
+        let out = twap_out_amount(deps.as_ref(), &mock_env(), &coin(1_000, "ustrd"), &routes);
+        assert_eq!(out.unwrap(), Uint128::new(1_600));
+
+        // every hop takes its swap fee off the output, 0.2% here
+        deps.querier.set_pool(mock_balancer_pool(
+            5,
+            &[("ustrd", 1_000_000), ("uion", 1_000_000)],
+            100_000,
+        ));
+        deps.querier.set_pool(mock_balancer_pool(
+            2,
+            &[("uion", 1_000_000), ("uosmo", 1_000_000)],
+            100_000,
+        ));
+        let out = twap_out_amount(deps.as_ref(), &mock_env(), &coin(1_000, "ustrd"), &routes);
+        assert_eq!(out.unwrap(), Uint128::new(1_593));
+
+        let min = min_out_amount(
+            deps.as_ref(),
+            &mock_env(),
+            &coin(1_000, "ustrd"),
+            &routes,
+            250,
+        );
+        assert_eq!(min.unwrap(), Uint128::new(1_553));
+
+        // full tolerance still asks for something back
+        let min = min_out_amount(
+            deps.as_ref(),
+            &mock_env(),
+            &coin(1_000, "ustrd"),
+            &routes,
+            MAX_BPS,
+        );
+        assert_eq!(min.unwrap(), Uint128::one());
+
+        // pools without a twap can't be priced
+        let routes = [hop(7, "uosmo")];
+        twap_out_amount(deps.as_ref(), &mock_env(), &coin(1_000, "ustrd"), &routes).unwrap_err();
+    }
+
+    #[test]
+    fn slippage_is_capped_at_100_percent() {
+        assert_eq!(validate_slippage(0).unwrap(), 0);
+        assert_eq!(validate_slippage(MAX_BPS).unwrap(), MAX_BPS);
+        assert_eq!(
+            validate_slippage(MAX_BPS + 1).unwrap_err(),
+            ContractError::InvalidSlippage {}
+        );
+    }
+}
@@ -0,0 +1,478 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+
+use cosmwasm_std::testing::{mock_env, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_slice, to_binary, Addr, Binary, Coin, ContractResult, Decimal, DepsMut, Empty, OwnedDeps,
+    Querier, QuerierResult, QueryRequest, StdError, StdResult, SystemError, SystemResult, Uint128,
+};
+use osmosis_std::shim::Any;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::epochs::v1beta1::{EpochInfo, QueryEpochsInfoResponse};
+use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::{
+    Pool as StableSwapPool, PoolParams as StableSwapPoolParams,
+};
+use osmosis_std::types::osmosis::gamm::v1beta1::{
+    Pool, PoolAsset, PoolParams, QueryPoolRequest, QueryPoolResponse, QuerySpotPriceRequest,
+    QuerySpotPriceResponse, QueryTotalPoolLiquidityRequest, QueryTotalPoolLiquidityResponse,
+    QueryTotalSharesRequest, QueryTotalSharesResponse,
+};
+use osmosis_std::types::osmosis::lockup::{
+    AccountLockedCoinsRequest, AccountLockedCoinsResponse,
+    AccountLockedLongerDurationNotUnlockingOnlyRequest,
+    AccountLockedLongerDurationNotUnlockingOnlyResponse, LockedRequest, LockedResponse, PeriodLock,
+};
+use osmosis_std::types::osmosis::twap::v1beta1::{
+    ArithmeticTwapToNowRequest, ArithmeticTwapToNowResponse,
+};
+use osmosis_std::types::osmosis::txfees::v1beta1::{
+    QueryBaseDenomResponse, QueryDenomSpotPriceRequest, QueryDenomSpotPriceResponse,
+};
+use prost::Message;
+
+use crate::epochs::COMPOUND_EPOCH_IDENTIFIER;
+use crate::msg::InstantiateMsg;
+use crate::pool::VaultPool;
+use crate::shares::mint_shares;
+use crate::state::TOKEN_INFO;
+
+/// Base denom of the mocked txfees module.
+pub const FEE_BASE_DENOM: &str = "uosmo";
+
+/// Querier that answers the osmosis stargate queries used by the vault from
+/// in-memory pools and locks, and hands everything else to `MockQuerier`.
+pub struct OsmosisQuerier {
+    base: MockQuerier,
+    pools: HashMap<u64, Any>,
+    locks: Vec<PeriodLock>,
+    twaps: HashMap<(u64, String, String), String>,
+    spot_prices: HashMap<(u64, String, String), String>,
+    fee_token_prices: HashMap<String, String>,
+    epochs: Vec<EpochInfo>,
+}
+
+impl OsmosisQuerier {
+    pub fn new(balances: &[(&str, &[Coin])]) -> Self {
+        OsmosisQuerier {
+            base: MockQuerier::new(balances),
+            pools: HashMap::new(),
+            locks: Vec::new(),
+            twaps: HashMap::new(),
+            spot_prices: HashMap::new(),
+            fee_token_prices: HashMap::new(),
+            epochs: vec![mock_day_epoch(1, 1_571_797_000)],
+        }
+    }
+
+    pub fn update_balance(&mut self, addr: impl Into<String>, balance: Vec<Coin>) {
+        self.base.update_balance(addr, balance);
+    }
+
+    pub fn set_pool(&mut self, pool: Pool) {
+        let any = Any {
+            type_url: Pool::TYPE_URL.to_string(),
+            value: pool.encode_to_vec(),
+        };
+        self.pools.insert(pool.id, any);
+    }
+
+    pub fn set_stableswap_pool(&mut self, pool: StableSwapPool) {
+        let any = Any {
+            type_url: StableSwapPool::TYPE_URL.to_string(),
+            value: pool.encode_to_vec(),
+        };
+        self.pools.insert(pool.id, any);
+    }
+
+    pub fn set_locks(&mut self, locks: Vec<PeriodLock>) {
+        self.locks = locks;
+    }
+
+    /// Sets the arithmetic TWAP of `base` in terms of `quote` in pool `pool_id`.
+    pub fn set_twap(&mut self, pool_id: u64, base: &str, quote: &str, price: &str) {
+        self.twaps.insert(
+            (pool_id, base.to_string(), quote.to_string()),
+            price.to_string(),
+        );
+    }
+
+    /// Sets the spot price of `base` in terms of `quote` in pool `pool_id`.
+    pub fn set_spot_price(&mut self, pool_id: u64, base: &str, quote: &str, price: &str) {
+        self.spot_prices.insert(
+            (pool_id, base.to_string(), quote.to_string()),
+            price.to_string(),
+        );
+    }
+
+    /// Sets the txfees spot price of `denom` in terms of `FEE_BASE_DENOM`.
+    pub fn set_fee_token_price(&mut self, denom: &str, price: &str) {
+        self.fee_token_prices
+            .insert(denom.to_string(), price.to_string());
+    }
+
+    /// Moves the compound epoch to `current_epoch`, started at `start_seconds`.
+    pub fn set_day_epoch(&mut self, current_epoch: i64, start_seconds: i64) {
+        self.epochs = vec![mock_day_epoch(current_epoch, start_seconds)];
+    }
+
+    fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
+        match request {
+            QueryRequest::Stargate { path, data } => self.handle_stargate(path, data),
+            _ => self.base.handle_query(request),
+        }
+    }
+
+    fn handle_stargate(&self, path: &str, data: &Binary) -> QuerierResult {
+        let response = match path {
+            "/osmosis.gamm.v1beta1.Query/Pool" => self.query_pool(data),
+            "/osmosis.gamm.v1beta1.Query/TotalShares" => self.query_total_shares(data),
+            "/osmosis.gamm.v1beta1.Query/TotalPoolLiquidity" => {
+                self.query_total_pool_liquidity(data)
+            }
+            "/osmosis.lockup.Query/AccountLockedCoins" => self.query_account_locked_coins(data),
+            "/osmosis.lockup.Query/LockedByID" => self.query_lock(data),
+            "/osmosis.lockup.Query/AccountLockedLongerDurationNotUnlockingOnly" => {
+                self.query_account_open_locks(data)
+            }
+            "/osmosis.twap.v1beta1.Query/ArithmeticTwapToNow" => self.query_twap(data),
+            "/osmosis.gamm.v1beta1.Query/SpotPrice" => self.query_spot_price(data),
+            "/osmosis.epochs.v1beta1.Query/EpochInfos" => to_binary(&QueryEpochsInfoResponse {
+                epochs: self.epochs.clone(),
+            }),
+            "/osmosis.txfees.v1beta1.Query/BaseDenom" => to_binary(&QueryBaseDenomResponse {
+                base_denom: FEE_BASE_DENOM.to_string(),
+            }),
+            "/osmosis.txfees.v1beta1.Query/DenomSpotPrice" => self.query_denom_spot_price(data),
+            _ => {
+                return SystemResult::Err(SystemError::UnsupportedRequest {
+                    kind: path.to_string(),
+                })
+            }
+        };
+        SystemResult::Ok(ContractResult::from(response))
+    }
+
+    fn query_pool(&self, data: &Binary) -> StdResult<Binary> {
+        let request = QueryPoolRequest::try_from(data.clone())?;
+        let pool = self.pools.get(&request.pool_id).cloned();
+        to_binary(&QueryPoolResponse { pool })
+    }
+
+    fn vault_pool(&self, pool_id: u64) -> StdResult<VaultPool> {
+        let any = self
+            .pools
+            .get(&pool_id)
+            .cloned()
+            .ok_or_else(|| StdError::not_found("pool"))?;
+        VaultPool::from_any(any)
+    }
+
+    fn query_total_shares(&self, data: &Binary) -> StdResult<Binary> {
+        let request = QueryTotalSharesRequest::try_from(data.clone())?;
+        let shares = self.vault_pool(request.pool_id)?.total_shares()?;
+        to_binary(&QueryTotalSharesResponse {
+            total_shares: Some(ProtoCoin {
+                denom: shares.denom,
+                amount: shares.amount.to_string(),
+            }),
+        })
+    }
+
+    fn query_total_pool_liquidity(&self, data: &Binary) -> StdResult<Binary> {
+        let request = QueryTotalPoolLiquidityRequest::try_from(data.clone())?;
+        let liquidity = self
+            .vault_pool(request.pool_id)?
+            .assets()?
+            .into_iter()
+            .map(|asset| ProtoCoin {
+                denom: asset.denom,
+                amount: asset.balance.to_string(),
+            })
+            .collect();
+        to_binary(&QueryTotalPoolLiquidityResponse { liquidity })
+    }
+
+    fn query_twap(&self, data: &Binary) -> StdResult<Binary> {
+        let request = ArithmeticTwapToNowRequest::try_from(data.clone())?;
+        let key = (request.pool_id, request.base_asset, request.quote_asset);
+        let arithmetic_twap = self
+            .twaps
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| StdError::not_found("twap"))?;
+        to_binary(&ArithmeticTwapToNowResponse { arithmetic_twap })
+    }
+
+    fn query_spot_price(&self, data: &Binary) -> StdResult<Binary> {
+        let request = QuerySpotPriceRequest::try_from(data.clone())?;
+        let key = (
+            request.pool_id,
+            request.base_asset_denom,
+            request.quote_asset_denom,
+        );
+        let spot_price = self
+            .spot_prices
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| StdError::not_found("spot price"))?;
+        to_binary(&QuerySpotPriceResponse { spot_price })
+    }
+
+    fn query_denom_spot_price(&self, data: &Binary) -> StdResult<Binary> {
+        let request = QueryDenomSpotPriceRequest::try_from(data.clone())?;
+        let spot_price = self
+            .fee_token_prices
+            .get(&request.denom)
+            .cloned()
+            .ok_or_else(|| StdError::not_found("fee token"))?;
+        to_binary(&QueryDenomSpotPriceResponse {
+            pool_id: 1,
+            spot_price,
+        })
+    }
+
+    fn query_lock(&self, data: &Binary) -> StdResult<Binary> {
+        let request = LockedRequest::try_from(data.clone())?;
+        let lock = self
+            .locks
+            .iter()
+            .find(|lock| lock.id == request.lock_id)
+            .cloned()
+            .ok_or_else(|| StdError::not_found("lock"))?;
+        to_binary(&LockedResponse { lock: Some(lock) })
+    }
+
+    /// Locks without an end time are the ones that didn't start unlocking.
+    fn query_account_open_locks(&self, data: &Binary) -> StdResult<Binary> {
+        let request = AccountLockedLongerDurationNotUnlockingOnlyRequest::try_from(data.clone())?;
+        let min_seconds = request
+            .duration
+            .as_ref()
+            .map(|d| d.seconds)
+            .unwrap_or_default();
+        let locks = self
+            .locks
+            .iter()
+            .filter(|lock| lock.owner == request.owner && lock.end_time.is_none())
+            .filter(|lock| {
+                lock.duration
+                    .as_ref()
+                    .map(|d| d.seconds)
+                    .unwrap_or_default()
+                    >= min_seconds
+            })
+            .cloned()
+            .collect();
+        to_binary(&AccountLockedLongerDurationNotUnlockingOnlyResponse { locks })
+    }
+
+    fn query_account_locked_coins(&self, data: &Binary) -> StdResult<Binary> {
+        let request = AccountLockedCoinsRequest::try_from(data.clone())?;
+        let mut totals: Vec<ProtoCoin> = vec![];
+        for coin in self
+            .locks
+            .iter()
+            .filter(|lock| lock.owner == request.owner)
+            .flat_map(|lock| lock.coins.iter())
+        {
+            let amount: u128 = coin.amount.parse().unwrap();
+            match totals.iter_mut().find(|c| c.denom == coin.denom) {
+                Some(total) => {
+                    let sum = total.amount.parse::<u128>().unwrap() + amount;
+                    total.amount = sum.to_string();
+                }
+                None => totals.push(coin.clone()),
+            }
+        }
+        to_binary(&AccountLockedCoinsResponse { coins: totals })
+    }
+}
+
+impl Querier for OsmosisQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+/// Same as `mock_dependencies_with_balance`, but backed by an `OsmosisQuerier`.
+/// Pool 1 is a uatom/uosmo balancer pool, as the vault checks its pool at instantiate.
+pub fn mock_osmosis_dependencies(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, OsmosisQuerier> {
+    let mut querier = OsmosisQuerier::new(&[(MOCK_CONTRACT_ADDR, contract_balance)]);
+    querier.set_pool(mock_balancer_pool(
+        1,
+        &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+        100_000,
+    ));
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier,
+        custom_query_type: PhantomData,
+    }
+}
+
+/// Drop-in for `cosmwasm_std::testing::mock_dependencies` with the default pool set.
+pub fn mock_dependencies() -> OwnedDeps<MockStorage, MockApi, OsmosisQuerier> {
+    mock_osmosis_dependencies(&[])
+}
+
+/// Drop-in for `cosmwasm_std::testing::mock_dependencies_with_balance`.
+pub fn mock_dependencies_with_balance(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, OsmosisQuerier> {
+    mock_osmosis_dependencies(contract_balance)
+}
+
+/// Instantiates a vault on the default uatom/uosmo pool 1, without fee nor whitelisted
+/// rewards and with cw20 shares. Tests override fields with struct update syntax.
+pub fn mock_instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        name: "Auto Gen".to_string(),
+        symbol: "AUTO".to_string(),
+        decimals: 6,
+        id: 1,
+        denom_1: Some("uatom".to_string()),
+        denom_2: Some("uosmo".to_string()),
+        white_list_denoms: vec![],
+        fee: Decimal::zero(),
+        fee_collector_address: "collector".to_string(),
+        superfluid_validator: None,
+        share_subdenom: None,
+        initial_balances: vec![],
+        mint: None,
+        marketing: None,
+    }
+}
+
+/// Credits `amount` vault tokens to `address` the way a deposit does, but without the LP
+/// behind them. Tests seed share holders with it, the vault itself never mints without LP.
+pub fn mock_shares(deps: DepsMut, address: &str, amount: Uint128) {
+    let mut config = TOKEN_INFO.load(deps.storage).unwrap();
+    let recipient = Addr::unchecked(address);
+    mint_shares(deps.storage, &mock_env(), &mut config, &recipient, amount).unwrap();
+    TOKEN_INFO.save(deps.storage, &config).unwrap();
+}
+
+/// Builds a 50/50 balancer pool holding `assets`, with `total_shares` LP shares issued.
+pub fn mock_balancer_pool(id: u64, assets: &[(&str, u128)], total_shares: u128) -> Pool {
+    Pool {
+        address: format!("pool{}", id),
+        id,
+        pool_params: Some(PoolParams {
+            swap_fee: "0.002".to_string(),
+            exit_fee: "0".to_string(),
+            smooth_weight_change_params: None,
+        }),
+        future_pool_governor: "".to_string(),
+        total_shares: Some(ProtoCoin {
+            denom: format!("gamm/pool/{}", id),
+            amount: total_shares.to_string(),
+        }),
+        pool_assets: assets
+            .iter()
+            .map(|(denom, amount)| PoolAsset {
+                token: Some(ProtoCoin {
+                    denom: denom.to_string(),
+                    amount: amount.to_string(),
+                }),
+                weight: "1073741824".to_string(),
+            })
+            .collect(),
+        total_weight: (1073741824u64 * assets.len() as u64).to_string(),
+    }
+}
+
+/// Builds a pool swapping `denom_in` for `denom_out` without a swap fee, for reward
+/// routes priced at their TWAP alone.
+pub fn mock_route_pool(id: u64, denom_in: &str, denom_out: &str) -> Pool {
+    let mut pool = mock_balancer_pool(
+        id,
+        &[(denom_in, 1_000_000), (denom_out, 1_000_000)],
+        100_000,
+    );
+    if let Some(params) = pool.pool_params.as_mut() {
+        params.swap_fee = "0".to_string();
+    }
+    pool
+}
+
+/// Builds a stableswap pool holding `assets`, each with its scaling factor.
+pub fn mock_stableswap_pool(
+    id: u64,
+    assets: &[(&str, u128, u64)],
+    total_shares: u128,
+) -> StableSwapPool {
+    StableSwapPool {
+        address: format!("pool{}", id),
+        id,
+        pool_params: Some(StableSwapPoolParams {
+            swap_fee: "0.001".to_string(),
+            exit_fee: "0".to_string(),
+        }),
+        future_pool_governor: "".to_string(),
+        total_shares: Some(ProtoCoin {
+            denom: format!("gamm/pool/{}", id),
+            amount: total_shares.to_string(),
+        }),
+        pool_liquidity: assets
+            .iter()
+            .map(|(denom, amount, _)| ProtoCoin {
+                denom: denom.to_string(),
+                amount: amount.to_string(),
+            })
+            .collect(),
+        scaling_factor: assets.iter().map(|(_, _, factor)| *factor).collect(),
+        scaling_factor_controller: "".to_string(),
+    }
+}
+
+/// A one day compound epoch, `current_epoch` of which started at `start_seconds`.
+pub fn mock_day_epoch(current_epoch: i64, start_seconds: i64) -> EpochInfo {
+    EpochInfo {
+        identifier: COMPOUND_EPOCH_IDENTIFIER.to_string(),
+        start_time: Some(osmosis_std::shim::Timestamp {
+            seconds: start_seconds - (current_epoch - 1) * 86_400,
+            nanos: 0,
+        }),
+        duration: Some(osmosis_std::shim::Duration {
+            seconds: 86_400,
+            nanos: 0,
+        }),
+        current_epoch,
+        current_epoch_start_time: Some(osmosis_std::shim::Timestamp {
+            seconds: start_seconds,
+            nanos: 0,
+        }),
+        epoch_counting_started: true,
+        current_epoch_start_height: 1,
+    }
+}
+
+/// Builds a lock of `amount` `denom` owned by the mock contract.
+pub fn mock_lock(id: u64, denom: &str, amount: u128) -> PeriodLock {
+    PeriodLock {
+        id,
+        owner: MOCK_CONTRACT_ADDR.to_string(),
+        duration: Some(osmosis_std::shim::Duration {
+            seconds: 1_209_600,
+            nanos: 0,
+        }),
+        end_time: None,
+        coins: vec![ProtoCoin {
+            denom: denom.to_string(),
+            amount: amount.to_string(),
+        }],
+    }
+}
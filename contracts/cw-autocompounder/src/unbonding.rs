@@ -0,0 +1,797 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use cosmwasm_std::{
+    Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError,
+    StdResult, SubMsg, Uint128,
+};
+use cw20::{ClaimExit, PendingUnbondsResponse};
+use cw_controllers::ClaimsResponse;
+use cw_utils::Expiration;
+use osmosis_std::shim::Duration;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::gamm::v1beta1::{
+    MsgExitPool, MsgExitPoolResponse, MsgExitSwapShareAmountIn, MsgExitSwapShareAmountInResponse,
+};
+use osmosis_std::types::osmosis::lockup::{LockupQuerier, PeriodLock};
+
+use crate::error::ContractError;
+use crate::locks::{record_unlock, unlock_msgs};
+use crate::shares::burn_shares;
+use crate::state::{
+    PendingClaim, ReleasedLp, CLAIMS, PENDING_CLAIM, PENDING_UNBONDS, POOL_INFO, RELEASED_LP,
+    STATE, TOKEN_INFO,
+};
+use crate::vault::{lp_for_shares, query_vault_lp};
+
+pub const EXIT_POOL_REPLY_ID: u64 = 4;
+pub const EXIT_SWAP_SHARE_REPLY_ID: u64 = 10;
+
+/// Burns `shares` vault tokens of the sender and starts unlocking the LP shares they
+/// are worth. The sender gets a claim on that LP, released when the last unlock ends.
+pub fn execute_request_unbond(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    shares: Uint128,
+) -> Result<Response, ContractError> {
+    if shares.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+
+    let vault_lp = query_vault_lp(deps.as_ref(), &env)?;
+    let mut config = TOKEN_INFO.load(deps.storage)?;
+    let amount = lp_for_shares(shares, vault_lp.total(), config.total_supply);
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+
+    // unlock from the locks first, whatever they can't cover is reserved from the liquid LP
+    let state = STATE.load(deps.storage)?;
+    let mut locks = query_open_locks(deps.as_ref(), &env, &vault_lp.denom)?
+        .into_iter()
+        .map(|lock| Ok((lock_amount(&lock, &vault_lp.denom)?, lock)))
+        .collect::<StdResult<Vec<_>>>()?;
+    if state.superfluid_validator.is_some() {
+        locks = pick_whole_locks(locks, amount);
+    }
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut released = RELEASED_LP.may_load(deps.storage)?.unwrap_or_default();
+    let mut remaining = amount;
+    let mut unlock_seconds = 0u64;
+    for (locked, lock) in locks {
+        if remaining.is_zero() {
+            break;
+        }
+        let unlock = locked.min(remaining);
+        let duration = lock.duration.map(|d| d.seconds as u64).unwrap_or_default();
+        // superfluid locks unlock as a whole, what the claim doesn't need turns liquid
+        let coins = match state.superfluid_validator {
+            Some(_) => {
+                if locked > unlock {
+                    released.push(ReleasedLp {
+                        amount: locked - unlock,
+                        release_at: env.block.time.plus_seconds(duration),
+                    });
+                }
+                vec![]
+            }
+            None => vec![Coin::new(unlock.u128(), &vault_lp.denom)],
+        };
+        record_unlock(deps.storage, lock.id, &coins)?;
+        messages.extend(unlock_msgs(&env, &state, lock.id, coins)?);
+        unlock_seconds = unlock_seconds.max(duration);
+        remaining -= unlock;
+    }
+    if !released.is_empty() {
+        RELEASED_LP.save(deps.storage, &released)?;
+    }
+    if remaining > vault_lp.available_liquid() {
+        return Err(ContractError::InsufficientLiquidity {});
+    }
+
+    messages.extend(burn_shares(deps.storage, &env, &info, &mut config, shares)?);
+    TOKEN_INFO.save(deps.storage, &config)?;
+
+    let release_at = Expiration::AtTime(env.block.time.plus_seconds(unlock_seconds));
+    CLAIMS.create_claim(deps.storage, &info.sender, amount, release_at)?;
+    let pending = PENDING_UNBONDS.may_load(deps.storage)?.unwrap_or_default();
+    PENDING_UNBONDS.save(deps.storage, &(pending + amount))?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "request_unbond")
+        .add_attribute("from", info.sender)
+        .add_attribute("shares", shares)
+        .add_attribute("amount", amount)
+        .add_attribute("release_at", release_at.to_string()))
+}
+
+/// Exits the pool with the LP of every matured claim of the sender, to both pool
+/// assets or to a single one as `exit` asks. The pool assets are paid out once the
+/// exit replies, see `reply_exit_pool`.
+pub fn execute_claim(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    exit: Option<ClaimExit>,
+) -> Result<Response, ContractError> {
+    let pool_info = POOL_INFO.load(deps.storage)?;
+    if let Some(ClaimExit::SingleDenom { denom, .. }) = &exit {
+        if denom != &pool_info.denom_1 && denom != &pool_info.denom_2 {
+            return Err(ContractError::NotPoolAsset {
+                denom: denom.clone(),
+            });
+        }
+    }
+
+    let amount = CLAIMS.claim_tokens(deps.storage, &info.sender, &env.block, None)?;
+    if amount.is_zero() {
+        return Err(ContractError::NothingToClaim {});
+    }
+    let pending = PENDING_UNBONDS.may_load(deps.storage)?.unwrap_or_default();
+    PENDING_UNBONDS.save(
+        deps.storage,
+        &pending.checked_sub(amount).map_err(StdError::from)?,
+    )?;
+
+    let (exit, reply_id, denom_out) = match exit {
+        Some(ClaimExit::SingleDenom { denom, min_out }) => {
+            let exit: CosmosMsg = MsgExitSwapShareAmountIn {
+                sender: env.contract.address.to_string(),
+                pool_id: pool_info.id,
+                token_out_denom: denom.clone(),
+                share_in_amount: amount.to_string(),
+                token_out_min_amount: min_out.max(Uint128::one()).to_string(),
+            }
+            .into();
+            (exit, EXIT_SWAP_SHARE_REPLY_ID, Some(denom))
+        }
+        exit => {
+            let token_out_mins = match exit {
+                Some(ClaimExit::Proportional { token_out_mins }) => token_out_mins,
+                _ => vec![],
+            };
+            let exit: CosmosMsg = MsgExitPool {
+                sender: env.contract.address.to_string(),
+                pool_id: pool_info.id,
+                share_in_amount: amount.to_string(),
+                token_out_mins: token_out_mins
+                    .into_iter()
+                    .map(|coin| ProtoCoin {
+                        denom: coin.denom,
+                        amount: coin.amount.to_string(),
+                    })
+                    .collect(),
+            }
+            .into();
+            (exit, EXIT_POOL_REPLY_ID, None)
+        }
+    };
+    PENDING_CLAIM.save(
+        deps.storage,
+        &PendingClaim {
+            recipient: info.sender.clone(),
+            denom_out,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(exit, reply_id))
+        .add_attribute("action", "claim")
+        .add_attribute("from", info.sender)
+        .add_attribute("amount", amount))
+}
+
+/// Sends the pool assets a claim exited the pool with to its owner.
+pub fn reply_exit_pool(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let claim = PENDING_CLAIM.load(deps.storage)?;
+    PENDING_CLAIM.remove(deps.storage);
+
+    let amount = match (msg.id, claim.denom_out) {
+        (EXIT_SWAP_SHARE_REPLY_ID, Some(denom)) => {
+            let res = MsgExitSwapShareAmountInResponse::try_from(msg.result)?;
+            vec![Coin::new(
+                Uint128::from_str(&res.token_out_amount)?.u128(),
+                denom,
+            )]
+        }
+        _ => MsgExitPoolResponse::try_from(msg.result)?
+            .token_out
+            .into_iter()
+            .map(|coin| {
+                Ok(Coin::new(
+                    Uint128::from_str(&coin.amount)?.u128(),
+                    coin.denom,
+                ))
+            })
+            .collect::<StdResult<Vec<_>>>()?,
+    };
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: claim.recipient.to_string(),
+            amount,
+        })
+        .add_attribute("action", "claim_payout")
+        .add_attribute("to", claim.recipient))
+}
+
+pub fn query_claims(deps: Deps, address: String) -> StdResult<ClaimsResponse> {
+    let address: Addr = deps.api.addr_validate(&address)?;
+    CLAIMS.query_claims(deps, &address)
+}
+
+pub fn query_pending_unbonds(deps: Deps) -> StdResult<PendingUnbondsResponse> {
+    Ok(PendingUnbondsResponse {
+        amount: PENDING_UNBONDS.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
+
+/// Superfluid mode only, takes the LP unbonds released beyond their claims once their
+/// unlock ended, for the compound to lock it again. `held_back` of the available liquid
+/// LP is locked by the compound anyway.
+pub fn take_released_lp(deps: DepsMut, env: &Env, held_back: Uint128) -> StdResult<Uint128> {
+    let released = match RELEASED_LP.may_load(deps.storage)? {
+        Some(released) => released,
+        None => return Ok(Uint128::zero()),
+    };
+    let (matured, unlocking): (Vec<_>, Vec<_>) = released
+        .into_iter()
+        .partition(|lp| lp.release_at <= env.block.time);
+    if unlocking.is_empty() {
+        RELEASED_LP.remove(deps.storage);
+    } else {
+        RELEASED_LP.save(deps.storage, &unlocking)?;
+    }
+    // withdraws may have paid out some of it in the meantime
+    let available = query_vault_lp(deps.as_ref(), env)?
+        .available_liquid()
+        .saturating_sub(held_back);
+    let matured = matured.iter().map(|lp| lp.amount).sum::<Uint128>();
+    Ok(matured.min(available))
+}
+
+/// Superfluid locks only unlock as a whole. Takes the smallest lock covering what is
+/// left of `amount`, or the largest one while none does, so the unbond releases as
+/// little LP beyond its claim as it can.
+fn pick_whole_locks(
+    mut locks: Vec<(Uint128, PeriodLock)>,
+    amount: Uint128,
+) -> Vec<(Uint128, PeriodLock)> {
+    locks.sort_by_key(|(locked, lock)| (*locked, lock.id));
+    let mut picked = vec![];
+    let mut remaining = amount;
+    while !remaining.is_zero() && !locks.is_empty() {
+        let index = locks
+            .iter()
+            .position(|(locked, _)| *locked >= remaining)
+            .unwrap_or(locks.len() - 1);
+        let (locked, lock) = locks.remove(index);
+        remaining = remaining.saturating_sub(locked);
+        picked.push((locked, lock));
+    }
+    picked
+}
+
+/// Locks of the vault holding `denom` that are not unlocking yet.
+fn query_open_locks(deps: Deps, env: &Env, denom: &str) -> StdResult<Vec<PeriodLock>> {
+    let locks = LockupQuerier::new(&deps.querier)
+        .account_locked_longer_duration_not_unlocking_only(
+            env.contract.address.to_string(),
+            Some(Duration {
+                seconds: 0,
+                nanos: 0,
+            }),
+        )?
+        .locks;
+    Ok(locks
+        .into_iter()
+        .filter(|lock| lock.coins.iter().any(|coin| coin.denom == denom))
+        .collect())
+}
+
+fn lock_amount(lock: &PeriodLock, denom: &str) -> StdResult<Uint128> {
+    lock.coins
+        .iter()
+        .filter(|coin| coin.denom == denom)
+        .map(|coin| Uint128::from_str(&coin.amount))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coin, SubMsgResponse, SubMsgResult};
+    use cw_controllers::Claim;
+    use osmosis_std::types::osmosis::gamm::v1beta1::MsgJoinSwapExternAmountInResponse;
+    use osmosis_std::types::osmosis::lockup::MsgBeginUnlocking;
+    use osmosis_std::types::osmosis::superfluid::{
+        MsgLockAndSuperfluidDelegate, MsgSuperfluidUnbondLock, MsgSuperfluidUndelegate,
+    };
+
+    use crate::compound::JOIN_SWAP_EXTERN_REPLY_ID;
+    use crate::contract::{execute, instantiate, reply};
+    use crate::locks::SUPERFLUID_LOCK_REPLY_ID;
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::testing::{
+        mock_balancer_pool, mock_instantiate_msg, mock_lock, mock_osmosis_dependencies,
+    };
+
+    const LP: &str = "gamm/pool/1";
+
+    fn do_instantiate(deps: DepsMut) {
+        let msg = mock_instantiate_msg();
+        instantiate(deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+    }
+
+    #[test]
+    fn unbond_then_claim() {
+        // alice deposits 10_000 LP and the vault locks 8_000 of it
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, LP)]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+            100_000,
+        ));
+        do_instantiate(deps.as_mut());
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[coin(10_000, LP)]),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(2_000, LP)]);
+        deps.querier
+            .set_locks(vec![mock_lock(7, LP, 5_000), mock_lock(8, LP, 3_000)]);
+
+        // 6_000 shares are 6_000 LP, taken from both locks
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::RequestUnbond {
+                shares: Uint128::new(6_000),
+            },
+        )
+        .unwrap();
+        let unlock = |id: u64, amount: u128| -> SubMsg {
+            SubMsg::new(MsgBeginUnlocking {
+                owner: MOCK_CONTRACT_ADDR.to_string(),
+                id,
+                coins: vec![ProtoCoin {
+                    denom: LP.to_string(),
+                    amount: amount.to_string(),
+                }],
+            })
+        };
+        assert_eq!(res.messages, vec![unlock(7, 5_000), unlock(8, 1_000)]);
+        let release_at = Expiration::AtTime(mock_env().block.time.plus_seconds(1_209_600));
+        assert_eq!(
+            query_claims(deps.as_ref(), "alice".to_string()).unwrap(),
+            ClaimsResponse {
+                claims: vec![Claim::new(6_000, release_at)],
+            }
+        );
+        assert_eq!(
+            query_pending_unbonds(deps.as_ref()).unwrap().amount,
+            Uint128::new(6_000)
+        );
+        // the shares are burned and the LP owed is out of the vault
+        let vault_lp = query_vault_lp(deps.as_ref(), &mock_env()).unwrap();
+        assert_eq!(vault_lp.total(), Uint128::new(4_000));
+        assert_eq!(
+            TOKEN_INFO.load(&deps.storage).unwrap().total_supply,
+            Uint128::new(4_000)
+        );
+
+        // nothing to claim before the unlock ends
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Claim { exit: None },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NothingToClaim {});
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(1_209_600);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Claim { exit: None },
+        )
+        .unwrap();
+        let exit: CosmosMsg = MsgExitPool {
+            sender: MOCK_CONTRACT_ADDR.to_string(),
+            pool_id: 1,
+            share_in_amount: "6000".to_string(),
+            token_out_mins: vec![],
+        }
+        .into();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(exit, EXIT_POOL_REPLY_ID)]
+        );
+        assert_eq!(
+            query_pending_unbonds(deps.as_ref()).unwrap().amount,
+            Uint128::zero()
+        );
+
+        let exited = MsgExitPoolResponse {
+            token_out: vec![
+                ProtoCoin {
+                    denom: "uatom".to_string(),
+                    amount: "60000".to_string(),
+                },
+                ProtoCoin {
+                    denom: "uosmo".to_string(),
+                    amount: "60000".to_string(),
+                },
+            ],
+        };
+        let msg = Reply {
+            id: EXIT_POOL_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(exited.into()),
+            }),
+        };
+        let res = reply(deps.as_mut(), env, msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: vec![coin(60_000, "uatom"), coin(60_000, "uosmo")],
+            })]
+        );
+    }
+
+    #[test]
+    fn claims_reserve_liquid_lp() {
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, LP)]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+            100_000,
+        ));
+        do_instantiate(deps.as_mut());
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[coin(10_000, LP)]),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+
+        // without locks the claim is released right away
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::RequestUnbond {
+                shares: Uint128::new(5_000),
+            },
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            query_claims(deps.as_ref(), "alice".to_string())
+                .unwrap()
+                .claims,
+            vec![Claim::new(5_000, Expiration::AtTime(mock_env().block.time))]
+        );
+
+        // the LP reserved for the claim can't be withdrawn, even once the owner locked the rest
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(6_000, LP)]);
+        deps.querier.set_locks(vec![mock_lock(7, LP, 4_000)]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Withdraw {
+                shares: Uint128::new(4_000),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InsufficientLiquidity {});
+    }
+
+    #[test]
+    fn claim_to_single_denom_or_with_minimums() {
+        // liquid LP only, so the claims mature right away
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, LP)]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+            100_000,
+        ));
+        do_instantiate(deps.as_mut());
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[coin(10_000, LP)]),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+        let request_unbond = |deps: DepsMut, shares: u128| {
+            let msg = ExecuteMsg::RequestUnbond {
+                shares: Uint128::new(shares),
+            };
+            execute(deps, mock_env(), mock_info("alice", &[]), msg).unwrap();
+        };
+        request_unbond(deps.as_mut(), 6_000);
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Claim {
+                exit: Some(ClaimExit::SingleDenom {
+                    denom: "ujuno".to_string(),
+                    min_out: Uint128::new(100_000),
+                }),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NotPoolAsset {
+                denom: "ujuno".to_string()
+            }
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Claim {
+                exit: Some(ClaimExit::SingleDenom {
+                    denom: "uatom".to_string(),
+                    min_out: Uint128::new(100_000),
+                }),
+            },
+        )
+        .unwrap();
+        let exit: CosmosMsg = MsgExitSwapShareAmountIn {
+            sender: MOCK_CONTRACT_ADDR.to_string(),
+            pool_id: 1,
+            token_out_denom: "uatom".to_string(),
+            share_in_amount: "6000".to_string(),
+            token_out_min_amount: "100000".to_string(),
+        }
+        .into();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(exit, EXIT_SWAP_SHARE_REPLY_ID)]
+        );
+
+        let exited = MsgExitSwapShareAmountInResponse {
+            token_out_amount: "118000".to_string(),
+        };
+        let msg = Reply {
+            id: EXIT_SWAP_SHARE_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(exited.into()),
+            }),
+        };
+        let res = reply(deps.as_mut(), mock_env(), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: vec![coin(118_000, "uatom")],
+            })]
+        );
+        assert!(PENDING_CLAIM.may_load(&deps.storage).unwrap().is_none());
+
+        // a proportional exit passes the minimums on to the pool
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(4_000, LP)]);
+        request_unbond(deps.as_mut(), 1_000);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Claim {
+                exit: Some(ClaimExit::Proportional {
+                    token_out_mins: vec![coin(9_900, "uatom"), coin(9_900, "uosmo")],
+                }),
+            },
+        )
+        .unwrap();
+        let exit: MsgExitPool = match &res.messages[0].msg {
+            CosmosMsg::Stargate { value, .. } => MsgExitPool::try_from(value.clone()).unwrap(),
+            msg => panic!("unexpected message {:?}", msg),
+        };
+        assert_eq!(exit.share_in_amount, "1000");
+        assert_eq!(
+            exit.token_out_mins,
+            vec![
+                ProtoCoin {
+                    denom: "uatom".to_string(),
+                    amount: "9900".to_string(),
+                },
+                ProtoCoin {
+                    denom: "uosmo".to_string(),
+                    amount: "9900".to_string(),
+                },
+            ]
+        );
+    }
+
+    fn superfluid_vault(deps: DepsMut) {
+        let msg = InstantiateMsg {
+            superfluid_validator: Some("osmovaloper1xyz".to_string()),
+            ..mock_instantiate_msg()
+        };
+        instantiate(deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+    }
+
+    fn superfluid_unlock(id: u64) -> Vec<SubMsg> {
+        vec![
+            SubMsg::new(MsgSuperfluidUndelegate {
+                sender: MOCK_CONTRACT_ADDR.to_string(),
+                lock_id: id,
+            }),
+            SubMsg::new(MsgSuperfluidUnbondLock {
+                sender: MOCK_CONTRACT_ADDR.to_string(),
+                lock_id: id,
+            }),
+        ]
+    }
+
+    #[test]
+    fn superfluid_unbond_takes_the_smallest_lock_covering_it() {
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, LP)]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+            100_000,
+        ));
+        superfluid_vault(deps.as_mut());
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[coin(10_000, LP)]),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(0, LP)]);
+        deps.querier.set_locks(vec![
+            mock_lock(7, LP, 6_000),
+            mock_lock(8, LP, 2_000),
+            mock_lock(9, LP, 2_000),
+        ]);
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::RequestUnbond {
+                shares: Uint128::new(100),
+            },
+        )
+        .unwrap();
+        assert_eq!(res.messages, superfluid_unlock(8));
+        assert_eq!(
+            RELEASED_LP.load(&deps.storage).unwrap(),
+            vec![ReleasedLp {
+                amount: Uint128::new(1_900),
+                release_at: mock_env().block.time.plus_seconds(1_209_600),
+            }]
+        );
+
+        // more than the largest lock takes it and the smallest covering the rest
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::RequestUnbond {
+                shares: Uint128::new(7_000),
+            },
+        )
+        .unwrap();
+        let mut unlocks = superfluid_unlock(7);
+        unlocks.extend(superfluid_unlock(8));
+        assert_eq!(res.messages, unlocks);
+    }
+
+    #[test]
+    fn small_superfluid_unbond_relocks_the_rest() {
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, LP)]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+            100_000,
+        ));
+        superfluid_vault(deps.as_mut());
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[coin(10_000, LP)]),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(0, LP)]);
+        deps.querier.set_locks(vec![mock_lock(7, LP, 10_000)]);
+
+        // the one lock unlocks as a whole for 100 LP, 9_900 of it is released with the claim
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::RequestUnbond {
+                shares: Uint128::new(100),
+            },
+        )
+        .unwrap();
+        assert_eq!(res.messages, superfluid_unlock(7));
+        let release_at = mock_env().block.time.plus_seconds(1_209_600);
+        assert_eq!(
+            RELEASED_LP.load(&deps.storage).unwrap(),
+            vec![ReleasedLp {
+                amount: Uint128::new(9_900),
+                release_at,
+            }]
+        );
+
+        // a compound before the unlock ended has nothing to relock
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(44, LP)]);
+        assert_eq!(
+            take_released_lp(deps.as_mut(), &mock_env(), Uint128::new(44)).unwrap(),
+            Uint128::zero()
+        );
+        assert_eq!(RELEASED_LP.load(&deps.storage).unwrap().len(), 1);
+
+        // once it ended the compound locks the released LP again, over several locks
+        let mut env = mock_env();
+        env.block.time = release_at;
+        deps.querier.set_locks(vec![]);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(1_000, "uosmo")]);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::Compound {},
+        )
+        .unwrap();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(10_044, LP)]);
+        let joined = MsgJoinSwapExternAmountInResponse {
+            share_out_amount: "44".to_string(),
+        };
+        let msg = Reply {
+            id: JOIN_SWAP_EXTERN_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(joined.into()),
+            }),
+        };
+        let res = reply(deps.as_mut(), env, msg).unwrap();
+        let lock = |amount: &str| {
+            let msg = MsgLockAndSuperfluidDelegate {
+                sender: MOCK_CONTRACT_ADDR.to_string(),
+                coins: vec![ProtoCoin {
+                    denom: LP.to_string(),
+                    amount: amount.to_string(),
+                }],
+                val_addr: "osmovaloper1xyz".to_string(),
+            };
+            SubMsg::reply_on_success(msg, SUPERFLUID_LOCK_REPLY_ID)
+        };
+        assert_eq!(res.messages, vec![lock("2486"); 4]);
+        assert!(RELEASED_LP.may_load(&deps.storage).unwrap().is_none());
+    }
+}
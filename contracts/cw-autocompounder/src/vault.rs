@@ -0,0 +1,355 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{
+    coins, Addr, BankMsg, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StdError,
+    StdResult, Uint128,
+};
+use cw_utils::must_pay;
+use osmosis_std::types::osmosis::lockup::LockupQuerier;
+
+use crate::emergency::{exited_assets, withdraw_exited_assets};
+use crate::error::ContractError;
+use crate::roles::assert_depositor;
+use crate::shares::{burn_shares, mint_shares};
+use crate::state::{PENDING_UNBONDS, POOL_INFO, TOKEN_INFO};
+
+/// Vault tokens minted to the contract itself on the first deposit and never redeemable.
+/// Keeps the share price from being inflated by donating LP to an almost empty vault.
+pub const MINIMUM_SHARES: Uint128 = Uint128::new(1_000);
+
+/// LP shares of the vault's pool owned by the vault, split by where they sit.
+pub struct VaultLp {
+    pub denom: String,
+    /// LP shares held in the vault's bank balance
+    pub liquid: Uint128,
+    /// LP shares locked (or unlocking) in the lockup module
+    pub locked: Uint128,
+    /// LP shares owed to unbond claims, no longer backing any vault token
+    pub pending: Uint128,
+}
+
+impl VaultLp {
+    pub fn total(&self) -> Uint128 {
+        (self.liquid + self.locked).saturating_sub(self.pending)
+    }
+
+    /// Liquid LP not reserved for claims. Claims are counted in full, even the
+    /// part still unlocking.
+    pub fn available_liquid(&self) -> Uint128 {
+        self.liquid.saturating_sub(self.pending)
+    }
+}
+
+/// Loads the LP position of the vault, both liquid and locked.
+pub fn query_vault_lp(deps: Deps, env: &Env) -> StdResult<VaultLp> {
+    let denom = POOL_INFO.load(deps.storage)?.lp_denom;
+
+    let liquid = deps
+        .querier
+        .query_balance(&env.contract.address, &denom)?
+        .amount;
+    let locked = LockupQuerier::new(&deps.querier)
+        .account_locked_coins(env.contract.address.to_string())?
+        .coins
+        .into_iter()
+        .filter(|coin| coin.denom == denom)
+        .map(|coin| Uint128::from_str(&coin.amount))
+        .sum::<StdResult<Uint128>>()?;
+
+    let pending = PENDING_UNBONDS.may_load(deps.storage)?.unwrap_or_default();
+
+    Ok(VaultLp {
+        denom,
+        liquid,
+        locked,
+        pending,
+    })
+}
+
+/// Number of vault tokens `amount` LP shares are worth, given the LP already
+/// held by the vault and the current vault token supply.
+pub fn shares_for_lp(
+    amount: Uint128,
+    total_lp: Uint128,
+    total_supply: Uint128,
+) -> Result<Uint128, ContractError> {
+    if total_supply.is_zero() {
+        return Ok(amount);
+    }
+    if total_lp.is_zero() {
+        return Err(ContractError::NoBalancesFound {});
+    }
+    Ok(amount.multiply_ratio(total_supply, total_lp))
+}
+
+/// Amount of LP shares `shares` vault tokens can be redeemed for.
+pub fn lp_for_shares(shares: Uint128, total_lp: Uint128, total_supply: Uint128) -> Uint128 {
+    if total_supply.is_zero() {
+        return Uint128::zero();
+    }
+    shares.multiply_ratio(total_lp, total_supply)
+}
+
+pub fn execute_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    assert_depositor(deps.as_ref(), &info.sender)?;
+    let lp_denom = query_vault_lp(deps.as_ref(), &env)?.denom;
+    let amount = must_pay(&info, &lp_denom)?;
+    let (messages, shares) = issue_shares(deps, &env, &info.sender, amount)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "deposit")
+        .add_attribute("from", info.sender)
+        .add_attribute("amount", amount)
+        .add_attribute("shares", shares))
+}
+
+/// Mints the vault tokens `amount` LP shares, already received by the vault, are
+/// worth to `depositor`. The first deposit also mints the `MINIMUM_SHARES`.
+pub fn issue_shares(
+    deps: DepsMut,
+    env: &Env,
+    depositor: &Addr,
+    amount: Uint128,
+) -> Result<(Vec<CosmosMsg>, Uint128), ContractError> {
+    // the deposit is already part of the vault balance at this point
+    let total_lp = query_vault_lp(deps.as_ref(), env)?
+        .total()
+        .checked_sub(amount)
+        .map_err(StdError::from)?;
+    let mut config = TOKEN_INFO.load(deps.storage)?;
+    let mut shares = shares_for_lp(amount, total_lp, config.total_supply)?;
+    let mut messages = vec![];
+
+    if config.total_supply.is_zero() {
+        if shares <= MINIMUM_SHARES {
+            return Err(ContractError::DepositTooSmall {});
+        }
+        shares -= MINIMUM_SHARES;
+        messages = mint_shares(
+            deps.storage,
+            env,
+            &mut config,
+            &env.contract.address,
+            MINIMUM_SHARES,
+        )?;
+    }
+    if shares.is_zero() {
+        return Err(ContractError::DepositTooSmall {});
+    }
+    messages.extend(mint_shares(
+        deps.storage,
+        env,
+        &mut config,
+        depositor,
+        shares,
+    )?);
+    TOKEN_INFO.save(deps.storage, &config)?;
+    Ok((messages, shares))
+}
+
+pub fn execute_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    shares: Uint128,
+) -> Result<Response, ContractError> {
+    if shares.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    if let Some(assets) = exited_assets(deps.storage)? {
+        return withdraw_exited_assets(deps, env, info, shares, assets);
+    }
+
+    let vault_lp = query_vault_lp(deps.as_ref(), &env)?;
+    let mut config = TOKEN_INFO.load(deps.storage)?;
+    let amount = lp_for_shares(shares, vault_lp.total(), config.total_supply);
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    if amount > vault_lp.available_liquid() {
+        return Err(ContractError::InsufficientLiquidity {});
+    }
+
+    let messages = burn_shares(deps.storage, &env, &info, &mut config, shares)?;
+    TOKEN_INFO.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: coins(amount.u128(), vault_lp.denom),
+        })
+        .add_attribute("action", "withdraw")
+        .add_attribute("from", info.sender)
+        .add_attribute("shares", shares)
+        .add_attribute("amount", amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coin, CosmosMsg, SubMsg};
+    use cw_utils::PaymentError;
+
+    use crate::contract::{execute, instantiate, query_balance, query_token_info};
+    use crate::msg::ExecuteMsg;
+    use crate::testing::{
+        mock_balancer_pool, mock_instantiate_msg, mock_lock, mock_osmosis_dependencies, mock_shares,
+    };
+
+    const LP: &str = "gamm/pool/1";
+
+    fn do_instantiate(deps: DepsMut) {
+        let msg = mock_instantiate_msg();
+        instantiate(deps, mock_env(), mock_info("creator", &[]), msg).unwrap();
+    }
+
+    fn get_balance(deps: Deps, address: &str) -> Uint128 {
+        query_balance(deps, address.to_string()).unwrap().balance
+    }
+
+    #[test]
+    fn share_math() {
+        // empty vault prices shares 1:1
+        assert_eq!(
+            shares_for_lp(Uint128::new(500), Uint128::zero(), Uint128::zero()).unwrap(),
+            Uint128::new(500)
+        );
+        // vault that doubled its LP through compounding halves the shares minted
+        assert_eq!(
+            shares_for_lp(Uint128::new(500), Uint128::new(2000), Uint128::new(1000)).unwrap(),
+            Uint128::new(250)
+        );
+        // supply without any backing LP can't be priced
+        assert_eq!(
+            shares_for_lp(Uint128::new(500), Uint128::zero(), Uint128::new(1000)).unwrap_err(),
+            ContractError::NoBalancesFound {}
+        );
+
+        assert_eq!(
+            lp_for_shares(Uint128::new(250), Uint128::new(2000), Uint128::new(1000)),
+            Uint128::new(500)
+        );
+        assert_eq!(
+            lp_for_shares(Uint128::new(250), Uint128::new(2000), Uint128::zero()),
+            Uint128::zero()
+        );
+    }
+
+    #[test]
+    fn first_deposit_locks_minimum_shares() {
+        let mut deps = mock_osmosis_dependencies(&[coin(100_000, LP)]);
+        deps.querier
+            .set_pool(mock_balancer_pool(1, &[("uatom", 1_000), ("uosmo", 1_000)], 1_000_000));
+        do_instantiate(deps.as_mut());
+
+        let info = mock_info("alice", &[coin(100_000, LP)]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+        assert_eq!(res.messages, vec![]);
+
+        assert_eq!(get_balance(deps.as_ref(), "alice"), Uint128::new(99_000));
+        assert_eq!(get_balance(deps.as_ref(), MOCK_CONTRACT_ADDR), MINIMUM_SHARES);
+        assert_eq!(
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            Uint128::new(100_000)
+        );
+
+        // a first deposit that can't cover the minimum shares is rejected
+        let mut deps = mock_osmosis_dependencies(&[coin(1_000, LP)]);
+        deps.querier
+            .set_pool(mock_balancer_pool(1, &[("uatom", 1_000), ("uosmo", 1_000)], 1_000_000));
+        do_instantiate(deps.as_mut());
+        let info = mock_info("alice", &[coin(1_000, LP)]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap_err();
+        assert_eq!(err, ContractError::DepositTooSmall {});
+    }
+
+    #[test]
+    fn deposit_prices_against_locked_and_liquid_lp() {
+        // 1_000 shares outstanding, backed by 500 liquid + 1_500 locked LP,
+        // plus the 400 LP being deposited
+        let mut deps = mock_osmosis_dependencies(&[coin(900, LP)]);
+        deps.querier
+            .set_pool(mock_balancer_pool(1, &[("uatom", 1_000), ("uosmo", 1_000)], 1_000_000));
+        deps.querier.set_locks(vec![mock_lock(7, LP, 1_500)]);
+        do_instantiate(deps.as_mut());
+        mock_shares(deps.as_mut(), "bob", Uint128::new(1_000));
+
+        let info = mock_info("alice", &[coin(400, LP)]);
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap();
+        assert_eq!(get_balance(deps.as_ref(), "alice"), Uint128::new(200));
+        assert_eq!(
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            Uint128::new(1_200)
+        );
+
+        // dust that rounds down to zero shares is rejected
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, vec![coin(901, LP)]);
+        let info = mock_info("alice", &[coin(1, LP)]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap_err();
+        assert_eq!(err, ContractError::DepositTooSmall {});
+
+        // only the pool LP is accepted
+        let info = mock_info("alice", &[coin(400, "uosmo")]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {}).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Payment(PaymentError::MissingDenom(LP.to_string()))
+        );
+    }
+
+    #[test]
+    fn withdraw_pays_out_liquid_lp() {
+        let mut deps = mock_osmosis_dependencies(&[coin(1_000, LP)]);
+        deps.querier
+            .set_pool(mock_balancer_pool(1, &[("uatom", 1_000), ("uosmo", 1_000)], 1_000_000));
+        deps.querier.set_locks(vec![mock_lock(7, LP, 1_000)]);
+        do_instantiate(deps.as_mut());
+        mock_shares(deps.as_mut(), "bob", Uint128::new(1_000));
+
+        let msg = ExecuteMsg::Withdraw {
+            shares: Uint128::new(300),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "bob".to_string(),
+                amount: coins(600, LP),
+            }))]
+        );
+        assert_eq!(get_balance(deps.as_ref(), "bob"), Uint128::new(700));
+        assert_eq!(
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            Uint128::new(700)
+        );
+
+        // the rest of bob's position needs more LP than the vault has liquid
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, vec![coin(400, LP)]);
+        let msg = ExecuteMsg::Withdraw {
+            shares: Uint128::new(700),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientLiquidity {});
+
+        // can't withdraw more than owned
+        let msg = ExecuteMsg::Withdraw {
+            shares: Uint128::new(100),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("carl", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::Overflow { .. })));
+
+        let msg = ExecuteMsg::Withdraw {
+            shares: Uint128::zero(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidZeroAmount {});
+    }
+}
@@ -0,0 +1,441 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use cosmwasm_std::{
+    BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError,
+    StdResult, SubMsg, Uint128,
+};
+use cw_utils::one_coin;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::gamm::v1beta1::{
+    GammQuerier, MsgJoinPool, MsgJoinPoolResponse, MsgJoinSwapExternAmountIn,
+    MsgJoinSwapExternAmountInResponse, MsgSwapExactAmountIn, MsgSwapExactAmountInResponse,
+    SwapAmountInRoute,
+};
+
+use crate::compound::single_sided_shares_min;
+use crate::error::ContractError;
+use crate::pool::{parse_amount, query_pool, PoolAsset};
+use crate::roles::assert_depositor;
+use crate::state::{ZapState, POOL_INFO, STATE, ZAP};
+use crate::swap::min_out_amount;
+use crate::vault::issue_shares;
+
+pub const DEPOSIT_SINGLE_REPLY_ID: u64 = 7;
+pub const ZAP_SWAP_REPLY_ID: u64 = 8;
+pub const ZAP_JOIN_REPLY_ID: u64 = 9;
+
+/// Joins the pool single sided with the pool asset sent along with the message. The
+/// vault tokens are minted once the join replies with the LP shares it got.
+pub fn execute_deposit_single(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    assert_depositor(deps.as_ref(), &info.sender)?;
+    let pool_id = POOL_INFO.load(deps.storage)?.id;
+    let token_in = pool_coin(deps.as_ref(), &info)?;
+
+    let share_out_min = single_sided_min(deps.as_ref(), pool_id, &token_in)?;
+    if share_out_min.is_zero() {
+        return Err(ContractError::DepositTooSmall {});
+    }
+    let join: CosmosMsg = MsgJoinSwapExternAmountIn {
+        sender: env.contract.address.to_string(),
+        pool_id,
+        token_in: Some(ProtoCoin {
+            denom: token_in.denom.clone(),
+            amount: token_in.amount.to_string(),
+        }),
+        share_out_min_amount: share_out_min.to_string(),
+    }
+    .into();
+    ZAP.save(
+        deps.storage,
+        &ZapState {
+            depositor: info.sender.clone(),
+            coins: vec![token_in.clone()],
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(join, DEPOSIT_SINGLE_REPLY_ID))
+        .add_attribute("action", "deposit_single")
+        .add_attribute("from", info.sender)
+        .add_attribute("token_in", token_in.to_string()))
+}
+
+/// Swaps half of the pool asset sent along with the message into the other pool asset,
+/// then joins the pool with both. What the balanced join leaves is refunded.
+pub fn execute_zap(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    assert_depositor(deps.as_ref(), &info.sender)?;
+    let pool_info = POOL_INFO.load(deps.storage)?;
+    let token_in = pool_coin(deps.as_ref(), &info)?;
+    let token_out_denom = if token_in.denom == pool_info.denom_1 {
+        pool_info.denom_2
+    } else {
+        pool_info.denom_1
+    };
+
+    let half = token_in.amount.multiply_ratio(1u128, 2u128);
+    if half.is_zero() {
+        return Err(ContractError::DepositTooSmall {});
+    }
+    let swap_in = Coin::new(half.u128(), &token_in.denom);
+    let routes = vec![SwapAmountInRoute {
+        pool_id: pool_info.id,
+        token_out_denom: token_out_denom.clone(),
+    }];
+    let state = STATE.load(deps.storage)?;
+    let min_out = min_out_amount(
+        deps.as_ref(),
+        &env,
+        &swap_in,
+        &routes,
+        state.max_slippage_bps,
+    )?;
+    let swap: CosmosMsg = MsgSwapExactAmountIn {
+        sender: env.contract.address.to_string(),
+        routes,
+        token_in: Some(ProtoCoin {
+            denom: swap_in.denom,
+            amount: half.to_string(),
+        }),
+        token_out_min_amount: min_out.to_string(),
+    }
+    .into();
+    ZAP.save(
+        deps.storage,
+        &ZapState {
+            depositor: info.sender.clone(),
+            coins: vec![Coin::new((token_in.amount - half).u128(), &token_in.denom)],
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(swap, ZAP_SWAP_REPLY_ID))
+        .add_attribute("action", "zap")
+        .add_attribute("from", info.sender)
+        .add_attribute("token_in", token_in.to_string()))
+}
+
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        DEPOSIT_SINGLE_REPLY_ID => {
+            let res = MsgJoinSwapExternAmountInResponse::try_from(msg.result)?;
+            reply_deposit(deps, env, &res.share_out_amount, vec![])
+        }
+        ZAP_SWAP_REPLY_ID => {
+            let res = MsgSwapExactAmountInResponse::try_from(msg.result)?;
+            reply_zap_swap(deps, env, res.token_out_amount)
+        }
+        ZAP_JOIN_REPLY_ID => {
+            let res = MsgJoinPoolResponse::try_from(msg.result)?;
+            reply_deposit(deps, env, &res.share_out_amount, res.token_in)
+        }
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+/// Joins the pool with the rest of the zapped asset and the swap output, as far as
+/// they match the pool ratio.
+fn reply_zap_swap(
+    deps: DepsMut,
+    env: Env,
+    token_out_amount: String,
+) -> Result<Response, ContractError> {
+    let mut zap = ZAP.load(deps.storage)?;
+    let pool_info = POOL_INFO.load(deps.storage)?;
+    let token_out_denom = if zap.coins[0].denom == pool_info.denom_1 {
+        pool_info.denom_2
+    } else {
+        pool_info.denom_1
+    };
+    let token_out_amount = Uint128::from_str(&token_out_amount)?;
+    zap.coins
+        .push(Coin::new(token_out_amount.u128(), token_out_denom));
+
+    let (share_out_amount, _) =
+        query_pool(deps.as_ref(), pool_info.id)?.simulate_join(&zap.coins)?;
+    if share_out_amount.is_zero() {
+        return Err(ContractError::DepositTooSmall {});
+    }
+
+    let join: CosmosMsg = MsgJoinPool {
+        sender: env.contract.address.to_string(),
+        pool_id: pool_info.id,
+        share_out_amount: share_out_amount.to_string(),
+        token_in_maxs: zap
+            .coins
+            .iter()
+            .map(|coin| ProtoCoin {
+                denom: coin.denom.clone(),
+                amount: coin.amount.to_string(),
+            })
+            .collect(),
+    }
+    .into();
+    ZAP.save(deps.storage, &zap)?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(join, ZAP_JOIN_REPLY_ID))
+        .add_attribute("swapped", token_out_amount))
+}
+
+/// Mints the vault tokens for the LP shares a deposit got, and refunds whatever part
+/// of the deposited assets the join didn't take. A single sided join takes it all.
+fn reply_deposit(
+    deps: DepsMut,
+    env: Env,
+    share_out_amount: &str,
+    token_in: Vec<ProtoCoin>,
+) -> Result<Response, ContractError> {
+    let zap = ZAP.load(deps.storage)?;
+    ZAP.remove(deps.storage);
+    let amount = Uint128::from_str(share_out_amount)?;
+    let (messages, shares) = issue_shares(deps, &env, &zap.depositor, amount)?;
+
+    let mut refund = vec![];
+    if !token_in.is_empty() {
+        for coin in zap.coins {
+            let used = token_in
+                .iter()
+                .find(|used| used.denom == coin.denom)
+                .map(|used| parse_amount(Some(&used.amount)))
+                .transpose()?
+                .unwrap_or_default();
+            let left = coin.amount.saturating_sub(used);
+            if !left.is_zero() {
+                refund.push(Coin::new(left.u128(), coin.denom));
+            }
+        }
+    }
+
+    let mut res = Response::new()
+        .add_messages(messages)
+        .add_attribute("lp", amount)
+        .add_attribute("shares", shares);
+    if !refund.is_empty() {
+        res = res.add_message(BankMsg::Send {
+            to_address: zap.depositor.to_string(),
+            amount: refund,
+        });
+    }
+    Ok(res)
+}
+
+/// The single coin sent along with the message, which has to be one of the pool assets.
+fn pool_coin(deps: Deps, info: &MessageInfo) -> Result<Coin, ContractError> {
+    let coin = one_coin(info)?;
+    let pool_info = POOL_INFO.load(deps.storage)?;
+    if coin.denom != pool_info.denom_1 && coin.denom != pool_info.denom_2 {
+        return Err(ContractError::NotPoolAsset { denom: coin.denom });
+    }
+    Ok(coin)
+}
+
+/// Lower bound of the LP shares a single sided join of `token_in` mints, from the
+/// pool liquidity and share supply reported by the gamm module.
+fn single_sided_min(deps: Deps, pool_id: u64, token_in: &Coin) -> StdResult<Uint128> {
+    let gamm = GammQuerier::new(&deps.querier);
+    let total_shares = parse_amount(
+        gamm.total_shares(pool_id)?
+            .total_shares
+            .as_ref()
+            .map(|shares| &shares.amount),
+    )?;
+    let balance = gamm
+        .total_pool_liquidity(pool_id)?
+        .liquidity
+        .iter()
+        .find(|coin| coin.denom == token_in.denom)
+        .map(|coin| parse_amount(Some(&coin.amount)))
+        .transpose()?
+        .ok_or_else(|| StdError::not_found("pool liquidity"))?;
+
+    let pool = query_pool(deps, pool_id)?;
+    let weight = pool
+        .assets()?
+        .into_iter()
+        .find(|asset| asset.denom == token_in.denom)
+        .map(|asset| asset.weight)
+        .ok_or_else(|| StdError::not_found("pool asset"))?;
+    let asset = PoolAsset {
+        denom: token_in.denom.clone(),
+        balance,
+        weight,
+    };
+    single_sided_shares_min(&pool, &asset, token_in.amount, total_shares)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coin, coins, Binary, DepsMut, SubMsgResponse, SubMsgResult};
+    use cw_utils::PaymentError;
+
+    use crate::contract::{execute, instantiate, query_balance, reply as contract_reply};
+    use crate::msg::ExecuteMsg;
+    use crate::testing::{mock_balancer_pool, mock_instantiate_msg, mock_osmosis_dependencies};
+
+    const LP: &str = "gamm/pool/1";
+
+    fn do_instantiate(deps: DepsMut) {
+        let msg = mock_instantiate_msg();
+        instantiate(deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+    }
+
+    fn reply_ok(id: u64, data: impl Into<Binary>) -> Reply {
+        Reply {
+            id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(data.into()),
+            }),
+        }
+    }
+
+    fn decode<T: TryFrom<Binary, Error = StdError>>(msg: &SubMsg) -> T {
+        match &msg.msg {
+            CosmosMsg::Stargate { value, .. } => T::try_from(value.clone()).unwrap(),
+            msg => panic!("unexpected message {:?}", msg),
+        }
+    }
+
+    fn balance(deps: Deps, address: &str) -> Uint128 {
+        query_balance(deps, address.to_string()).unwrap().balance
+    }
+
+    #[test]
+    fn deposit_single_joins_and_mints() {
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, "uatom")]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+            10_000_000,
+        ));
+        do_instantiate(deps.as_mut());
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[coin(10_000, "ujuno")]),
+            ExecuteMsg::DepositSingle {},
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NotPoolAsset {
+                denom: "ujuno".to_string()
+            }
+        );
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::DepositSingle {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[coin(10_000, "uatom")]),
+            ExecuteMsg::DepositSingle {},
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, DEPOSIT_SINGLE_REPLY_ID);
+        let join: MsgJoinSwapExternAmountIn = decode(&res.messages[0]);
+        // half the pool value per asset, net of the swap fee on the half swapped away
+        assert_eq!(join.share_out_min_amount, "49455");
+
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(49_500, LP)]);
+        let data = MsgJoinSwapExternAmountInResponse {
+            share_out_amount: "49500".to_string(),
+        };
+        let res = contract_reply(
+            deps.as_mut(),
+            mock_env(),
+            reply_ok(DEPOSIT_SINGLE_REPLY_ID, data),
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(balance(deps.as_ref(), "alice"), Uint128::new(48_500));
+        assert!(ZAP.may_load(&deps.storage).unwrap().is_none());
+    }
+
+    #[test]
+    fn zap_swaps_half_and_refunds_dust() {
+        let mut deps = mock_osmosis_dependencies(&[coin(10_000, "uatom")]);
+        deps.querier.set_pool(mock_balancer_pool(
+            1,
+            &[("uatom", 1_000_000), ("uosmo", 1_000_000)],
+            10_000_000,
+        ));
+        deps.querier.set_twap(1, "uatom", "uosmo", "1");
+        do_instantiate(deps.as_mut());
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[coin(10_000, "uatom")]),
+            ExecuteMsg::Zap {},
+        )
+        .unwrap();
+        assert_eq!(res.messages[0].id, ZAP_SWAP_REPLY_ID);
+        let swap: MsgSwapExactAmountIn = decode(&res.messages[0]);
+        assert_eq!(
+            swap.token_in,
+            Some(ProtoCoin {
+                denom: "uatom".to_string(),
+                amount: "5000".to_string(),
+            })
+        );
+        // 1% under the TWAP value of the swap, less the 0.2% pool fee
+        assert_eq!(swap.token_out_min_amount, "4940");
+
+        let data = MsgSwapExactAmountInResponse {
+            token_out_amount: "4980".to_string(),
+        };
+        let res =
+            contract_reply(deps.as_mut(), mock_env(), reply_ok(ZAP_SWAP_REPLY_ID, data)).unwrap();
+        assert_eq!(res.messages[0].id, ZAP_JOIN_REPLY_ID);
+        let join: MsgJoinPool = decode(&res.messages[0]);
+        // the swap output is the scarcer side
+        assert_eq!(join.share_out_amount, "49800");
+        assert_eq!(join.token_in_maxs.len(), 2);
+
+        deps.querier.update_balance(
+            MOCK_CONTRACT_ADDR,
+            vec![coin(49_800, LP), coin(20, "uatom")],
+        );
+        let data = MsgJoinPoolResponse {
+            share_out_amount: "49800".to_string(),
+            token_in: vec![
+                ProtoCoin {
+                    denom: "uatom".to_string(),
+                    amount: "4980".to_string(),
+                },
+                ProtoCoin {
+                    denom: "uosmo".to_string(),
+                    amount: "4980".to_string(),
+                },
+            ],
+        };
+        let res =
+            contract_reply(deps.as_mut(), mock_env(), reply_ok(ZAP_JOIN_REPLY_ID, data)).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: coins(20, "uatom"),
+            })]
+        );
+        assert_eq!(balance(deps.as_ref(), "alice"), Uint128::new(48_800));
+    }
+}
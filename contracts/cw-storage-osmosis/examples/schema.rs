@@ -5,7 +5,7 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use cw_storage_osmosis::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use cw_storage_osmosis::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 
 fn main() {
   let mut out_dir = current_dir().unwrap();
@@ -16,4 +16,5 @@ fn main() {
   export_schema(&schema_for!(InstantiateMsg), &out_dir);
   export_schema(&schema_for!(ExecuteMsg), &out_dir);
   export_schema(&schema_for!(QueryMsg), &out_dir);
+  export_schema(&schema_for!(MigrateMsg), &out_dir);
 }
\ No newline at end of file
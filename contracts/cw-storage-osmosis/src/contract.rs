@@ -1,20 +1,25 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Addr, Decimal, Order,
+    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Addr, Decimal, Order, Empty,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::Bound;
 use std::ops::Add;
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, ListResponseEntry, ListResponsePool, AllEntriesResponse};
-use crate::state::{Config, CONFIG, ENTRY_SEQ, LIST, Entry, Pool, POOLS, TokenInfo, AnnualInfo};
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, ListResponseEntry, ListResponsePool, AllEntriesResponse, PoolEntriesResponse};
+use crate::state::{Config, CONFIG, ENTRY_SEQ, LIST, USERS, entries, Entry, Pool, POOLS, TokenInfo, AnnualInfo};
 
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:osmo";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// Pagination of `QueryPoolEntries`
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -44,6 +49,36 @@ pub fn instantiate(
         .add_attribute("owner", owner))
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    // Only this contract can be migrated to a newer version of it.
+    let version = get_contract_version(deps.storage)?;
+    if version.contract != CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate { contract: version.contract });
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // Before 0.3.0 the entries of each user were stored as a single vector in `LIST`,
+    // they are moved to `entries()` one by one.
+    let users = LIST
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut migrated = 0u64;
+    for (user, user_v) in users {
+        for entry in user_v {
+            entries().save(deps.storage, (&user, entry.pool_id), &entry)?;
+            migrated += 1;
+        }
+        USERS.save(deps.storage, &user, &Empty {})?;
+        LIST.remove(deps.storage, &user);
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("version", CONTRACT_VERSION)
+        .add_attribute("migrated_entries", migrated.to_string()))
+}
+
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
@@ -62,6 +97,7 @@ pub fn execute(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_create_new_entry(deps: DepsMut, info: MessageInfo, user: Addr, pool_id: u64, lp_token_amount: Decimal, token_1_amount: Decimal, token_2_amount: Decimal, pool_addr: String) -> Result<Response, ContractError> {
     // Before creating the new entry, the function checks if the message sender is 
     // the owner of the contract.
@@ -86,22 +122,14 @@ pub fn execute_create_new_entry(deps: DepsMut, info: MessageInfo, user: Addr, po
         pool_addr,
     };
 
-    // Before creating the new entry, the function checks if the user is in storage.
-    if LIST.has(deps.storage, &user){
-        let mut user_v = LIST.load(deps.storage, &user)?;
-        if user_v.iter().find(|e| e.pool_id == new_entry.pool_id).is_none() {
-            user_v.push(new_entry);
-            LIST.save(deps.storage, &user, &user_v)?;
-        } else {
-            return Err(ContractError::UserEntryDuplicate { pool_id: pool_id.to_string() });
-        }
-    } else{
-        let user_v = vec![new_entry];
-        LIST.save(deps.storage, &user, &user_v)?;
+    // Before creating the new entry, the function checks if the user already has one in this pool.
+    if entries().may_load(deps.storage, (&user, pool_id))?.is_some() {
+        return Err(ContractError::UserEntryDuplicate { pool_id: pool_id.to_string() });
     }
+    entries().save(deps.storage, (&user, pool_id), &new_entry)?;
+    USERS.save(deps.storage, &user, &Empty {})?;
 
-    // The function finally saves the new entry to the `LIST` with the matching `user` and returns a `Response`
-    // with the relevant attributes. 
+    // The function returns a `Response` with the relevant attributes.
     Ok(Response::new().add_attribute("method", "execute_add_new_entry")
         .add_attribute("new_entry_id", id.to_string()))
 }
@@ -115,24 +143,16 @@ pub fn execute_update_entry(deps: DepsMut, info: MessageInfo, user: Addr, pool_i
         return Err(ContractError::Unauthorized {});
     }
     // The function is used to update amount of coins stored in specified pool
-    // The entries that belong to the matching `user` are loaded from the `LIST`.
-    let mut user_v = LIST.load(deps.storage, &user)?;
-    if user_v.iter().find(|e| e.pool_id == pool_id).is_none() {
-        return Err(ContractError::EntryNotExists { pool_id: pool_id.to_string() });
-    }
-    
-    for i in 0..user_v.len(){
-        if user_v[i].pool_id == pool_id {
-            user_v[i].lp_token_amount = lp_token_amount;
-            user_v[i].token_1_amount = token_1_amount;
-            user_v[i].token_2_amount = token_2_amount;
-            break
-        }
-    }  
+    // The entry of the matching `user` and `pool_id` is loaded from `entries()`.
+    let mut entry = entries()
+        .may_load(deps.storage, (&user, pool_id))?
+        .ok_or_else(|| ContractError::EntryNotExists { pool_id: pool_id.to_string() })?;
+    entry.lp_token_amount = lp_token_amount;
+    entry.token_1_amount = token_1_amount;
+    entry.token_2_amount = token_2_amount;
 
-    LIST.save(deps.storage, &user, &user_v)?;
-    // The function saves the updated entry to the `LIST` with the matching `pool_id` and returns a `Response` 
-    // with the relevant attributes.
+    entries().save(deps.storage, (&user, pool_id), &entry)?;
+    // The function saves the updated entry and returns a `Response` with the relevant attributes.
     Ok(Response::new().add_attribute("method", "execute_update_entry")
                       .add_attribute("updated_pool_id", pool_id.to_string()))
 }
@@ -145,25 +165,17 @@ pub fn execute_delete_entry(deps: DepsMut, info: MessageInfo, user: Addr, pool_i
         // If not, it returns an error and the deletion fails to be performed.
         return Err(ContractError::Unauthorized {});
     }
-    // The entry with the matching `pool_id` is removed from the `POOLS`.
-    let mut user_v = LIST.load(deps.storage, &user)?;
-
-    if user_v.iter().find(|e| e.pool_id == pool_id).is_none() {
+    // The entry with the matching `user` and `pool_id` is removed from `entries()`.
+    if entries().may_load(deps.storage, (&user, pool_id))?.is_none() {
         return Err(ContractError::EntryNotExists { pool_id: pool_id.to_string() });
     }
-
-    for i in 0..user_v.len(){
-        if user_v[i].pool_id == pool_id {
-            user_v.remove(i);
-            break;
-        }
-    }
-    LIST.save(deps.storage, &user, &user_v)?;
+    entries().remove(deps.storage, (&user, pool_id))?;
     // The function returns a `Response` with the relevant attributes.
     Ok(Response::new().add_attribute("method", "execute_delete_entry")
                       .add_attribute("deleted_pool_id", pool_id.to_string()))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_create_new_pool(deps: DepsMut, info: MessageInfo, pool_id: u64, token_1: TokenInfo, token_2: TokenInfo, apr: AnnualInfo, apy: AnnualInfo, tvl: Decimal, converted_tvl: Decimal, reward_coin: Vec<Addr>) -> Result<Response, ContractError> {
     // Before creating the new pool, the function checks if the message sender is 
     // the owner of the contract.
@@ -202,7 +214,7 @@ pub fn execute_update_pool(deps: DepsMut, info: MessageInfo, pool_id: u64, apr:
         return Err(ContractError::Unauthorized {});
     }
 
-    if POOLS.has(deps.storage, pool_id) == false {
+    if !POOLS.has(deps.storage, pool_id) {
         return Err(ContractError::PoolNotExists { pool_id: pool_id.to_string() });
     }
     let pool = POOLS.load(deps.storage, pool_id)?;
@@ -232,7 +244,7 @@ pub fn execute_remove_pool(deps: DepsMut, info: MessageInfo, pool_id: u64) -> Re
         // If not, it returns an error and the deletion fails to be performed.
         return Err(ContractError::Unauthorized {});
     }
-    if POOLS.has(deps.storage, pool_id) == false {
+    if !POOLS.has(deps.storage, pool_id) {
         // If no such pool, it returns an error and the deletion fails to be performed.
         return Err(ContractError::PoolNotExists { pool_id: pool_id.to_string() });
     }
@@ -249,15 +261,16 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
         QueryMsg::QueryAllPools {} => to_binary(&query_all_pools(deps)?).map_err(ContractError::from),
         QueryMsg::QueryUserEntries {user} => to_binary(&query_user_entries(deps, user)?).map_err(ContractError::from),
         QueryMsg::QueryAllEntries {} => to_binary(&query_all_entries(deps)?).map_err(ContractError::from),
+        QueryMsg::QueryPoolEntries {pool_id, start_after, limit} => to_binary(&query_pool_entries(deps, pool_id, start_after, limit)?).map_err(ContractError::from),
     }
 }
 
 fn query_user_entries(deps: Deps, user: Addr) -> Result<ListResponseEntry, ContractError> {
-    // The entries with the matching `user` address are loaded from the `LIST`.
-    if LIST.has(deps.storage, &user) == false {
+    // The entries with the matching `user` address are loaded from `entries()`, ordered by pool.
+    if !USERS.has(deps.storage, &user) {
         return Err(ContractError::UserNotExists { user: user.to_string() });
     }
-    let user_v = LIST.load(deps.storage, &user)?;
+    let user_v = load_user_entries(deps, &user)?;
     if user_v.is_empty() {
         return Err(ContractError::UserNoEntries { user: user.to_string() });
     }
@@ -269,13 +282,21 @@ fn query_user_entries(deps: Deps, user: Addr) -> Result<ListResponseEntry, Contr
     // Example of output:    {"data":{"entries":[{"id":1,"pool_id":"102","amount":"30100"}, {"id":2,"pool_id":"100","amount":"1500"}]}}
 }
 
+fn load_user_entries(deps: Deps, user: &Addr) -> StdResult<Vec<Entry>> {
+    entries()
+        .prefix(user)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, entry)| entry))
+        .collect()
+}
+
 fn query_all_entries(deps: Deps) -> StdResult<AllEntriesResponse> {
     let mut entries = vec![];
-    let keys = LIST.keys(deps.storage, None, None, Order::Ascending);
+    let keys = USERS.keys(deps.storage, None, None, Order::Ascending);
     
     for key in keys {
-        let addr: Addr = key.unwrap();
-        let value = LIST.load(deps.storage, &addr)?;
+        let addr: Addr = key?;
+        let value = load_user_entries(deps, &addr)?;
         entries.push((addr, value));
     }
 
@@ -287,6 +308,21 @@ fn query_all_entries(deps: Deps) -> StdResult<AllEntriesResponse> {
     // Example of output:    {"data":{"user_1":{"entries":[{"id":1,"pool_id":"102","amount":"30100"}, {"id":2,"pool_id":"100","amount":"1500"}]}}}
 }
 
+fn query_pool_entries(deps: Deps, pool_id: u64, start_after: Option<Addr>, limit: Option<u32>) -> StdResult<PoolEntriesResponse> {
+    // The entries in `pool_id` are read from the `pool` index, in the storage order of their users.
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|user| Bound::exclusive((user, pool_id)));
+    let entries = entries()
+        .idx
+        .pool
+        .prefix(pool_id)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|((user, _), entry)| (user, entry)))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(PoolEntriesResponse { entries })
+}
+
 fn query_all_pools(deps: Deps) -> StdResult<ListResponsePool> {
     // All available pools from storage loaded.
     let mut pools = vec![];
@@ -302,3 +338,290 @@ fn query_all_pools(deps: Deps) -> StdResult<ListResponsePool> {
     };
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::from_binary;
+    use cw2::ContractVersion;
+
+    use crate::multitest::{mock_entry, mock_pool};
+
+    const OWNER: &str = "owner";
+    const ALICE: &str = "alice";
+
+    fn do_instantiate(deps: DepsMut, owner: Option<&str>) -> Response {
+        let msg = InstantiateMsg {
+            owner: owner.map(String::from),
+        };
+        instantiate(deps, mock_env(), mock_info(OWNER, &[]), msg).unwrap()
+    }
+
+    fn new_entry(deps: DepsMut, sender: &str, pool_id: u64, amount: u64) -> Result<Response, ContractError> {
+        let entry = mock_entry(pool_id, amount);
+        let msg = ExecuteMsg::NewEntry {
+            user: Addr::unchecked(ALICE),
+            pool_id,
+            lp_token_amount: entry.lp_token_amount,
+            token_1_amount: entry.token_1_amount,
+            token_2_amount: entry.token_2_amount,
+            pool_addr: entry.pool_addr,
+        };
+        execute(deps, mock_env(), mock_info(sender, &[]), msg)
+    }
+
+    fn new_pool(deps: DepsMut, sender: &str, pool: Pool) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::NewPool {
+            pool_id: pool.pool_id,
+            token_1: pool.token_1,
+            token_2: pool.token_2,
+            apr: pool.apr,
+            apy: pool.apy,
+            tvl: pool.tvl,
+            converted_tvl: pool.converted_tvl,
+            reward_coin: pool.reward_coin,
+        };
+        execute(deps, mock_env(), mock_info(sender, &[]), msg)
+    }
+
+    fn user_entries(deps: Deps, user: &str) -> Result<Vec<Entry>, ContractError> {
+        let msg = QueryMsg::QueryUserEntries { user: Addr::unchecked(user) };
+        let res: ListResponseEntry = from_binary(&query(deps, mock_env(), msg)?)?;
+        Ok(res.entries)
+    }
+
+    #[test]
+    fn instantiate_sets_the_owner() {
+        let mut deps = mock_dependencies();
+        let res = do_instantiate(deps.as_mut(), None);
+        assert_eq!(res.attributes[1], ("owner", OWNER));
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().owner, OWNER);
+        assert_eq!(ENTRY_SEQ.load(&deps.storage).unwrap(), 0);
+
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut(), Some("admin"));
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().owner, "admin");
+
+        // an invalid owner falls back to the sender
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut(), Some("a"));
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().owner, OWNER);
+    }
+
+    #[test]
+    fn new_entry_numbers_entries() {
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut(), None);
+
+        let res = new_entry(deps.as_mut(), OWNER, 1, 100).unwrap();
+        assert_eq!(res.attributes[1], ("new_entry_id", "1"));
+        let res = new_entry(deps.as_mut(), OWNER, 2, 50).unwrap();
+        assert_eq!(res.attributes[1], ("new_entry_id", "2"));
+
+        let entries = user_entries(deps.as_ref(), ALICE).unwrap();
+        assert_eq!(entries.iter().map(|e| (e.id, e.pool_id)).collect::<Vec<_>>(), vec![(1, 1), (2, 2)]);
+        assert_eq!(entries[0].lp_token_amount, Decimal::from_ratio(100u64, 1u64));
+
+        let err = new_entry(deps.as_mut(), OWNER, 1, 10).unwrap_err();
+        assert_eq!(err, ContractError::UserEntryDuplicate { pool_id: "1".to_string() });
+        let err = new_entry(deps.as_mut(), ALICE, 3, 10).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn update_and_delete_entry() {
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut(), None);
+        new_entry(deps.as_mut(), OWNER, 1, 100).unwrap();
+        new_entry(deps.as_mut(), OWNER, 2, 50).unwrap();
+
+        let update = |pool_id| ExecuteMsg::UpdateEntry {
+            user: Addr::unchecked(ALICE),
+            pool_id,
+            lp_token_amount: Decimal::percent(150),
+            token_1_amount: Decimal::percent(250),
+            token_2_amount: Decimal::percent(350),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), update(2)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), update(3)).unwrap_err();
+        assert_eq!(err, ContractError::EntryNotExists { pool_id: "3".to_string() });
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), update(2)).unwrap();
+
+        let entries = user_entries(deps.as_ref(), ALICE).unwrap();
+        assert_eq!(entries[0], Entry { id: 1, ..mock_entry(1, 100) });
+        assert_eq!(entries[1].id, 2);
+        assert_eq!(entries[1].lp_token_amount, Decimal::percent(150));
+        assert_eq!(entries[1].token_1_amount, Decimal::percent(250));
+        assert_eq!(entries[1].token_2_amount, Decimal::percent(350));
+
+        let delete = |pool_id| ExecuteMsg::DeleteEntry { user: Addr::unchecked(ALICE), pool_id };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), delete(1)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), delete(3)).unwrap_err();
+        assert_eq!(err, ContractError::EntryNotExists { pool_id: "3".to_string() });
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), delete(1)).unwrap();
+        assert_eq!(res.attributes[1], ("deleted_pool_id", "1"));
+        let entries = user_entries(deps.as_ref(), ALICE).unwrap();
+        assert_eq!(entries.iter().map(|e| e.pool_id).collect::<Vec<_>>(), vec![2]);
+
+        // users never given an entry have none to delete
+        let msg = ExecuteMsg::DeleteEntry { user: Addr::unchecked("bob"), pool_id: 1 };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::EntryNotExists { pool_id: "1".to_string() });
+    }
+
+    #[test]
+    fn pools_crud() {
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut(), None);
+
+        let err = new_pool(deps.as_mut(), ALICE, mock_pool(1)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        new_pool(deps.as_mut(), OWNER, mock_pool(2)).unwrap();
+        new_pool(deps.as_mut(), OWNER, mock_pool(1)).unwrap();
+
+        let update = |pool_id| ExecuteMsg::UpdatePool {
+            pool_id,
+            apr: mock_pool(pool_id).apy,
+            apy: mock_pool(pool_id).apr,
+            tvl: Decimal::one(),
+            converted_tvl: Decimal::percent(200),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), update(1)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), update(3)).unwrap_err();
+        assert_eq!(err, ContractError::PoolNotExists { pool_id: "3".to_string() });
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), update(1)).unwrap();
+
+        let res: ListResponsePool = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::QueryAllPools {}).unwrap()).unwrap();
+        assert_eq!(res.pools.iter().map(|p| p.pool_id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(res.pools[0].tvl, Decimal::one());
+        assert_eq!(res.pools[0].converted_tvl, Decimal::percent(200));
+        assert_eq!(res.pools[1], mock_pool(2));
+
+        let remove = |pool_id| ExecuteMsg::RemovePool { pool_id };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), remove(1)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), remove(3)).unwrap_err();
+        assert_eq!(err, ContractError::PoolNotExists { pool_id: "3".to_string() });
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), remove(1)).unwrap();
+        assert!(!POOLS.has(&deps.storage, 1));
+    }
+
+    #[test]
+    fn query_entries() {
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut(), None);
+
+        let err = user_entries(deps.as_ref(), ALICE).unwrap_err();
+        assert_eq!(err, ContractError::UserNotExists { user: ALICE.to_string() });
+
+        new_entry(deps.as_mut(), OWNER, 1, 100).unwrap();
+        let bob = ExecuteMsg::NewEntry {
+            user: Addr::unchecked("bob"),
+            pool_id: 1,
+            lp_token_amount: Decimal::one(),
+            token_1_amount: Decimal::one(),
+            token_2_amount: Decimal::one(),
+            pool_addr: "pool1".to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), bob).unwrap();
+
+        let res: AllEntriesResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::QueryAllEntries {}).unwrap()).unwrap();
+        let users: Vec<_> = res.entries.iter().map(|(user, entries)| (user.as_str(), entries.len())).collect();
+        assert_eq!(users, vec![(ALICE, 1), ("bob", 1)]);
+
+        let msg = ExecuteMsg::DeleteEntry { user: Addr::unchecked(ALICE), pool_id: 1 };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        let err = user_entries(deps.as_ref(), ALICE).unwrap_err();
+        assert_eq!(err, ContractError::UserNoEntries { user: ALICE.to_string() });
+    }
+
+    fn pool_entries(deps: Deps, pool_id: u64, start_after: Option<&str>, limit: Option<u32>) -> Vec<(String, u64)> {
+        let msg = QueryMsg::QueryPoolEntries { pool_id, start_after: start_after.map(Addr::unchecked), limit };
+        let res: PoolEntriesResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        res.entries.into_iter().map(|(user, entry)| (user.to_string(), entry.id)).collect()
+    }
+
+    #[test]
+    fn query_pool_entries_pages_through_a_pool() {
+        let mut deps = mock_dependencies();
+        do_instantiate(deps.as_mut(), None);
+        for (id, user) in ["carol", "alice", "dave", "bob"].iter().enumerate() {
+            let msg = ExecuteMsg::NewEntry {
+                user: Addr::unchecked(*user),
+                pool_id: 678,
+                lp_token_amount: Decimal::one(),
+                token_1_amount: Decimal::one(),
+                token_2_amount: Decimal::one(),
+                pool_addr: "pool678".to_string(),
+            };
+            execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+            // another pool, left out of the results
+            if id % 2 == 0 {
+                let msg = ExecuteMsg::NewEntry {
+                    user: Addr::unchecked(*user),
+                    pool_id: 1,
+                    lp_token_amount: Decimal::one(),
+                    token_1_amount: Decimal::one(),
+                    token_2_amount: Decimal::one(),
+                    pool_addr: "pool1".to_string(),
+                };
+                execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+            }
+        }
+
+        // users come in the order of their storage keys, shorter addresses first
+        let all = vec![("bob".to_string(), 6), ("dave".to_string(), 4), ("alice".to_string(), 3), ("carol".to_string(), 1)];
+        assert_eq!(pool_entries(deps.as_ref(), 678, None, None), all);
+        assert_eq!(pool_entries(deps.as_ref(), 678, None, Some(2)), all[..2]);
+        assert_eq!(pool_entries(deps.as_ref(), 678, Some("dave"), Some(2)), all[2..]);
+        assert_eq!(pool_entries(deps.as_ref(), 678, Some("carol"), None), vec![]);
+        assert_eq!(pool_entries(deps.as_ref(), 1, None, None), vec![("dave".to_string(), 5), ("carol".to_string(), 2)]);
+
+        // the index follows deletions
+        let msg = ExecuteMsg::DeleteEntry { user: Addr::unchecked("alice"), pool_id: 678 };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        assert_eq!(pool_entries(deps.as_ref(), 678, Some("dave"), None), all[3..]);
+        assert_eq!(pool_entries(deps.as_ref(), 2, None, None), vec![]);
+    }
+
+    #[test]
+    fn migrate_moves_entries_out_of_the_list() {
+        let mut deps = mock_dependencies();
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.2.0").unwrap();
+        let alice = Addr::unchecked(ALICE);
+        let bob = Addr::unchecked("bob");
+        let alice_v = vec![Entry { id: 2, ..mock_entry(5, 10) }, Entry { id: 1, ..mock_entry(3, 20) }];
+        LIST.save(deps.as_mut().storage, &alice, &alice_v).unwrap();
+        LIST.save(deps.as_mut().storage, &bob, &vec![]).unwrap();
+
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(res.attributes[2], ("migrated_entries", "2"));
+        assert_eq!(
+            get_contract_version(&deps.storage).unwrap(),
+            ContractVersion { contract: CONTRACT_NAME.to_string(), version: CONTRACT_VERSION.to_string() }
+        );
+        assert_eq!(LIST.keys(&deps.storage, None, None, Order::Ascending).count(), 0);
+
+        // ordered by pool now
+        let entries = user_entries(deps.as_ref(), ALICE).unwrap();
+        assert_eq!(entries, vec![alice_v[1].clone(), alice_v[0].clone()]);
+        let err = user_entries(deps.as_ref(), "bob").unwrap_err();
+        assert_eq!(err, ContractError::UserNoEntries { user: "bob".to_string() });
+        assert_eq!(pool_entries(deps.as_ref(), 5, None, None), vec![(ALICE.to_string(), 2)]);
+
+        // migrating again changes nothing
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(res.attributes[2], ("migrated_entries", "0"));
+        assert_eq!(user_entries(deps.as_ref(), ALICE).unwrap().len(), 2);
+
+        let mut deps = mock_dependencies();
+        set_contract_version(deps.as_mut().storage, "crates.io:cw20-base", "0.15.1").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+        assert_eq!(err, ContractError::CannotMigrate { contract: "crates.io:cw20-base".to_string() });
+    }
+}
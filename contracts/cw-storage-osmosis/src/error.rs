@@ -1,7 +1,7 @@
 use cosmwasm_std::StdError;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
@@ -24,6 +24,9 @@ pub enum ContractError {
     #[error("User does not have any entries (user: {user})")]
     UserNoEntries { user: String },
 
+    #[error("Cannot migrate from a different contract (contract: {contract})")]
+    CannotMigrate { contract: String },
+
     #[error("Custom Error val: {val:?}")]
     CustomError { val: String },
     // Add any other custom errors you like here.
@@ -1,6 +1,8 @@
 pub mod contract;
 mod error;
 pub mod msg;
+#[cfg(any(test, feature = "multitest"))]
+pub mod multitest;
 pub mod state;
 
 pub use crate::error::ContractError;
@@ -8,6 +8,9 @@ pub struct InstantiateMsg {
     pub owner: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
@@ -25,6 +28,8 @@ pub enum QueryMsg {
     QueryAllPools {},
     QueryUserEntries {user: Addr},
     QueryAllEntries {},
+    // Entries in `pool_id` with their user, `limit` of them after the entry of `start_after`
+    QueryPoolEntries {pool_id: u64, start_after: Option<Addr>, limit: Option<u32>},
 }
 
 // A custom struct is defined for each query response
@@ -46,4 +51,9 @@ pub struct SpecificPoolResponse {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AllEntriesResponse {
     pub entries: Vec<(Addr, Vec<Entry>)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PoolEntriesResponse {
+    pub entries: Vec<(Addr, Entry)>,
 }
\ No newline at end of file
@@ -0,0 +1,469 @@
+//! Multi-test harness for the registry. `SuiteBuilder` instantiates it with seeded pools
+//! and entries, for its own tests and, with the `multitest` feature, those of the
+//! contracts that read it.
+
+use cosmwasm_std::{Addr, Decimal, Empty, StdResult};
+use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
+
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::msg::{
+    AllEntriesResponse, ExecuteMsg, InstantiateMsg, ListResponseEntry, ListResponsePool,
+    PoolEntriesResponse, QueryMsg,
+};
+use crate::state::{AnnualInfo, Entry, Pool, TokenInfo};
+use crate::ContractError;
+
+pub fn contract_registry() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(execute, instantiate, query).with_migrate(migrate);
+    Box::new(contract)
+}
+
+/// An uatom/uosmo pool with `pool_id`, rewarding in no coin
+pub fn mock_pool(pool_id: u64) -> Pool {
+    let annual = AnnualInfo {
+        one_day: Decimal::percent(1),
+        one_week: Decimal::percent(7),
+        two_week: Decimal::percent(14),
+    };
+    Pool {
+        pool_id,
+        token_1: mock_token("ATOM", "uatom"),
+        token_2: mock_token("OSMO", "uosmo"),
+        apr: annual.clone(),
+        apy: annual,
+        tvl: Decimal::percent(100_000),
+        converted_tvl: Decimal::percent(200_000),
+        reward_coin: vec![],
+    }
+}
+
+fn mock_token(symbol: &str, denom: &str) -> TokenInfo {
+    TokenInfo {
+        symbol: symbol.to_string(),
+        denom: denom.to_string(),
+        icon_url: format!("https://example.com/{}.svg", denom),
+    }
+}
+
+/// An entry of `amount` LP tokens in `pool_id`, backed by as many of each token. Its `id`
+/// is left at 0, the registry numbers entries in the order they are created.
+pub fn mock_entry(pool_id: u64, amount: u64) -> Entry {
+    let amount = Decimal::from_ratio(amount, 1u64);
+    Entry {
+        id: 0,
+        pool_id,
+        lp_token_amount: amount,
+        token_1_amount: amount,
+        token_2_amount: amount,
+        pool_addr: format!("pool{}", pool_id),
+    }
+}
+
+pub struct SuiteBuilder {
+    owner: String,
+    pools: Vec<Pool>,
+    entries: Vec<(String, Entry)>,
+}
+
+impl Default for SuiteBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SuiteBuilder {
+    pub fn new() -> Self {
+        SuiteBuilder {
+            owner: "owner".to_string(),
+            pools: vec![],
+            entries: vec![],
+        }
+    }
+
+    pub fn with_owner(mut self, owner: &str) -> Self {
+        self.owner = owner.to_string();
+        self
+    }
+
+    pub fn with_pool(mut self, pool: Pool) -> Self {
+        self.pools.push(pool);
+        self
+    }
+
+    /// Seeds an entry of `user`, see `mock_entry` for how ids are given
+    pub fn with_entry(mut self, user: &str, entry: Entry) -> Self {
+        self.entries.push((user.to_string(), entry));
+        self
+    }
+
+    /// Instantiates the registry in `app` and seeds it, as its owner, who is also its admin.
+    /// Panics if the seeds are rejected, e.g. two entries of a user in the same pool.
+    pub fn instantiate(self, app: &mut App) -> Addr {
+        let owner = Addr::unchecked(&self.owner);
+        let code_id = app.store_code(contract_registry());
+        let registry = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg { owner: None },
+                &[],
+                "registry",
+                Some(self.owner.clone()),
+            )
+            .unwrap();
+
+        for pool in self.pools {
+            app.execute_contract(owner.clone(), registry.clone(), &new_pool_msg(pool), &[])
+                .unwrap();
+        }
+        for (user, entry) in self.entries {
+            let msg = new_entry_msg(Addr::unchecked(user), entry);
+            app.execute_contract(owner.clone(), registry.clone(), &msg, &[])
+                .unwrap();
+        }
+        registry
+    }
+
+    pub fn build(self) -> Suite {
+        let mut app = App::default();
+        let owner = Addr::unchecked(&self.owner);
+        let registry = self.instantiate(&mut app);
+        Suite {
+            app,
+            owner,
+            registry,
+        }
+    }
+}
+
+fn new_pool_msg(pool: Pool) -> ExecuteMsg {
+    ExecuteMsg::NewPool {
+        pool_id: pool.pool_id,
+        token_1: pool.token_1,
+        token_2: pool.token_2,
+        apr: pool.apr,
+        apy: pool.apy,
+        tvl: pool.tvl,
+        converted_tvl: pool.converted_tvl,
+        reward_coin: pool.reward_coin,
+    }
+}
+
+fn new_entry_msg(user: Addr, entry: Entry) -> ExecuteMsg {
+    ExecuteMsg::NewEntry {
+        user,
+        pool_id: entry.pool_id,
+        lp_token_amount: entry.lp_token_amount,
+        token_1_amount: entry.token_1_amount,
+        token_2_amount: entry.token_2_amount,
+        pool_addr: entry.pool_addr,
+    }
+}
+
+pub struct Suite {
+    pub app: App,
+    pub owner: Addr,
+    pub registry: Addr,
+}
+
+impl Suite {
+    fn execute(&mut self, sender: &str, msg: ExecuteMsg) -> Result<AppResponse, ContractError> {
+        self.app
+            .execute_contract(Addr::unchecked(sender), self.registry.clone(), &msg, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    pub fn new_entry(
+        &mut self,
+        sender: &str,
+        user: &str,
+        entry: Entry,
+    ) -> Result<AppResponse, ContractError> {
+        self.execute(sender, new_entry_msg(Addr::unchecked(user), entry))
+    }
+
+    /// Sets the amounts of the entry of `user` in `entry.pool_id` to those of `entry`
+    pub fn update_entry(
+        &mut self,
+        sender: &str,
+        user: &str,
+        entry: Entry,
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::UpdateEntry {
+            user: Addr::unchecked(user),
+            pool_id: entry.pool_id,
+            lp_token_amount: entry.lp_token_amount,
+            token_1_amount: entry.token_1_amount,
+            token_2_amount: entry.token_2_amount,
+        };
+        self.execute(sender, msg)
+    }
+
+    pub fn delete_entry(
+        &mut self,
+        sender: &str,
+        user: &str,
+        pool_id: u64,
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::DeleteEntry {
+            user: Addr::unchecked(user),
+            pool_id,
+        };
+        self.execute(sender, msg)
+    }
+
+    pub fn new_pool(&mut self, sender: &str, pool: Pool) -> Result<AppResponse, ContractError> {
+        self.execute(sender, new_pool_msg(pool))
+    }
+
+    /// Sets the yields and TVL of `pool.pool_id` to those of `pool`
+    pub fn update_pool(&mut self, sender: &str, pool: Pool) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::UpdatePool {
+            pool_id: pool.pool_id,
+            apr: pool.apr,
+            apy: pool.apy,
+            tvl: pool.tvl,
+            converted_tvl: pool.converted_tvl,
+        };
+        self.execute(sender, msg)
+    }
+
+    pub fn remove_pool(
+        &mut self,
+        sender: &str,
+        pool_id: u64,
+    ) -> Result<AppResponse, ContractError> {
+        self.execute(sender, ExecuteMsg::RemovePool { pool_id })
+    }
+
+    pub fn query_all_pools(&self) -> StdResult<Vec<Pool>> {
+        let res: ListResponsePool = self
+            .app
+            .wrap()
+            .query_wasm_smart(&self.registry, &QueryMsg::QueryAllPools {})?;
+        Ok(res.pools)
+    }
+
+    pub fn query_user_entries(&self, user: &str) -> StdResult<Vec<Entry>> {
+        let msg = QueryMsg::QueryUserEntries {
+            user: Addr::unchecked(user),
+        };
+        let res: ListResponseEntry = self.app.wrap().query_wasm_smart(&self.registry, &msg)?;
+        Ok(res.entries)
+    }
+
+    pub fn query_all_entries(&self) -> StdResult<Vec<(Addr, Vec<Entry>)>> {
+        let res: AllEntriesResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(&self.registry, &QueryMsg::QueryAllEntries {})?;
+        Ok(res.entries)
+    }
+
+    pub fn query_pool_entries(
+        &self,
+        pool_id: u64,
+        start_after: Option<&str>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<(Addr, Entry)>> {
+        let msg = QueryMsg::QueryPoolEntries {
+            pool_id,
+            start_after: start_after.map(Addr::unchecked),
+            limit,
+        };
+        let res: PoolEntriesResponse = self.app.wrap().query_wasm_smart(&self.registry, &msg)?;
+        Ok(res.entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::msg::MigrateMsg;
+
+    const OWNER: &str = "owner";
+    const ALICE: &str = "alice";
+    const BOB: &str = "bob";
+
+    fn with_id(id: u64, entry: Entry) -> Entry {
+        Entry { id, ..entry }
+    }
+
+    #[test]
+    fn builder_seeds_pools_and_entries() {
+        let suite = SuiteBuilder::new()
+            .with_pool(mock_pool(1))
+            .with_pool(mock_pool(678))
+            .with_entry(ALICE, mock_entry(1, 100))
+            .with_entry(BOB, mock_entry(678, 50))
+            .with_entry(ALICE, mock_entry(678, 10))
+            .build();
+
+        assert_eq!(
+            suite.query_all_pools().unwrap(),
+            vec![mock_pool(1), mock_pool(678)]
+        );
+        assert_eq!(
+            suite.query_user_entries(ALICE).unwrap(),
+            vec![
+                with_id(1, mock_entry(1, 100)),
+                with_id(3, mock_entry(678, 10))
+            ]
+        );
+        assert_eq!(
+            suite.query_all_entries().unwrap(),
+            vec![
+                (
+                    Addr::unchecked(ALICE),
+                    vec![
+                        with_id(1, mock_entry(1, 100)),
+                        with_id(3, mock_entry(678, 10))
+                    ]
+                ),
+                (Addr::unchecked(BOB), vec![with_id(2, mock_entry(678, 50))]),
+            ]
+        );
+        assert_eq!(
+            suite.query_pool_entries(678, None, None).unwrap(),
+            vec![
+                (Addr::unchecked(BOB), with_id(2, mock_entry(678, 50))),
+                (Addr::unchecked(ALICE), with_id(3, mock_entry(678, 10))),
+            ]
+        );
+    }
+
+    #[test]
+    fn builder_instantiates_in_an_existing_app() {
+        let mut app = App::default();
+        let registry = SuiteBuilder::new()
+            .with_owner("admin")
+            .with_pool(mock_pool(2))
+            .instantiate(&mut app);
+
+        let res: ListResponsePool = app
+            .wrap()
+            .query_wasm_smart(&registry, &QueryMsg::QueryAllPools {})
+            .unwrap();
+        assert_eq!(res.pools, vec![mock_pool(2)]);
+    }
+
+    #[test]
+    fn migrate_keeps_entries() {
+        let mut suite = SuiteBuilder::new()
+            .with_entry(ALICE, mock_entry(1, 100))
+            .build();
+
+        let code_id = suite.app.store_code(contract_registry());
+        suite
+            .app
+            .migrate_contract(
+                suite.owner.clone(),
+                suite.registry.clone(),
+                &MigrateMsg {},
+                code_id,
+            )
+            .unwrap();
+        assert_eq!(
+            suite.query_user_entries(ALICE).unwrap(),
+            vec![with_id(1, mock_entry(1, 100))]
+        );
+    }
+
+    #[test]
+    fn entries_lifecycle() {
+        let mut suite = SuiteBuilder::new().with_pool(mock_pool(1)).build();
+
+        suite.new_entry(OWNER, ALICE, mock_entry(1, 100)).unwrap();
+        let err = suite.new_entry(OWNER, ALICE, mock_entry(1, 5)).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::UserEntryDuplicate {
+                pool_id: "1".to_string()
+            }
+        );
+        let err = suite.new_entry(ALICE, ALICE, mock_entry(2, 5)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        suite.update_entry(OWNER, ALICE, mock_entry(1, 40)).unwrap();
+        assert_eq!(
+            suite.query_user_entries(ALICE).unwrap(),
+            vec![with_id(1, mock_entry(1, 40))]
+        );
+        let err = suite
+            .update_entry(OWNER, ALICE, mock_entry(2, 40))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::EntryNotExists {
+                pool_id: "2".to_string()
+            }
+        );
+        let err = suite
+            .update_entry(BOB, ALICE, mock_entry(1, 40))
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let err = suite.delete_entry(BOB, ALICE, 1).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        suite.delete_entry(OWNER, ALICE, 1).unwrap();
+        let err = suite.delete_entry(OWNER, ALICE, 1).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::EntryNotExists {
+                pool_id: "1".to_string()
+            }
+        );
+
+        // alice is still known, without entries
+        let err = suite.query_user_entries(ALICE).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("User does not have any entries (user: alice)"));
+        let err = suite.query_user_entries(BOB).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("User does not exist in storage (user: bob)"));
+    }
+
+    #[test]
+    fn pools_lifecycle() {
+        let mut suite = SuiteBuilder::new().build();
+        assert_eq!(suite.query_all_pools().unwrap(), vec![]);
+
+        let err = suite.new_pool(ALICE, mock_pool(1)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        suite.new_pool(OWNER, mock_pool(1)).unwrap();
+
+        let mut updated = mock_pool(1);
+        updated.tvl = Decimal::percent(5);
+        // tokens and rewards are set once, when the pool is created
+        updated.token_1 = mock_token("ION", "uion");
+        let err = suite.update_pool(ALICE, updated.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        suite.update_pool(OWNER, updated).unwrap();
+        let pools = suite.query_all_pools().unwrap();
+        assert_eq!(pools[0].tvl, Decimal::percent(5));
+        assert_eq!(pools[0].token_1, mock_pool(1).token_1);
+
+        let err = suite.update_pool(OWNER, mock_pool(2)).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::PoolNotExists {
+                pool_id: "2".to_string()
+            }
+        );
+
+        let err = suite.remove_pool(ALICE, 1).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        suite.remove_pool(OWNER, 1).unwrap();
+        let err = suite.remove_pool(OWNER, 1).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::PoolNotExists {
+                pool_id: "1".to_string()
+            }
+        );
+        assert_eq!(suite.query_all_pools().unwrap(), vec![]);
+    }
+}
@@ -1,8 +1,8 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Decimal};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Decimal, Empty};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -54,4 +54,27 @@ pub struct Pool {
 
 pub const POOLS: Map<u64, Pool> = Map::new("pools");
 pub const ENTRY_SEQ: Item<u64> = Item::new("entry_seq");
-pub const LIST: Map<&Addr, Vec<Entry>> = Map::new("list");
\ No newline at end of file
+// Entries of all users, before 0.3.0. `migrate` moves them to `entries()`.
+pub const LIST: Map<&Addr, Vec<Entry>> = Map::new("list");
+// Every user that was given an entry, including those whose entries were all deleted.
+pub const USERS: Map<&Addr, Empty> = Map::new("users");
+
+pub struct EntryIndexes<'a> {
+    pub pool: MultiIndex<'a, u64, Entry, (Addr, u64)>,
+}
+
+impl<'a> IndexList<Entry> for EntryIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Entry>> + '_> {
+        let v: Vec<&dyn Index<Entry>> = vec![&self.pool];
+        Box::new(v.into_iter())
+    }
+}
+
+// Entries keyed by user and pool, a user has at most one entry in each pool.
+// The `pool` index lists the users in a pool.
+pub fn entries<'a>() -> IndexedMap<'a, (&'a Addr, u64), Entry, EntryIndexes<'a>> {
+    let indexes = EntryIndexes {
+        pool: MultiIndex::new(|entry| entry.pool_id, "entries", "entries__pool"),
+    };
+    IndexedMap::new("entries", indexes)
+}
\ No newline at end of file